@@ -0,0 +1,493 @@
+//! A terminal dashboard with the live telemetry of the firmware.
+//!
+//! The firmware is driven from a worker thread, so the screen keeps
+//! refreshing while an io operation blocks or the port reconnects.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::error;
+use std::io::ErrorKind;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, Paragraph, Row, Sparkline, Table};
+use ratatui::{DefaultTerminal, Frame};
+
+use xmaxx_host::*;
+use xmaxx_messages::*;
+
+use crate::RESEND;
+
+/// The number of sensor readings kept for the sparklines.
+const HISTORY: usize = 256;
+/// The number of logs listed.
+const LOGS: usize = 8;
+/// How often the screen is redrawn.
+const REFRESH: Duration = Duration::from_millis(50);
+
+/// The change of speed of the arrow keys.
+const RPM_STEP: CentiRpm = CentiRpm(100 * 100);
+/// The change of steering of the arrow keys.
+const STEERING_STEP: CentiDegrees = CentiDegrees(5 * 100);
+/// The steering angle to go straight.
+const STEERING_ZERO: CentiDegrees = CentiDegrees(90 * 100);
+/// The range of the steering angle.
+const STEERING_MIN: CentiDegrees = CentiDegrees(40 * 100);
+const STEERING_MAX: CentiDegrees = CentiDegrees(140 * 100);
+
+/// What the worker thread tells the dashboard.
+enum Update {
+    Info(Info),
+    Event(ConnectionEvent),
+    Link(ConnectionState, RecvStats),
+    Error(String),
+}
+
+/// Whether the dashboard may drive the Xmaxx.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// The commands are not sent, the Xmaxx was told to stop.
+    Disarmed,
+    /// The arrow keys drive the Xmaxx.
+    Armed,
+    /// Stopped by the emergency stop, it must be armed again to drive.
+    Stopped,
+}
+
+/// The state of the dashboard.
+struct Dashboard {
+    port: String,
+    start: Instant,
+    mode: Mode,
+    /// The last command sent.
+    commanded: Drive,
+    commands: Sender<Drive>,
+    /// The speeds of the wheels (RPM): fl, fr, rl, rr.
+    measured: [VecDeque<CentiRpm>; 4],
+    /// The last currents of the motors.
+    currents: Currents,
+    /// The last state of the battery.
+    battery: Battery,
+    /// Who drives, once the firmware told.
+    control: Option<Control>,
+    /// The last logs with the time they were received.
+    logs: VecDeque<(Duration, Log)>,
+    /// The number of each log received.
+    log_counts: BTreeMap<String, u64>,
+    state: ConnectionState,
+    stats: RecvStats,
+    /// The number of frames per second.
+    rate: f64,
+    /// The frames counted at the start of the rate measurement.
+    rate_start: (Instant, u64),
+    /// The last connection event or error.
+    event: Option<String>,
+}
+
+/// Shows the dashboard until `q` is pressed.
+pub fn run(firmware: Firmware) -> Result<(), Box<dyn error::Error>> {
+    let port = firmware.path().unwrap_or_default().to_string();
+    let (commands, commands_rx) = mpsc::channel();
+    let (updates, updates_rx) = mpsc::channel();
+    let worker = thread::spawn(move || work(firmware, commands_rx, updates));
+
+    let mut dashboard = Dashboard::new(port, commands);
+    let mut terminal = ratatui::init();
+    let result = dashboard.show(&mut terminal, &updates_rx);
+    ratatui::restore();
+
+    // leaving disarms, which stops the Xmaxx before the worker exits
+    dashboard.set_mode(Mode::Disarmed);
+    drop(dashboard);
+    drop(updates_rx);
+    worker.join().expect("the worker should not panic");
+
+    result
+}
+
+/// Forwards the commands to the firmware and the messages to the dashboard
+/// until the dashboard is closed.
+fn work(mut firmware: Firmware, commands: Receiver<Drive>, updates: Sender<Update>) {
+    // the dashboard may be gone while the last commands are sent
+    let send = |update| {
+        let _ = updates.send(update);
+    };
+
+    // the last command, sent again so the firmware does not stop the Xmaxx
+    let mut commanded = None;
+    let mut sent: Option<Instant> = None;
+    loop {
+        loop {
+            match commands.try_recv() {
+                Ok(drive) => {
+                    commanded = Some(drive);
+                    sent = None;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        }
+        if let Some(drive) = &commanded {
+            if sent.is_none_or(|sent| sent.elapsed() >= RESEND) {
+                if let Err(err) = firmware.send(&Command::Drive(drive.clone())) {
+                    send(Update::Error(err.to_string()));
+                }
+                sent = Some(Instant::now());
+            }
+        }
+
+        match firmware.recv() {
+            Ok(info) => send(Update::Info(info)),
+            Err(Error::Io(err)) if err.kind() == ErrorKind::TimedOut => {
+                send(Update::Error(err.to_string()))
+            }
+            Err(err @ Error::Timeout) => send(Update::Error(err.to_string())),
+            Err(err) => {
+                send(Update::Error(err.to_string()));
+                // do not spin while the port is gone for good
+                thread::sleep(Duration::from_millis(500));
+            }
+        }
+
+        while let Some(event) = firmware.poll_event() {
+            send(Update::Event(event));
+        }
+        send(Update::Link(firmware.state(), firmware.stats()));
+    }
+}
+
+impl Dashboard {
+    fn new(port: String, commands: Sender<Drive>) -> Self {
+        Self {
+            port,
+            start: Instant::now(),
+            mode: Mode::Disarmed,
+            commanded: Drive {
+                steering: STEERING_ZERO,
+                ..Default::default()
+            },
+            commands,
+            measured: Default::default(),
+            currents: Currents::default(),
+            battery: Battery::default(),
+            control: None,
+            logs: VecDeque::new(),
+            log_counts: BTreeMap::new(),
+            state: ConnectionState::Connected,
+            stats: RecvStats::default(),
+            rate: 0.0,
+            rate_start: (Instant::now(), 0),
+            event: None,
+        }
+    }
+
+    fn show(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        updates: &Receiver<Update>,
+    ) -> Result<(), Box<dyn error::Error>> {
+        loop {
+            for update in updates.try_iter() {
+                self.update(update);
+            }
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if !event::poll(REFRESH)? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                let ctrl_c =
+                    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                    return Ok(());
+                }
+                self.press(key.code);
+            }
+        }
+    }
+
+    fn update(&mut self, update: Update) {
+        match update {
+            Update::Info(Info::Sensors(sensors)) => {
+                let rpms = [
+                    sensors.fl_whl_rpm,
+                    sensors.fr_whl_rpm,
+                    sensors.rl_whl_rpm,
+                    sensors.rr_whl_rpm,
+                ];
+                for (history, rpm) in self.measured.iter_mut().zip(rpms) {
+                    if history.len() == HISTORY {
+                        history.pop_front();
+                    }
+                    history.push_back(rpm);
+                }
+            }
+            Update::Info(Info::Currents(currents)) => self.currents = currents,
+            Update::Info(Info::Battery(battery)) => self.battery = battery,
+            Update::Info(Info::Control(control)) => self.control = Some(control),
+            Update::Info(Info::Log(log)) => {
+                *self.log_counts.entry(format!("{log:?}")).or_default() += 1;
+                if self.logs.len() == LOGS {
+                    self.logs.pop_front();
+                }
+                self.logs.push_back((self.start.elapsed(), log));
+            }
+            Update::Info(
+                Info::Pong(_)
+                | Info::Param(..)
+                | Info::Encoders(_)
+                | Info::Imu(_)
+                | Info::Proximity(_)
+                | Info::Traction(_)
+                | Info::Odometry(_)
+                | Info::Axles(_),
+            ) => {}
+            Update::Event(event) => self.event = Some(event.to_string()),
+            Update::Error(error) => self.event = Some(error),
+            Update::Link(state, stats) => {
+                self.state = state;
+                self.stats = stats;
+
+                let (since, frames) = self.rate_start;
+                let elapsed = since.elapsed();
+                if elapsed >= Duration::from_secs(1) {
+                    self.rate = (stats.frames - frames) as f64 / elapsed.as_secs_f64();
+                    self.rate_start = (Instant::now(), stats.frames);
+                }
+            }
+        }
+    }
+
+    fn press(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Char(' ') => self.set_mode(Mode::Stopped),
+            KeyCode::Char('a') if self.mode == Mode::Armed => self.set_mode(Mode::Disarmed),
+            KeyCode::Char('a') => self.set_mode(Mode::Armed),
+            _ if self.mode != Mode::Armed => {}
+            KeyCode::Up => self.drive(RPM_STEP.0, 0),
+            KeyCode::Down => self.drive(-RPM_STEP.0, 0),
+            KeyCode::Left => self.drive(0, STEERING_STEP.0),
+            KeyCode::Right => self.drive(0, -STEERING_STEP.0),
+            KeyCode::Char('0') => self.drive(-self.commanded.fl_whl_rpm.0, 0),
+            KeyCode::Char('c') => self.drive(0, STEERING_ZERO.0 - self.commanded.steering.0),
+            _ => {}
+        }
+    }
+
+    /// Changes the mode, stopping the Xmaxx unless it is armed.
+    fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        if mode != Mode::Armed {
+            self.commanded = Drive {
+                steering: self.commanded.steering,
+                ..Default::default()
+            };
+            // the worker is gone if the port died for good
+            let _ = self.commands.send(self.drive_command());
+        }
+    }
+
+    /// Changes the speed of every wheel and the steering, in hundredths of
+    /// RPM and degree, then sends them.
+    fn drive(&mut self, rpm: i32, steering: i32) {
+        let rpm = CentiRpm(self.commanded.fl_whl_rpm.0 + rpm);
+        self.commanded = Drive {
+            steering: CentiDegrees(self.commanded.steering.0 + steering)
+                .clamp(STEERING_MIN, STEERING_MAX),
+            fl_whl_rpm: rpm,
+            fr_whl_rpm: rpm,
+            rl_whl_rpm: rpm,
+            rr_whl_rpm: rpm,
+        };
+        let _ = self.commands.send(self.drive_command());
+    }
+
+    fn drive_command(&self) -> Drive {
+        Drive {
+            steering: self.commanded.steering,
+            fl_whl_rpm: self.commanded.fl_whl_rpm,
+            fr_whl_rpm: self.commanded.fr_whl_rpm,
+            rl_whl_rpm: self.commanded.rl_whl_rpm,
+            rr_whl_rpm: self.commanded.rr_whl_rpm,
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, wheels, details, link, help] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(8),
+            Constraint::Length(LOGS as u16 + 2),
+            Constraint::Length(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        self.draw_header(frame, header);
+        self.draw_wheels(frame, wheels);
+        let [speeds, logs, counts] = Layout::horizontal([
+            Constraint::Percentage(40),
+            Constraint::Percentage(35),
+            Constraint::Percentage(25),
+        ])
+        .areas(details);
+        self.draw_speeds(frame, speeds);
+        self.draw_logs(frame, logs, counts);
+        self.draw_link(frame, link);
+
+        frame.render_widget(
+            Paragraph::new(
+                " a arm/disarm · space e-stop · ↑↓ speed · ←→ steering · 0 stop · c center · q quit",
+            )
+            .dim(),
+            help,
+        );
+    }
+
+    fn draw_header(&self, frame: &mut Frame, area: Rect) {
+        let mode = match self.mode {
+            Mode::Disarmed => Span::styled(" DISARMED ", Style::new().black().on_yellow()),
+            Mode::Armed => Span::styled(" ARMED ", Style::new().black().on_green()),
+            Mode::Stopped => Span::styled(" E-STOP ", Style::new().white().on_red()),
+        };
+        let state = match self.state {
+            ConnectionState::Connected => Span::raw(self.state.to_string()).green(),
+            _ => Span::raw(self.state.to_string()).red(),
+        };
+
+        // the commands of the dashboard are ignored when the host does not
+        // drive
+        let control = match self.control {
+            Some(control) if control.source == ControlSource::Host => {
+                Span::raw(format!("  {} mode", control.mode))
+            }
+            Some(control) => Span::raw(format!("  {control}")).yellow(),
+            None => Span::raw(""),
+        };
+
+        let mut line = Line::from(vec![
+            Span::raw(" Xmaxx ").bold(),
+            mode,
+            Span::raw(format!("  {}  ", self.port)),
+            state,
+            Span::raw(format!(
+                "  steering {:.2} deg",
+                self.commanded.steering.degrees()
+            )),
+            Span::raw(format!("  battery {}", self.battery.voltage)),
+        ]);
+        line.push_span(control);
+        frame.render_widget(Paragraph::new(line), area);
+    }
+
+    fn draw_wheels(&self, frame: &mut Frame, area: Rect) {
+        let [top, bottom] =
+            Layout::vertical([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)]).areas(area);
+        let [fl, fr] =
+            Layout::horizontal([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)]).areas(top);
+        let [rl, rr] =
+            Layout::horizontal([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)]).areas(bottom);
+
+        let names = ["front left", "front right", "rear left", "rear right"];
+        for ((history, name), area) in self.measured.iter().zip(names).zip([fl, fr, rl, rr]) {
+            let rpm = history.back().copied().unwrap_or_default();
+            // the sparkline cannot show negative values, so it shows the speed
+            let width = area.width.saturating_sub(2) as usize;
+            let data: Vec<u64> = history
+                .iter()
+                .skip(history.len().saturating_sub(width))
+                .map(|rpm| rpm.rpm().abs() as u64)
+                .collect();
+
+            let sparkline = Sparkline::default()
+                .block(Block::bordered().title(format!(" {name}: {:.0} rpm ", rpm.rpm())))
+                .data(&data)
+                .style(Style::new().fg(Color::Cyan));
+            frame.render_widget(sparkline, area);
+        }
+    }
+
+    fn draw_speeds(&self, frame: &mut Frame, area: Rect) {
+        let commanded = [
+            self.commanded.fl_whl_rpm,
+            self.commanded.fr_whl_rpm,
+            self.commanded.rl_whl_rpm,
+            self.commanded.rr_whl_rpm,
+        ];
+        let currents = [
+            self.currents.fl,
+            self.currents.fr,
+            self.currents.rl,
+            self.currents.rr,
+        ];
+        let names = ["FL", "FR", "RL", "RR"];
+
+        let rows = names
+            .iter()
+            .zip(commanded)
+            .zip(&self.measured)
+            .zip(currents)
+            .map(|(((name, commanded), history), current)| {
+                let commanded = commanded.rpm();
+                let measured = history.back().copied().unwrap_or_default().rpm();
+                Row::new([
+                    name.to_string(),
+                    format!("{commanded:.0}"),
+                    format!("{measured:.0}"),
+                    format!("{:+.0}", measured - commanded),
+                    format!("{:.1}", current.amps()),
+                ])
+            });
+        let table = Table::new(rows, [Constraint::Fill(1); 5])
+            .header(
+                Row::new(["wheel", "cmd", "meas", "error", "amps"])
+                    .style(Style::new().add_modifier(Modifier::BOLD)),
+            )
+            .block(Block::bordered().title(" commanded vs measured (rpm) "));
+        frame.render_widget(table, area);
+    }
+
+    fn draw_logs(&self, frame: &mut Frame, logs: Rect, counts: Rect) {
+        let items: Vec<ListItem> = self
+            .logs
+            .iter()
+            .rev()
+            .map(|(time, log)| ListItem::new(format!("{:>8.3}  {log}", time.as_secs_f64())))
+            .collect();
+        frame.render_widget(
+            List::new(items).block(Block::bordered().title(" last logs ")),
+            logs,
+        );
+
+        let items: Vec<ListItem> = self
+            .log_counts
+            .iter()
+            .map(|(log, count)| ListItem::new(format!("{count:>6}  {log}")))
+            .collect();
+        frame.render_widget(
+            List::new(items).block(Block::bordered().title(" log counts ")),
+            counts,
+        );
+    }
+
+    fn draw_link(&self, frame: &mut Frame, area: Rect) {
+        let stats = &self.stats;
+        let mut text = format!(
+            "{:.0} frames/s · {} frames · {} discarded ({} bytes) · {} timeouts",
+            self.rate, stats.frames, stats.discarded_frames, stats.discarded_bytes, stats.timeouts
+        );
+        if let Some(event) = &self.event {
+            text.push_str(&format!(" · {event}"));
+        }
+        frame.render_widget(
+            Paragraph::new(text).block(Block::bordered().title(" link ")),
+            area,
+        );
+    }
+}
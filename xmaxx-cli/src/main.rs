@@ -0,0 +1,505 @@
+use std::error;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use xmaxx_host::*;
+use xmaxx_messages::*;
+
+mod dashboard;
+
+/// Set when Ctrl-C is pressed, to stop the subcommands that run until then.
+static STOP: AtomicBool = AtomicBool::new(false);
+
+/// How often a drive command is sent again, well within the 500 ms after
+/// which the firmware stops the Xmaxx without commands.
+const RESEND: Duration = Duration::from_millis(100);
+
+/// A command-line tool to work with the Xmaxx's firmware.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    #[command(flatten)]
+    connection: ConnectionArgs,
+    #[command(subcommand)]
+    command: Commands,
+}
+
+/// Where to find the firmware.
+///
+/// Without `--port` nor `--usb`, the first known board found is used.
+#[derive(Args)]
+struct ConnectionArgs {
+    /// The path of the serial port, like /dev/ttyACM0.
+    #[arg(short, long, global = true)]
+    port: Option<String>,
+    /// The USB device, as VID:PID[:SERIAL] in hexadecimal.
+    #[arg(long, global = true, value_parser = parse_usb, conflicts_with = "port")]
+    usb: Option<UsbId>,
+    /// The baudrate of the communication.
+    #[arg(short, long, global = true, default_value_t = 57600)]
+    baudrate: u32,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Shows a dashboard with the live telemetry, to drive the Xmaxx from the
+    /// keyboard.
+    Dashboard,
+    /// Prints the information received from the firmware until Ctrl-C.
+    Monitor {
+        /// Stops after this many messages.
+        #[arg(short = 'n', long)]
+        count: Option<u64>,
+    },
+    /// Sends one drive command.
+    ///
+    /// The firmware stops the Xmaxx when it receives no command for 500 ms,
+    /// unless the command is held.
+    Send {
+        /// The steering angle (deg), 90 is straight.
+        #[arg(short, long, default_value_t = 90.0, allow_negative_numbers = true)]
+        steering: f64,
+        /// The speed of every wheel (RPM).
+        #[arg(short, long, default_value_t = 0.0, allow_negative_numbers = true)]
+        rpm: f64,
+        /// The speed of the front left wheel (RPM), instead of `--rpm`.
+        #[arg(long, allow_negative_numbers = true)]
+        fl: Option<f64>,
+        /// The speed of the front right wheel (RPM), instead of `--rpm`.
+        #[arg(long, allow_negative_numbers = true)]
+        fr: Option<f64>,
+        /// The speed of the rear left wheel (RPM), instead of `--rpm`.
+        #[arg(long, allow_negative_numbers = true)]
+        rl: Option<f64>,
+        /// The speed of the rear right wheel (RPM), instead of `--rpm`.
+        #[arg(long, allow_negative_numbers = true)]
+        rr: Option<f64>,
+        /// Sends the command again for this long (s), or until Ctrl-C.
+        #[arg(long)]
+        hold: Option<f64>,
+    },
+    /// Records the session to a file until Ctrl-C.
+    Record {
+        /// The recording file, overwritten if it exists.
+        recording: PathBuf,
+        /// Stops after this many seconds.
+        #[arg(short, long)]
+        duration: Option<f64>,
+    },
+    /// Prints the messages of a recorded session.
+    Replay {
+        /// The recording file.
+        recording: PathBuf,
+        /// The playback speed.
+        #[arg(short, long, default_value_t = 1.0, conflicts_with = "fast")]
+        speed: f64,
+        /// Plays back as fast as possible.
+        #[arg(short, long)]
+        fast: bool,
+    },
+    /// Pings the firmware and prints the round trip times.
+    Ping {
+        /// The number of pings.
+        #[arg(short = 'n', long, default_value_t = 4)]
+        count: u32,
+        /// The delay between two pings (ms).
+        #[arg(short, long, default_value_t = 1000)]
+        interval: u64,
+    },
+    /// Moves the odometry of the firmware back to the origin.
+    ResetOdometry,
+    /// Selects the axles driven by their motors.
+    DriveMode {
+        #[arg(value_enum)]
+        mode: DrivenAxles,
+    },
+    /// Lists, reads or sets the runtime parameters of the firmware.
+    Params {
+        #[command(subcommand)]
+        action: Option<ParamsAction>,
+    },
+    /// Converts a recorded session to other formats.
+    Export {
+        /// The recording file.
+        recording: PathBuf,
+        /// The output directory for CSV, the output file for MCAP.
+        output: PathBuf,
+        /// The output format.
+        #[arg(short, long, value_enum, default_value_t = Format::Csv)]
+        format: Format,
+    },
+}
+
+#[derive(Subcommand)]
+enum ParamsAction {
+    /// Prints the value of a parameter.
+    Get {
+        #[arg(value_parser = parse_param)]
+        name: Param,
+    },
+    /// Sets a parameter until the firmware resets.
+    Set {
+        #[arg(value_parser = parse_param)]
+        name: Param,
+        #[arg(allow_negative_numbers = true)]
+        value: i32,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DrivenAxles {
+    /// All-wheel drive.
+    Awd,
+    /// Front-wheel drive.
+    Fwd,
+    /// Rear-wheel drive.
+    Rwd,
+    /// No wheel driven.
+    Coast,
+}
+
+impl From<DrivenAxles> for DriveMode {
+    fn from(axles: DrivenAxles) -> Self {
+        match axles {
+            DrivenAxles::Awd => DriveMode::AllWheel,
+            DrivenAxles::Fwd => DriveMode::FrontWheel,
+            DrivenAxles::Rwd => DriveMode::RearWheel,
+            DrivenAxles::Coast => DriveMode::Coast,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// One CSV file per message type.
+    Csv,
+    /// An MCAP file with one topic per message type.
+    Mcap,
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    if let Err(err) = run(cli) {
+        eprintln!("error: {err}");
+        process::exit(1);
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn error::Error>> {
+    ctrlc::set_handler(|| STOP.store(true, Ordering::Relaxed))?;
+
+    match cli.command {
+        Commands::Dashboard => dashboard::run(connect(cli.connection)?),
+        Commands::Monitor { count } => monitor(cli.connection, count),
+        Commands::Send {
+            steering,
+            rpm,
+            fl,
+            fr,
+            rl,
+            rr,
+            hold,
+        } => {
+            let rpm = |value: Option<f64>| CentiRpm::from_rpm(value.unwrap_or(rpm) as f32);
+            let drive = Drive {
+                steering: CentiDegrees::from_degrees(steering as f32),
+                fl_whl_rpm: rpm(fl),
+                fr_whl_rpm: rpm(fr),
+                rl_whl_rpm: rpm(rl),
+                rr_whl_rpm: rpm(rr),
+            };
+            send(cli.connection, drive, hold)
+        }
+        Commands::Record {
+            recording,
+            duration,
+        } => record(cli.connection, recording, duration),
+        Commands::Replay {
+            recording,
+            speed,
+            fast,
+        } => replay(recording, (!fast).then_some(speed)),
+        Commands::Ping { count, interval } => ping(cli.connection, count, interval),
+        Commands::ResetOdometry => reset_odometry(cli.connection),
+        Commands::DriveMode { mode } => drive_mode(cli.connection, mode.into()),
+        Commands::Params { action } => params(cli.connection, action),
+        Commands::Export {
+            recording,
+            output,
+            format,
+        } => export(recording, output, format),
+    }
+}
+
+/// Parses a USB identity like `2341:0042` or `2341:0042:SERIAL`.
+fn parse_usb(s: &str) -> Result<UsbId, String> {
+    let mut parts = s.splitn(3, ':');
+    let mut id = || {
+        let part = parts.next().unwrap_or_default();
+        u16::from_str_radix(part, 16).map_err(|_| format!("invalid USB id: {part:?}"))
+    };
+    let vid = id()?;
+    let pid = id()?;
+
+    Ok(UsbId {
+        vid,
+        pid,
+        serial_number: parts.next().map(String::from),
+    })
+}
+
+fn parse_param(s: &str) -> Result<Param, String> {
+    Param::from_name(s).ok_or_else(|| {
+        let names: Vec<_> = Param::ALL.iter().map(Param::name).collect();
+        format!("expected one of: {}", names.join(", "))
+    })
+}
+
+/// Opens the connection to the firmware.
+fn connect(args: ConnectionArgs) -> Result<Firmware, Box<dyn error::Error>> {
+    let spec = match (args.port, args.usb) {
+        (Some(path), _) => PortSpec::Path(path),
+        (None, Some(usb)) => PortSpec::Usb(usb),
+        (None, None) => {
+            let candidate = list_firmwares(false, None)?
+                .into_iter()
+                .next()
+                .ok_or("no known board found, use --port or --usb")?;
+            PortSpec::Usb(candidate.usb)
+        }
+    };
+
+    let settings = Settings {
+        baudrate: args.baudrate,
+        ..Default::default()
+    };
+    let firmware = Firmware::open(spec, settings)?;
+    eprintln!("connected to {}", firmware.path().unwrap_or_default());
+
+    Ok(firmware)
+}
+
+/// Receives the next message, or `None` on timeout.
+///
+/// The connection events are printed to stderr.
+fn recv(firmware: &mut Firmware) -> Result<Option<Info>, Error> {
+    let result = firmware.recv();
+    while let Some(event) = firmware.poll_event() {
+        eprintln!("{event}");
+    }
+
+    match result {
+        Ok(info) => Ok(Some(info)),
+        Err(Error::Io(err)) if err.kind() == ErrorKind::TimedOut => Ok(None),
+        Err(Error::Timeout) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Formats a message on one line.
+fn describe(message: &Message) -> String {
+    match message {
+        Message::Received(info) => format!("<- {info}"),
+        Message::Sent(command) => format!("-> {command}"),
+    }
+}
+
+fn print_stats(stats: RecvStats) {
+    eprintln!(
+        "{} frames, {} discarded ({} bytes), {} timeouts",
+        stats.frames, stats.discarded_frames, stats.discarded_bytes, stats.timeouts
+    );
+}
+
+fn monitor(args: ConnectionArgs, count: Option<u64>) -> Result<(), Box<dyn error::Error>> {
+    let mut firmware = connect(args)?;
+    let start = Instant::now();
+
+    let mut received = 0;
+    while !STOP.load(Ordering::Relaxed) && count.is_none_or(|count| received < count) {
+        if let Some(info) = recv(&mut firmware)? {
+            let time = start.elapsed().as_secs_f64();
+            println!("{time:>10.3}  {}", describe(&Message::Received(info)));
+            received += 1;
+        }
+    }
+
+    print_stats(firmware.stats());
+    Ok(())
+}
+
+fn send(
+    args: ConnectionArgs,
+    drive: Drive,
+    hold: Option<f64>,
+) -> Result<(), Box<dyn error::Error>> {
+    let mut firmware = connect(args)?;
+    let command = Command::Drive(drive);
+    firmware.send(&command)?;
+    println!("{}", describe(&Message::Sent(command.clone())));
+
+    let start = Instant::now();
+    let hold = hold.map(Duration::from_secs_f64).unwrap_or_default();
+    let mut sent = start;
+    while !STOP.load(Ordering::Relaxed) && start.elapsed() < hold {
+        if sent.elapsed() >= RESEND {
+            firmware.send(&command)?;
+            sent = Instant::now();
+        }
+        // the telemetry is not shown, but must not pile up
+        recv(&mut firmware)?;
+    }
+
+    Ok(())
+}
+
+fn record(
+    args: ConnectionArgs,
+    recording: PathBuf,
+    duration: Option<f64>,
+) -> Result<(), Box<dyn error::Error>> {
+    let mut firmware = connect(args)?;
+    firmware.record(Some(Recorder::create(&recording)?))?;
+    eprintln!("recording to {}, Ctrl-C to stop", recording.display());
+
+    let start = Instant::now();
+    let duration = duration.map(Duration::from_secs_f64);
+    while !STOP.load(Ordering::Relaxed) && duration.is_none_or(|d| start.elapsed() < d) {
+        recv(&mut firmware)?;
+    }
+
+    // stopping flushes the recording
+    firmware.record(None)?;
+    print_stats(firmware.stats());
+    Ok(())
+}
+
+fn replay(recording: PathBuf, speed: Option<f64>) -> Result<(), Box<dyn error::Error>> {
+    let recording = Recording::open(recording)?;
+    let start = Instant::now();
+
+    for record in &recording.records {
+        if STOP.load(Ordering::Relaxed) {
+            break;
+        }
+        if let Some(speed) = speed {
+            let due = record.time().div_f64(speed);
+            if let Some(delay) = due.checked_sub(start.elapsed()) {
+                thread::sleep(delay);
+            }
+        }
+
+        let time = record.time().as_secs_f64();
+        match record.decode() {
+            Some(message) => println!("{time:>10.3}  {}", describe(&message)),
+            None => println!("{time:>10.3}  invalid frame {:02x?}", record.frame),
+        }
+    }
+
+    Ok(())
+}
+
+fn ping(args: ConnectionArgs, count: u32, interval: u64) -> Result<(), Box<dyn error::Error>> {
+    let mut firmware = connect(args)?;
+
+    let mut sent = 0;
+    let mut rtts = Vec::new();
+    for i in 0..count {
+        if STOP.load(Ordering::Relaxed) {
+            break;
+        }
+        if i > 0 {
+            thread::sleep(Duration::from_millis(interval));
+        }
+
+        sent += 1;
+        match firmware.ping(Duration::from_secs(1)) {
+            Ok(rtt) => {
+                let rtt = rtt.as_secs_f64() * 1000.0;
+                println!("pong: time={rtt:.2} ms");
+                rtts.push(rtt);
+            }
+            Err(Error::Timeout) => println!("no pong"),
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    print!("{}/{sent} answered", rtts.len());
+    if !rtts.is_empty() {
+        let min = rtts.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = rtts.iter().copied().fold(0.0, f64::max);
+        let avg = rtts.iter().sum::<f64>() / rtts.len() as f64;
+        print!(", min/avg/max = {min:.2}/{avg:.2}/{max:.2} ms");
+    }
+    println!();
+
+    Ok(())
+}
+
+fn reset_odometry(args: ConnectionArgs) -> Result<(), Box<dyn error::Error>> {
+    let mut firmware = connect(args)?;
+    let odometry = firmware.reset_odometry(Duration::from_secs(1))?;
+    println!("{odometry}");
+
+    Ok(())
+}
+
+fn drive_mode(args: ConnectionArgs, mode: DriveMode) -> Result<(), Box<dyn error::Error>> {
+    let mut firmware = connect(args)?;
+    let axles = firmware.set_drive_mode(mode, Duration::from_secs(1))?;
+    println!("{axles}");
+
+    Ok(())
+}
+
+fn params(args: ConnectionArgs, action: Option<ParamsAction>) -> Result<(), Box<dyn error::Error>> {
+    let mut firmware = connect(args)?;
+    let timeout = Duration::from_secs(1);
+
+    match action {
+        None => {
+            for param in Param::ALL {
+                let value = firmware.get_param(*param, timeout)?;
+                println!("{} = {value}", param.name());
+            }
+        }
+        Some(ParamsAction::Get { name }) => {
+            println!("{}", firmware.get_param(name, timeout)?);
+        }
+        Some(ParamsAction::Set { name, value }) => {
+            let value = firmware.set_param(name, value, timeout)?;
+            println!("{} = {value}", name.name());
+        }
+    }
+
+    Ok(())
+}
+
+fn export(
+    recording: PathBuf,
+    output: PathBuf,
+    format: Format,
+) -> Result<(), Box<dyn error::Error>> {
+    let recording = Recording::open(recording)?;
+
+    match format {
+        Format::Csv => {
+            for path in export_csv(&recording, output)? {
+                println!("{}", path.display());
+            }
+        }
+        Format::Mcap => {
+            export_mcap(&recording, &output)?;
+            println!("{}", output.display());
+        }
+    }
+
+    Ok(())
+}
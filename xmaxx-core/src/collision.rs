@@ -0,0 +1,118 @@
+//! The protection against collisions with the range sensors.
+//!
+//! The range sensors are ultrasonic, like the HC-SR04: once triggered, they
+//! output a pulse as long as their sound took to come back from the
+//! nearest obstacle.
+
+use xmaxx_messages::{CentiRpm, Log, MilliMeters};
+
+use crate::conversion::RPM_MAX;
+
+/// The farthest distance measured by the range sensors.
+pub const RANGE_MAX: MilliMeters = MilliMeters(4000);
+/// The default distance at which the wheels are stopped towards an
+/// obstacle, leaving room to brake and for the latency of the sensors.
+pub const STOP_DISTANCE: MilliMeters = MilliMeters(400);
+/// The default distance from which the speed towards an obstacle is
+/// limited.
+pub const SLOW_DISTANCE: MilliMeters = MilliMeters(2000);
+/// The time between two measurements of the range sensors, so the echoes
+/// of one fade before the next (ms).
+pub const RANGE_PERIOD: u32 = 60;
+/// How long a measurement is kept, after which the sensor is considered
+/// disconnected (ms).
+pub const RANGE_TIMEOUT: u32 = 250;
+/// The speed of sound in air at 20 °C (mm/ms).
+const SPEED_OF_SOUND: u32 = 343;
+
+/// Returns the distance to the obstacle from the width of an echo pulse
+/// (µs), or `None` if it is out of range.
+pub fn echo_to_distance(width: u32) -> Option<MilliMeters> {
+    // the sound goes there and back
+    let distance = width.saturating_mul(SPEED_OF_SOUND) / 2000;
+    // safe to cast: at most the range
+    (distance <= RANGE_MAX.0 as u32).then_some(MilliMeters(distance as i32))
+}
+
+/// Returns the highest speed towards an obstacle at `distance`.
+///
+/// The speed is full from `slow`, and goes down linearly to zero at
+/// `stop`.
+pub fn speed_limit(distance: MilliMeters, stop: MilliMeters, slow: MilliMeters) -> CentiRpm {
+    if distance <= stop {
+        CentiRpm(0)
+    } else if distance >= slow {
+        RPM_MAX
+    } else {
+        // no overflow: the distances are within the range of the sensors
+        CentiRpm(RPM_MAX.0 / (slow.0 - stop.0) * (distance.0 - stop.0))
+    }
+}
+
+/// Limits the speed towards the obstacles measured in front and behind.
+///
+/// A sensor without a recent measurement does not limit the speed, so the
+/// Xmaxx drives without sensors.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Collision {
+    /// The last measurements in front and behind, the distance if anything
+    /// was in range, and when (ms).
+    ranges: [Option<(Option<MilliMeters>, u32)>; 2],
+    now: u32,
+    /// Whether the wheels are stopped by an obstacle.
+    stopped: bool,
+}
+
+impl Collision {
+    /// Returns the protection without measurements.
+    pub fn new() -> Self {
+        Self {
+            ranges: [None; 2],
+            now: 0,
+            stopped: false,
+        }
+    }
+
+    /// Updates the protection with the widths of the echoes since the last
+    /// update, if any, at `now` (ms): front and rear.
+    pub fn update(&mut self, now: u32, echoes: [Option<u32>; 2]) {
+        self.now = now;
+        for (range, echo) in self.ranges.iter_mut().zip(echoes) {
+            if let Some(width) = echo {
+                *range = Some((echo_to_distance(width), now));
+            }
+        }
+    }
+
+    /// Returns the distances to the obstacles in range, if the measurements
+    /// are recent: front and rear.
+    pub fn distances(&self) -> [Option<MilliMeters>; 2] {
+        self.ranges.map(|range| match range {
+            Some((distance, time)) if self.now.wrapping_sub(time) < RANGE_TIMEOUT => distance,
+            _ => None,
+        })
+    }
+
+    /// Limits the speeds of the wheels towards the obstacles, and returns
+    /// [`Log::CollisionStop`] when it starts stopping them.
+    pub fn limit(
+        &mut self,
+        rpms: &mut [CentiRpm; 4],
+        stop: MilliMeters,
+        slow: MilliMeters,
+    ) -> Option<Log> {
+        let [forwards, backwards] = self
+            .distances()
+            .map(|distance| distance.map_or(RPM_MAX, |distance| speed_limit(distance, stop, slow)));
+
+        let mut stopped = false;
+        for rpm in rpms.iter_mut() {
+            stopped |= (rpm.0 > 0 && forwards.0 == 0) || (rpm.0 < 0 && backwards.0 == 0);
+            *rpm = (*rpm).clamp(CentiRpm(-backwards.0), forwards);
+        }
+
+        let log = (stopped && !self.stopped).then_some(Log::CollisionStop);
+        self.stopped = stopped;
+        log
+    }
+}
@@ -0,0 +1,568 @@
+//! The handling of the commands received by the firmware.
+
+use embedded_hal::digital::OutputPin;
+use embedded_hal::pwm::SetDutyCycle;
+
+use xmaxx_messages::*;
+
+use crate::collision::Collision;
+use crate::conversion::*;
+use crate::encoder::EncoderSpeed;
+use crate::filter::LowPass;
+use crate::imu::{ImuReading, YawRate};
+use crate::low_voltage::{Level, LowVoltage};
+use crate::odometry::{kinematic_yaw_rate, DeadReckoning};
+use crate::overcurrent::{Overcurrent, FULL_SPEED};
+use crate::rc::{RcReceiver, HOST_TIMEOUT, RC_CHANNELS};
+use crate::stability::StabilityControl;
+use crate::status::Status;
+use crate::traction::TractionControl;
+use crate::{Params, SpeedSource};
+
+/// The outputs driving the Xmaxx: the PWM of the steering and the motors,
+/// and the enable pins of the motor drivers of each axle.
+pub struct Drivetrain<S, FL, FR, RL, RR, EF, ER> {
+    pub steering: S,
+    pub motor_fl: FL,
+    pub motor_fr: FR,
+    pub motor_rl: RL,
+    pub motor_rr: RR,
+    pub enable_front: EF,
+    pub enable_rear: ER,
+}
+
+impl<S, FL, FR, RL, RR, EF, ER> Drivetrain<S, FL, FR, RL, RR, EF, ER>
+where
+    S: SetDutyCycle,
+    FL: SetDutyCycle,
+    FR: SetDutyCycle,
+    RL: SetDutyCycle,
+    RR: SetDutyCycle,
+    EF: OutputPin,
+    ER: OutputPin,
+{
+    /// Sets the outputs to follow the command.
+    ///
+    /// It assumes that the command is valid, see [`validate`].
+    pub fn execute(&mut self, command: &Drive) {
+        self.steering
+            .set_duty_cycle_fraction(angle_to_duty(command.steering), DUTY_CYCLE_DENOM)
+            .expect("duty cycle should not be too large");
+        self.motor_fl
+            .set_duty_cycle_fraction(rpm_to_duty(command.fl_whl_rpm), DUTY_CYCLE_DENOM)
+            .expect("duty cycle should not be too large");
+        self.motor_fr
+            .set_duty_cycle_fraction(rpm_to_duty(command.fr_whl_rpm), DUTY_CYCLE_DENOM)
+            .expect("duty cycle should not be too large");
+        self.motor_rl
+            .set_duty_cycle_fraction(rpm_to_duty(command.rl_whl_rpm), DUTY_CYCLE_DENOM)
+            .expect("duty cycle should not be too large");
+        self.motor_rr
+            .set_duty_cycle_fraction(rpm_to_duty(command.rr_whl_rpm), DUTY_CYCLE_DENOM)
+            .expect("duty cycle should not be too large");
+    }
+
+    /// Enables or disables the motor drivers of the axles: front and rear.
+    pub fn enable(&mut self, [front, rear]: [bool; 2]) {
+        self.enable_front
+            .set_state(front.into())
+            .expect("enable pin should be settable");
+        self.enable_rear
+            .set_state(rear.into())
+            .expect("enable pin should be settable");
+    }
+}
+
+/// Checks that the command is in the range of the Xmaxx.
+pub fn validate(command: &Drive) -> Result<(), Log> {
+    if !(STEERING_ANGLE_RANGE.contains(&command.steering))
+        || !(RPM_RANGE.contains(&command.fl_whl_rpm))
+        || !(RPM_RANGE.contains(&command.fr_whl_rpm))
+        || !(RPM_RANGE.contains(&command.rl_whl_rpm))
+        || !(RPM_RANGE.contains(&command.rr_whl_rpm))
+    {
+        return Err(Log::InvalidCommand);
+    }
+
+    Ok(())
+}
+
+/// The wheel speed below which a wheel is at rest, above the noise of the
+/// speed sensors.
+pub const REST_SPEED: CentiRpm = CentiRpm(100);
+
+/// The slower telemetry, sent one an iteration in turn, see
+/// [`Controller::telemetry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Telemetry {
+    /// [`Info::Currents`].
+    Currents,
+    /// [`Info::Battery`].
+    Battery,
+    /// [`Info::Control`].
+    Control,
+    /// [`Info::Traction`].
+    Traction,
+    /// [`Info::Axles`].
+    Axles,
+    /// [`Info::Encoders`], with the wheel encoders.
+    Encoders,
+    /// [`Info::Imu`], with the IMU.
+    Imu,
+    /// [`Info::Proximity`], with the range sensors.
+    Proximity,
+}
+
+/// The readings of the sensors in an iteration of the main loop.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Readings {
+    /// The speeds of the wheels: fl, fr, rl, rr.
+    pub speeds: [CentiRpm; 4],
+    /// The currents of the motors: fl, fr, rl, rr.
+    pub currents: [MilliAmps; 4],
+    /// The voltage of the battery.
+    pub voltage: MilliVolts,
+    /// The ticks of the wheel encoders: fl, fr, rl, rr.
+    pub ticks: [i32; 4],
+    /// The readings of the IMU, if it answered.
+    pub imu: Option<ImuReading>,
+    /// The widths of the pulses of the RC receiver since the last readings,
+    /// if any (µs): throttle, steering and mode switch.
+    pub rc: [Option<u16>; RC_CHANNELS],
+    /// The widths of the echoes of the range sensors since the last
+    /// readings, if any (µs): front and rear.
+    pub echoes: [Option<u32>; 2],
+}
+
+/// The state of the firmware between two iterations of the main loop.
+pub struct Controller<S, FL, FR, RL, RR, EF, ER> {
+    pub drivetrain: Drivetrain<S, FL, FR, RL, RR, EF, ER>,
+    pub params: Params,
+    /// The last valid command of the host.
+    pub command: Drive,
+    /// Whether the host sent a valid drive command since the last update.
+    pub host_commanded: bool,
+    /// When the host last sent a valid drive command (ms).
+    pub last_host_command: Option<u32>,
+    pub rc: RcReceiver,
+    /// Who drives, since the last update.
+    pub control: Control,
+    /// The axles that the host wants driven.
+    pub drive_mode: DriveMode,
+    /// Whether the motor drivers of the axles are enabled, since the last
+    /// update: front and rear.
+    pub enabled: [bool; 2],
+    pub overcurrent: Overcurrent,
+    pub low_voltage: LowVoltage,
+    pub collision: Collision,
+    pub traction: TractionControl,
+    pub stability: StabilityControl,
+    /// The speeds of the wheels from the encoders: fl, fr, rl, rr.
+    pub encoder_speeds: [EncoderSpeed; 4],
+    /// The filters of the speeds of the wheels: fl, fr, rl, rr.
+    pub speed_filters: [LowPass; 4],
+    pub yaw_rate: YawRate,
+    pub odometry: DeadReckoning,
+    /// The last readings of the sensors, with the speeds filtered.
+    pub readings: Readings,
+    /// The number of slower telemetry messages sent.
+    pub slow_sent: usize,
+}
+
+impl<S, FL, FR, RL, RR, EF, ER> Controller<S, FL, FR, RL, RR, EF, ER>
+where
+    S: SetDutyCycle,
+    FL: SetDutyCycle,
+    FR: SetDutyCycle,
+    RL: SetDutyCycle,
+    RR: SetDutyCycle,
+    EF: OutputPin,
+    ER: OutputPin,
+{
+    /// Returns a controller with the default parameters.
+    pub fn new(drivetrain: Drivetrain<S, FL, FR, RL, RR, EF, ER>) -> Self {
+        Self {
+            drivetrain,
+            params: Params::new(),
+            command: stop(),
+            host_commanded: false,
+            last_host_command: None,
+            rc: RcReceiver::new(),
+            control: Control {
+                mode: ControlMode::Host,
+                source: ControlSource::Host,
+            },
+            drive_mode: DriveMode::AllWheel,
+            // until the first update
+            enabled: [false; 2],
+            overcurrent: Overcurrent::new(),
+            low_voltage: LowVoltage::new(),
+            collision: Collision::new(),
+            traction: TractionControl::new(),
+            stability: StabilityControl::new(),
+            encoder_speeds: [EncoderSpeed::new(); 4],
+            speed_filters: [LowPass::new(); 4],
+            yaw_rate: YawRate::new(),
+            odometry: DeadReckoning::new(),
+            readings: Readings::default(),
+            slow_sent: 0,
+        }
+    }
+
+    /// Handles the result of reading a command and returns the information
+    /// to answer with, if any.
+    ///
+    /// A drive command is followed from the next [`Controller::update`], if
+    /// the host drives.
+    pub fn handle(&mut self, command: Result<Option<Command>, Log>) -> Option<Info> {
+        match command {
+            // keep the command to follow it
+            Ok(Some(Command::Drive(command))) => match validate(&command) {
+                Ok(()) => {
+                    self.command = command;
+                    self.host_commanded = true;
+                    None
+                }
+                Err(log) => Some(Info::Log(log)),
+            },
+            // answer the host to show that the firmware is alive
+            Ok(Some(Command::Ping(id))) => Some(Info::Pong(id)),
+            Ok(Some(Command::GetParam(param))) => Some(Info::Param(param, self.params.get(param))),
+            // answer with the new value to confirm it was set
+            Ok(Some(Command::SetParam(param, value))) => match self.params.set(param, value) {
+                Ok(()) => Some(Info::Param(param, self.params.get(param))),
+                Err(log) => Some(Info::Log(log)),
+            },
+            // answer with the odometry to confirm the reset and its time
+            Ok(Some(Command::ResetOdometry)) => {
+                self.odometry.reset();
+                Some(Info::Odometry(self.odometry()))
+            }
+            // answer with the axles to confirm the mode, which the drivers
+            // follow from the next update
+            Ok(Some(Command::SetDriveMode(mode))) => {
+                self.drive_mode = mode;
+                Some(Info::Axles(self.axles()))
+            }
+            // there was no command
+            Ok(None) => Some(Info::Log(Log::NoCommandReceived)),
+            // could not read a command
+            Err(log) => Some(Info::Log(log)),
+        }
+    }
+
+    /// Updates the state with the readings of the sensors at `now` (ms),
+    /// then sets the outputs to follow the command of the host or of the RC
+    /// transmitter within the limits.
+    ///
+    /// It returns the logs of the protections that tripped.
+    pub fn update(&mut self, now: u32, readings: Readings) -> impl Iterator<Item = Log> {
+        self.readings = readings;
+        let (command, rc_lost) = self.select_command(now);
+        self.collision.update(now, readings.echoes);
+        // the encoders are measured even when unused, to switch to them
+        let resolution = self.params.encoder_resolution;
+        for (i, encoder) in self.encoder_speeds.iter_mut().enumerate() {
+            let speed = encoder.update(now, readings.ticks[i], resolution);
+            if self.params.speed_source == SpeedSource::Encoders {
+                self.readings.speeds[i] = speed;
+            }
+        }
+        let filters = self.readings.speeds.iter_mut().zip(&mut self.speed_filters);
+        for (speed, filter) in filters {
+            *speed = CentiRpm(filter.update(speed.0, self.params.speed_filter));
+        }
+        // the slips are measured even when unused, to tune the threshold
+        self.traction.update(
+            self.readings.speeds,
+            wheel_speeds(&command),
+            self.params.slip_threshold,
+        );
+        // the gyroscope is only calibrated when the Xmaxx is surely still
+        let commanded = wheel_speeds(&command).iter().any(|rpm| rpm.0 != 0);
+        let moving = self
+            .readings
+            .speeds
+            .iter()
+            .any(|rpm| rpm.0.abs() > REST_SPEED.0);
+        // without a reading, the yaw rate would be stale
+        if let Some(imu) = readings.imu {
+            self.yaw_rate.update(imu.gyro[2], !commanded && !moving);
+            self.stability.update(
+                self.yaw_rate.rate(),
+                kinematic_yaw_rate(self.readings.speeds, command.steering),
+            );
+        }
+        self.odometry
+            .update(now, self.readings.speeds, command.steering);
+
+        // the protections are rearmed by commanding zero
+        self.overcurrent.rearm(wheel_speeds(&command));
+        self.low_voltage.rearm(wheel_speeds(&command));
+        let overcurrent =
+            self.overcurrent
+                .update(now, readings.currents, self.params.current_limits);
+        let low_voltage = self.low_voltage.update(
+            now,
+            readings.voltage,
+            self.params.battery_warning,
+            self.params.battery_cutoff,
+        );
+
+        let limit = CentiRpm(self.params.rpm_limit.0 * self.low_voltage.speed() / FULL_SPEED);
+        let mut rpms = wheel_speeds(&command);
+        for (rpm, speed) in rpms.iter_mut().zip(self.overcurrent.speeds()) {
+            let limited = (*rpm).clamp(CentiRpm(-limit.0), limit);
+            *rpm = CentiRpm(limited.0 * speed / FULL_SPEED);
+        }
+        if self.params.traction_control {
+            self.traction.limit(&mut rpms);
+        }
+        if self.params.stability_control && readings.imu.is_some() {
+            self.stability.limit(&mut rpms);
+        }
+        // onboard, so it stops in time even if the host lags
+        let collision = self.collision.limit(
+            &mut rpms,
+            self.params.stop_distance,
+            self.params.slow_distance,
+        );
+
+        // the protections that cut the motors override the host, and the
+        // wheels of a disabled axle roll freely whatever their command
+        let cuts = self.overcurrent.cuts();
+        let disarmed = self.low_voltage.is_disarmed();
+        let driven = driven_axles(self.drive_mode);
+        for (axle, enabled) in self.enabled.iter_mut().enumerate() {
+            *enabled = driven[axle] && !disarmed && !cuts[2 * axle] && !cuts[2 * axle + 1];
+            if !*enabled {
+                rpms[2 * axle..2 * axle + 2].fill(CentiRpm(0));
+            }
+        }
+
+        let [fl, fr, rl, rr] = rpms;
+        self.drivetrain.execute(&Drive {
+            steering: command.steering,
+            fl_whl_rpm: fl,
+            fr_whl_rpm: fr,
+            rl_whl_rpm: rl,
+            rr_whl_rpm: rr,
+        });
+        self.drivetrain.enable(self.enabled);
+
+        [overcurrent, low_voltage, rc_lost, collision]
+            .into_iter()
+            .flatten()
+    }
+
+    /// Selects the command to follow at `now` (ms) from the mode switch, and
+    /// returns it with [`Log::RcSignalLost`] if the RC transmitter stopped
+    /// driving.
+    ///
+    /// The wheels stop when the source of the command is lost: the RC
+    /// signal, or the host not sending a drive command for [`HOST_TIMEOUT`].
+    fn select_command(&mut self, now: u32) -> (Drive, Option<Log>) {
+        self.rc.update(now, self.readings.rc);
+        if self.host_commanded {
+            self.host_commanded = false;
+            self.last_host_command = Some(now);
+        }
+        let host_alive = self
+            .last_host_command
+            .is_some_and(|time| now.wrapping_sub(time) < HOST_TIMEOUT);
+
+        // the switch stays in its last position while the signal is lost
+        if let Some(mode) = self.rc.mode() {
+            self.control.mode = mode;
+        }
+        let rc = || match self.rc.drive(self.params.rc_speed) {
+            Some(command) => (ControlSource::Rc, command),
+            None => (ControlSource::Stopped, stop()),
+        };
+        // the host went silent, rather than never drove
+        let host_lost = self.last_host_command.is_some() && !host_alive;
+        let (source, command) = match self.control.mode {
+            ControlMode::Host if host_lost => (ControlSource::Stopped, stop()),
+            ControlMode::Host => (ControlSource::Host, self.command.clone()),
+            ControlMode::Auto if host_alive => (ControlSource::Host, self.command.clone()),
+            ControlMode::Rc | ControlMode::Auto => rc(),
+        };
+
+        let lost = self.control.source == ControlSource::Rc && source == ControlSource::Stopped;
+        self.control.source = source;
+        (command, lost.then_some(Log::RcSignalLost))
+    }
+
+    /// Returns the speeds of the wheels.
+    pub fn sensors(&self) -> Sensors {
+        let [fl_whl_rpm, fr_whl_rpm, rl_whl_rpm, rr_whl_rpm] = self.readings.speeds;
+        Sensors {
+            fl_whl_rpm,
+            fr_whl_rpm,
+            rl_whl_rpm,
+            rr_whl_rpm,
+        }
+    }
+
+    /// Returns the state of the battery.
+    pub fn battery(&self) -> Battery {
+        Battery {
+            voltage: self.readings.voltage,
+        }
+    }
+
+    /// Returns the ticks of the wheel encoders.
+    pub fn encoders(&self) -> Encoders {
+        let [fl, fr, rl, rr] = self.readings.ticks;
+        Encoders { fl, fr, rl, rr }
+    }
+
+    /// Returns the readings of the IMU.
+    pub fn imu(&self) -> Imu {
+        let imu = self.readings.imu.unwrap_or_default();
+        let [accel_x, accel_y, accel_z] = imu.accel;
+        let [gyro_x, gyro_y, gyro_z] = imu.gyro;
+        Imu {
+            accel_x,
+            accel_y,
+            accel_z,
+            gyro_x,
+            gyro_y,
+            gyro_z,
+            yaw_rate: self.yaw_rate.rate(),
+        }
+    }
+
+    /// Returns the distances to the obstacles.
+    pub fn proximity(&self) -> Proximity {
+        let [front, rear] = self.collision.distances();
+        Proximity { front, rear }
+    }
+
+    /// Returns the slips of the wheels and whether the traction control
+    /// reduces their speed.
+    pub fn traction(&self) -> Traction {
+        let [fl, fr, rl, rr] = self.traction.slips();
+        Traction {
+            fl,
+            fr,
+            rl,
+            rr,
+            active: self.params.traction_control && self.traction.is_slipping(),
+        }
+    }
+
+    /// Returns the pose dead-reckoned from the wheels.
+    pub fn odometry(&self) -> Odometry {
+        let (x, y) = self.odometry.position();
+        Odometry {
+            time: self.odometry.time(),
+            x,
+            y,
+            heading: self.odometry.heading(),
+            distance: self.odometry.distance(),
+        }
+    }
+
+    /// Returns the axles driven.
+    pub fn axles(&self) -> Axles {
+        let [front, rear] = self.enabled;
+        Axles {
+            mode: self.drive_mode,
+            front,
+            rear,
+        }
+    }
+
+    /// Returns the most urgent state of the Xmaxx at `now` (ms), for the
+    /// status LED.
+    pub fn status(&self, now: u32) -> Status {
+        let host_alive = self
+            .last_host_command
+            .is_some_and(|time| now.wrapping_sub(time) < HOST_TIMEOUT);
+
+        if self.overcurrent.is_cut() {
+            Status::Fault
+        } else if self.low_voltage.level() != Level::Normal {
+            Status::LowBattery
+        } else {
+            match self.control.source {
+                ControlSource::Stopped => Status::Timeout,
+                ControlSource::Rc => Status::Armed,
+                ControlSource::Host if host_alive => Status::Armed,
+                ControlSource::Host => Status::WaitingForHost,
+            }
+        }
+    }
+
+    /// Returns who drives.
+    pub fn control(&self) -> Control {
+        self.control
+    }
+
+    /// Returns the currents of the motors.
+    pub fn currents(&self) -> Currents {
+        let [fl, fr, rl, rr] = self.readings.currents;
+        Currents { fl, fr, rl, rr }
+    }
+
+    /// Returns the telemetry of an iteration of the main loop: the wheel
+    /// speeds and the odometry, then the next of the `slow` messages in
+    /// turn.
+    ///
+    /// So the serial port keeps up with the commands at 57600 baud, while
+    /// the odometry follows the control rate.
+    pub fn telemetry(&mut self, slow: &[Telemetry]) -> impl Iterator<Item = Info> {
+        let next = match slow.len() {
+            0 => None,
+            len => Some(slow[self.slow_sent % len]),
+        };
+        self.slow_sent = self.slow_sent.wrapping_add(1);
+
+        let slow = next.map(|telemetry| match telemetry {
+            Telemetry::Currents => Info::Currents(self.currents()),
+            Telemetry::Battery => Info::Battery(self.battery()),
+            Telemetry::Control => Info::Control(self.control()),
+            Telemetry::Traction => Info::Traction(self.traction()),
+            Telemetry::Axles => Info::Axles(self.axles()),
+            Telemetry::Encoders => Info::Encoders(self.encoders()),
+            Telemetry::Imu => Info::Imu(self.imu()),
+            Telemetry::Proximity => Info::Proximity(self.proximity()),
+        });
+        [
+            Some(Info::Sensors(self.sensors())),
+            Some(Info::Odometry(self.odometry())),
+            slow,
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+/// Returns a command stopping the wheels, straight.
+fn stop() -> Drive {
+    Drive {
+        steering: STEERING_ANGLE_ZERO,
+        ..Default::default()
+    }
+}
+
+/// Returns whether the axles are driven in the mode: front and rear.
+fn driven_axles(mode: DriveMode) -> [bool; 2] {
+    match mode {
+        DriveMode::AllWheel => [true, true],
+        DriveMode::FrontWheel => [true, false],
+        DriveMode::RearWheel => [false, true],
+        DriveMode::Coast => [false, false],
+    }
+}
+
+/// Returns the speeds of the wheels of the command: fl, fr, rl, rr.
+fn wheel_speeds(command: &Drive) -> [CentiRpm; 4] {
+    [
+        command.fl_whl_rpm,
+        command.fr_whl_rpm,
+        command.rl_whl_rpm,
+        command.rr_whl_rpm,
+    ]
+}
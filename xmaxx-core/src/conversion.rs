@@ -0,0 +1,92 @@
+//! Conversions between the physical units and the hardware's units.
+
+use core::ops::RangeInclusive;
+
+use xmaxx_messages::{CentiDegrees, CentiRpm, MilliAmps, MilliVolts};
+
+pub const SCALE: i32 = 100;
+pub const DUTY_CYCLE_DENOM: u16 = 1000;
+
+pub const STEERING_DUTY_MIN: i32 = 510; // 130 / 255 * 1000
+pub const STEERING_DUTY_ZERO: i32 = 745; // 190 / 255 * 1000
+pub const STEERING_DUTY_MAX: i32 = 980; // 250 / 255 * 1000
+pub const STEERING_ANGLE_MIN: CentiDegrees = CentiDegrees(40 * SCALE);
+pub const STEERING_ANGLE_MAX: CentiDegrees = CentiDegrees(140 * SCALE);
+pub const STEERING_ANGLE_ZERO: CentiDegrees = CentiDegrees(90 * SCALE); // straight
+pub const STEERING_ANGLE_RANGE: RangeInclusive<CentiDegrees> =
+    STEERING_ANGLE_MIN..=STEERING_ANGLE_MAX;
+
+/// Compute the duty cycle to achieve the desired angle.
+///
+/// It assumes that `angle` is in the steering range of motion.
+pub fn angle_to_duty(angle: CentiDegrees) -> u16 {
+    let delta_duty = STEERING_DUTY_MAX - STEERING_DUTY_MIN;
+    let delta_angle = STEERING_ANGLE_MAX.0 - STEERING_ANGLE_MIN.0;
+
+    // safe to cast: all positive and in range of u16
+    (delta_duty * (angle.0 - STEERING_ANGLE_MIN.0) / delta_angle + STEERING_DUTY_MIN) as u16
+}
+
+pub const MOTOR_DUTY_NUM_MIN: i32 = 100;
+pub const MOTOR_DUTY_NUM_ZERO: i32 = 500;
+pub const MOTOR_DUTY_NUM_MAX: i32 = 900;
+pub const RPM_MIN: CentiRpm = CentiRpm(-4500 * SCALE);
+pub const RPM_MAX: CentiRpm = CentiRpm(4500 * SCALE);
+pub const RPM_RANGE: RangeInclusive<CentiRpm> = RPM_MIN..=RPM_MAX;
+
+/// Computes the duty cycle to achieve the wheel speed.
+///
+/// It assumes that `rpm` is in the range.
+pub fn rpm_to_duty(rpm: CentiRpm) -> u16 {
+    let delta_duty = MOTOR_DUTY_NUM_MAX - MOTOR_DUTY_NUM_MIN;
+    let delta_rpm = RPM_MAX.0 - RPM_MIN.0;
+
+    (delta_duty * rpm.0 / delta_rpm + MOTOR_DUTY_NUM_ZERO) as u16
+}
+
+pub const ANALOG_ZERO_RPM: i32 = 412; // analog_unit
+                                      // const MAX_RPM: u16 = 4500;
+pub const ANALOG: i32 = 410; // half analog range
+pub const ANALOG_MAX: i32 = 1023; // 10 bits ADC
+pub const GEARING_10: i32 = 106; // 10.6 (motor) : 1 (wheel)
+pub const WHEEL_RADIUS: f32 = 0.1; // m
+pub const WHEEL_CIRCUMFERENCE: i32 = 628; // mm, 2 π WHEEL_RADIUS
+pub const WHEELBASE: i32 = 480; // mm
+pub const STEERING_RATIO_10: i32 = 6; // 0.6 (wheels) : 1 (servo)
+
+/// Computes the wheel speed from the analog reading.
+pub fn analog_to_rpm(analog: i32) -> CentiRpm {
+    //     (Fxp::from_num(MAX_RPM * (analog - ANALOG_ZERO_RPM))
+    //         / Fxp::from_num(GEARING)
+    //         / Fxp::from_num(ANALOG))
+    //     .to_num::<f32>()
+    analog_sum_to_rpm(analog, 1)
+}
+
+/// Computes the wheel speed from the sum of `samples` analog readings.
+///
+/// The sum is converted as a whole so the mean keeps the fraction of an
+/// analog unit that oversampling gains.
+pub fn analog_sum_to_rpm(sum: i32, samples: i32) -> CentiRpm {
+    // the numerator overflows an i32 from 8 samples
+    let delta = i64::from(sum - ANALOG_ZERO_RPM * samples);
+    let denom = i64::from(ANALOG * GEARING_10 / 10 * samples);
+    CentiRpm((i64::from(RPM_MAX.0) * delta / denom) as i32)
+}
+
+pub const ANALOG_ZERO_CURRENT: i32 = 412; // analog_unit
+pub const CURRENT_MAX: MilliAmps = MilliAmps(8000); // -8..=8 A
+
+/// Computes the motor current from the analog reading.
+pub fn analog_to_current(analog: i32) -> MilliAmps {
+    MilliAmps(CURRENT_MAX.0 * (analog - ANALOG_ZERO_CURRENT) / ANALOG)
+}
+
+pub const ANALOG_REFERENCE: MilliVolts = MilliVolts(5000);
+pub const BATTERY_DIVIDER: i32 = 8; // 1 : 8 voltage divider
+pub const BATTERY_MAX: MilliVolts = MilliVolts(ANALOG_REFERENCE.0 * BATTERY_DIVIDER); // 0..=40 V
+
+/// Computes the battery voltage from the analog reading.
+pub fn analog_to_voltage(analog: i32) -> MilliVolts {
+    MilliVolts(BATTERY_MAX.0 * analog / (ANALOG_MAX + 1))
+}
@@ -0,0 +1,77 @@
+//! The wheel encoders.
+
+use xmaxx_messages::CentiRpm;
+
+use crate::conversion::SCALE;
+
+/// The default ticks in a revolution of a wheel: a 12 counts per revolution
+/// motor encoder, counted on every edge of both channels, through the 10.6:1
+/// gearing.
+pub const ENCODER_RESOLUTION: i32 = 509;
+/// The most ticks in a revolution of a wheel.
+pub const MAX_ENCODER_RESOLUTION: i32 = 100_000;
+/// How many ticks are counted to measure a speed, so one tick more or less
+/// changes it by a few percents.
+pub const SPEED_TICKS: i32 = 16;
+/// The shortest time the ticks are counted to measure a speed, so a
+/// millisecond more or less changes it by 5% at most (ms).
+pub const MIN_SPEED_WINDOW: u32 = 20;
+/// The longest time the ticks are counted to measure a speed, after which a
+/// slow wheel is measured with fewer ticks (ms).
+pub const MAX_SPEED_WINDOW: u32 = 500;
+
+/// Returns the ticks between two states of the channels of a quadrature
+/// encoder, as `A << 1 | B`.
+///
+/// The channels go through 00, 01, 11 and 10 forwards. A state skipped
+/// because of a missed edge counts as no movement.
+pub fn quadrature_step(previous: u8, current: u8) -> i32 {
+    const STEPS: [i8; 16] = [0, 1, -1, 0, -1, 0, 0, 1, 1, 0, 0, -1, 0, -1, 1, 0];
+    STEPS[usize::from((previous & 0b11) << 2 | (current & 0b11))].into()
+}
+
+/// Measures the speed of a wheel from the ticks of its encoder.
+///
+/// The speed is updated with the ticks over each window, which lasts until
+/// the wheel turned [`SPEED_TICKS`], within [`MIN_SPEED_WINDOW`] and
+/// [`MAX_SPEED_WINDOW`]. So a fast wheel is measured often and a slow one
+/// precisely.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EncoderSpeed {
+    /// The ticks and the time (ms) at the start of the window, once there was
+    /// a reading.
+    start: Option<(i32, u32)>,
+    speed: CentiRpm,
+}
+
+impl EncoderSpeed {
+    /// Returns a measurement of a wheel at rest.
+    pub fn new() -> Self {
+        Self {
+            start: None,
+            speed: CentiRpm(0),
+        }
+    }
+
+    /// Updates the measurement with the ticks at `now` (ms) and returns the
+    /// speed.
+    pub fn update(&mut self, now: u32, ticks: i32, resolution: i32) -> CentiRpm {
+        let Some((start_ticks, start)) = self.start else {
+            self.start = Some((ticks, now));
+            return self.speed;
+        };
+
+        let elapsed = now.wrapping_sub(start);
+        let delta = ticks.wrapping_sub(start_ticks);
+        let enough = delta.saturating_abs() >= SPEED_TICKS && elapsed >= MIN_SPEED_WINDOW;
+        if enough || elapsed >= MAX_SPEED_WINDOW {
+            // ticks per ms to hundredths of revolutions per minute
+            let delta = i64::from(delta) * 60_000 * i64::from(SCALE);
+            let speed = delta / (i64::from(resolution) * i64::from(elapsed));
+            self.speed = CentiRpm(speed as i32);
+            self.start = Some((ticks, now));
+        }
+
+        self.speed
+    }
+}
@@ -0,0 +1,41 @@
+//! The filtering of the noisy readings of the sensors.
+
+/// The fractional bits of the state of a filter, so a smooth filter does
+/// not lose the small changes of its input.
+const FRACTION: u32 = 8;
+
+/// The largest smoothing of a [`LowPass`].
+pub const MAX_SHIFT: u8 = 8;
+
+/// A first-order low-pass filter, or exponential moving average, on
+/// integers.
+///
+/// Each sample moves the output by 1/2^shift of its distance to it, so a
+/// larger shift smooths more and lags more. A shift of 0 lets the samples
+/// through. The output starts at the first sample rather than ramping up
+/// from zero.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LowPass {
+    /// The output, with [`FRACTION`] bits, once there was a sample.
+    state: Option<i32>,
+}
+
+impl LowPass {
+    /// Returns a filter without samples.
+    pub fn new() -> Self {
+        Self { state: None }
+    }
+
+    /// Filters the sample, within ±2^23, and returns the output.
+    pub fn update(&mut self, sample: i32, shift: u8) -> i32 {
+        let sample = sample << FRACTION;
+        let state = match self.state {
+            Some(state) => state + ((sample - state) >> shift.min(MAX_SHIFT)),
+            None => sample,
+        };
+        self.state = Some(state);
+
+        // round to the nearest
+        (state + (1 << (FRACTION - 1))) >> FRACTION
+    }
+}
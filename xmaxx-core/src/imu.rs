@@ -0,0 +1,295 @@
+//! The inertial measurement unit, an MPU-6050 on I2C.
+//!
+//! The driver only uses the I2C trait of `embedded-hal`, so it runs on the
+//! Arduino and is tested on the host against a mock bus. The MPU-6500 and
+//! MPU-9250 have the same registers and are accepted too.
+
+use embedded_hal::i2c::I2c;
+
+use xmaxx_messages::{CentiDegreesPerSecond, Log, MilliG};
+
+use crate::conversion::SCALE;
+use crate::filter::LowPass;
+
+/// The I2C address of the IMU with its AD0 pin low.
+pub const IMU_ADDRESS: u8 = 0x68;
+/// The default sample rate of the IMU (Hz).
+pub const IMU_RATE: u16 = 100;
+/// The lowest sample rate of the IMU (Hz).
+pub const MIN_IMU_RATE: u16 = 4;
+/// The highest sample rate of the IMU (Hz).
+pub const MAX_IMU_RATE: u16 = 1000;
+
+const SMPLRT_DIV: u8 = 0x19;
+const CONFIG: u8 = 0x1A;
+const GYRO_CONFIG: u8 = 0x1B;
+const ACCEL_CONFIG: u8 = 0x1C;
+const ACCEL_XOUT_H: u8 = 0x3B;
+const PWR_MGMT_1: u8 = 0x6B;
+const WHO_AM_I: u8 = 0x75;
+
+/// The identities of the MPU-6050, MPU-6500 and MPU-9250.
+const IDENTITIES: [u8; 3] = [0x68, 0x70, 0x71];
+/// Wakes the IMU up, clocked by the X gyroscope which is more stable than
+/// its internal oscillator.
+const CLOCK_PLL_X_GYRO: u8 = 0x01;
+/// The ±250 deg/s range of the gyroscopes, 131 LSB per deg/s.
+const GYRO_RANGE_250: u8 = 0b00 << 3;
+const GYRO_LSB_PER_DEGREE_PER_SECOND: i32 = 131;
+/// The ±4 g range of the accelerometers, 8192 LSB per g.
+const ACCEL_RANGE_4G: u8 = 0b01 << 3;
+const ACCEL_LSB_PER_G: i32 = 8192;
+/// The rate of the gyroscopes with their low-pass filter on (Hz).
+const GYRO_OUTPUT_RATE: u16 = 1000;
+/// The bandwidths of the low-pass filters of the IMU (Hz), by their setting.
+const LOW_PASS_BANDWIDTHS: [(u8, u16); 6] = [(1, 188), (2, 98), (3, 42), (4, 20), (5, 10), (6, 5)];
+
+/// How long the IMU is left alone after it failed to be set up (ms).
+pub const IMU_RETRY: u32 = 100;
+
+/// The smoothing of the bias of the gyroscope, see [`LowPass`].
+pub const GYRO_BIAS_FILTER: u8 = 7;
+
+/// An error of the IMU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<E> {
+    /// The I2C bus failed, or the IMU did not acknowledge.
+    I2c(E),
+    /// Another device answered at the address, with this identity.
+    UnknownDevice(u8),
+}
+
+impl<E> From<E> for Error<E> {
+    fn from(error: E) -> Self {
+        Self::I2c(error)
+    }
+}
+
+/// The readings of the IMU, in its frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImuReading {
+    /// The accelerations: x, y, z.
+    pub accel: [MilliG; 3],
+    /// The rotation rates: x, y, z.
+    pub gyro: [CentiDegreesPerSecond; 3],
+}
+
+/// Returns the divider of the gyroscope output rate closest to `rate`
+/// (Hz), within [`MIN_IMU_RATE`] and [`MAX_IMU_RATE`].
+pub fn sample_rate_divider(rate: u16) -> u8 {
+    let rate = rate.clamp(MIN_IMU_RATE, MAX_IMU_RATE);
+    // safe to cast: at most 1000 / 4 - 1
+    (GYRO_OUTPUT_RATE / rate - 1) as u8
+}
+
+/// Returns the setting of the widest low-pass filter below the Nyquist
+/// frequency of `rate` (Hz), or the narrowest one.
+pub fn low_pass_config(rate: u16) -> u8 {
+    LOW_PASS_BANDWIDTHS
+        .iter()
+        .find(|(_, bandwidth)| 2 * bandwidth <= rate)
+        .unwrap_or(&LOW_PASS_BANDWIDTHS[LOW_PASS_BANDWIDTHS.len() - 1])
+        .0
+}
+
+/// An MPU-6050 on an I2C bus.
+pub struct Mpu6050<I2C> {
+    i2c: I2C,
+    address: u8,
+}
+
+impl<I2C: I2c> Mpu6050<I2C> {
+    /// Returns the IMU at `address` on the bus, before it is initialized.
+    pub fn new(i2c: I2C, address: u8) -> Self {
+        Self { i2c, address }
+    }
+
+    /// Checks the identity of the IMU, wakes it up and sets its ranges and
+    /// its sample rate (Hz).
+    pub fn init(&mut self, rate: u16) -> Result<(), Error<I2C::Error>> {
+        let mut identity = [0];
+        self.i2c
+            .write_read(self.address, &[WHO_AM_I], &mut identity)?;
+        if !IDENTITIES.contains(&identity[0]) {
+            return Err(Error::UnknownDevice(identity[0]));
+        }
+
+        self.write(PWR_MGMT_1, CLOCK_PLL_X_GYRO)?;
+        self.write(GYRO_CONFIG, GYRO_RANGE_250)?;
+        self.write(ACCEL_CONFIG, ACCEL_RANGE_4G)?;
+        self.set_rate(rate)
+    }
+
+    /// Sets the sample rate (Hz) and a low-pass filter to avoid aliasing.
+    pub fn set_rate(&mut self, rate: u16) -> Result<(), Error<I2C::Error>> {
+        self.write(CONFIG, low_pass_config(rate))?;
+        self.write(SMPLRT_DIV, sample_rate_divider(rate))
+    }
+
+    /// Reads the last sample.
+    pub fn read(&mut self) -> Result<ImuReading, Error<I2C::Error>> {
+        // accelerometers, temperature and gyroscopes, big-endian
+        let mut bytes = [0; 14];
+        self.i2c
+            .write_read(self.address, &[ACCEL_XOUT_H], &mut bytes)?;
+        let raw = |i: usize| i32::from(i16::from_be_bytes([bytes[2 * i], bytes[2 * i + 1]]));
+
+        // safe to cast: the ranges fit in i16
+        let accel = |i| MilliG((raw(i) * 1000 / ACCEL_LSB_PER_G) as i16);
+        let gyro =
+            |i| CentiDegreesPerSecond((raw(i) * SCALE / GYRO_LSB_PER_DEGREE_PER_SECOND) as i16);
+        Ok(ImuReading {
+            accel: [accel(0), accel(1), accel(2)],
+            gyro: [gyro(4), gyro(5), gyro(6)],
+        })
+    }
+
+    /// Returns the bus.
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    fn write(&mut self, register: u8, value: u8) -> Result<(), Error<I2C::Error>> {
+        self.i2c.write(self.address, &[register, value])?;
+        Ok(())
+    }
+}
+
+/// Reads the IMU in the main loop, following the sample rate of the
+/// parameters.
+///
+/// The Xmaxx keeps driving without the IMU: its initialization and a new
+/// sample rate are retried every [`IMU_RETRY`], and [`Log::ImuError`] is
+/// only returned when the IMU stops answering, not at every failed read.
+pub struct ImuReader<I2C> {
+    imu: Mpu6050<I2C>,
+    /// Whether the IMU was initialized.
+    initialized: bool,
+    /// The sample rate to set (Hz).
+    rate: u16,
+    /// Whether the sample rate changed since it was last set.
+    rate_pending: bool,
+    /// When setting up the IMU last failed (ms).
+    last_failure: Option<u32>,
+    /// Whether the IMU answered last, so its errors are logged once.
+    answering: bool,
+}
+
+impl<I2C: I2c> ImuReader<I2C> {
+    /// Returns a reader of the IMU, initialized at its first update with
+    /// the sample rate (Hz).
+    pub fn new(imu: Mpu6050<I2C>, rate: u16) -> Self {
+        Self {
+            imu,
+            initialized: false,
+            rate,
+            rate_pending: false,
+            last_failure: None,
+            // so that a first failure is logged
+            answering: true,
+        }
+    }
+
+    /// Sets the IMU up if needed and reads it at `now` (ms) with the sample
+    /// rate (Hz).
+    ///
+    /// It returns the reading, if any, and [`Log::ImuError`] if the IMU
+    /// just stopped answering.
+    pub fn update(&mut self, now: u32, rate: u16) -> (Option<ImuReading>, Option<Log>) {
+        if rate != self.rate {
+            self.rate = rate;
+            self.rate_pending = true;
+        }
+
+        let mut failed = false;
+        let waiting = self
+            .last_failure
+            .is_some_and(|time| now.wrapping_sub(time) < IMU_RETRY);
+        if !waiting && (!self.initialized || self.rate_pending) {
+            let result = if self.initialized {
+                self.imu.set_rate(self.rate)
+            } else {
+                self.imu.init(self.rate)
+            };
+            match result {
+                Ok(()) => {
+                    self.initialized = true;
+                    self.rate_pending = false;
+                    self.last_failure = None;
+                }
+                Err(_) => {
+                    self.last_failure = Some(now);
+                    failed = true;
+                }
+            }
+        }
+
+        // still read at the previous rate if the new one could not be set
+        let reading = if self.initialized {
+            self.imu.read().ok()
+        } else {
+            None
+        };
+        failed |= reading.is_none();
+
+        let log = (failed && self.answering).then_some(Log::ImuError);
+        self.answering = !failed;
+        (reading, log)
+    }
+
+    /// Returns the IMU.
+    pub fn release(self) -> Mpu6050<I2C> {
+        self.imu
+    }
+}
+
+/// Measures the yaw rate with the gyroscope around z.
+///
+/// A gyroscope reads a small rate at rest, its bias, which drifts with its
+/// temperature. The bias is followed while the Xmaxx is at rest and removed
+/// from the readings.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct YawRate {
+    filter: LowPass,
+    bias: i32,
+    rate: CentiDegreesPerSecond,
+}
+
+impl YawRate {
+    /// Returns a measurement without bias.
+    pub fn new() -> Self {
+        Self {
+            filter: LowPass::new(),
+            bias: 0,
+            rate: CentiDegreesPerSecond(0),
+        }
+    }
+
+    /// Updates the measurement with a reading of the gyroscope around z and
+    /// returns the yaw rate.
+    pub fn update(
+        &mut self,
+        gyro_z: CentiDegreesPerSecond,
+        at_rest: bool,
+    ) -> CentiDegreesPerSecond {
+        if at_rest {
+            self.bias = self.filter.update(gyro_z.0.into(), GYRO_BIAS_FILTER);
+        }
+
+        let rate = i32::from(gyro_z.0) - self.bias;
+        // safe to cast: clamped to i16
+        self.rate = CentiDegreesPerSecond(rate.clamp(i16::MIN.into(), i16::MAX.into()) as i16);
+        self.rate
+    }
+
+    /// Returns the bias of the gyroscope.
+    pub fn bias(&self) -> CentiDegreesPerSecond {
+        // safe to cast: a mean of i16
+        CentiDegreesPerSecond(self.bias as i16)
+    }
+
+    /// Returns the last yaw rate.
+    pub fn rate(&self) -> CentiDegreesPerSecond {
+        self.rate
+    }
+}
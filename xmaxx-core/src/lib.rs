@@ -0,0 +1,26 @@
+#![no_std]
+
+//! The logic of the firmware that does not depend on the hardware.
+//!
+//! It is shared by the firmware and the simulator, so the simulator runs
+//! the same command handling and protections as the Arduino.
+
+pub mod collision;
+pub mod controller;
+pub mod conversion;
+pub mod encoder;
+pub mod filter;
+pub mod imu;
+pub mod low_voltage;
+pub mod odometry;
+pub mod overcurrent;
+pub mod params;
+pub mod rc;
+pub mod readbuf;
+pub mod serial;
+pub mod stability;
+pub mod status;
+pub mod traction;
+
+pub use controller::*;
+pub use params::*;
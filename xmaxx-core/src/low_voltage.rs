@@ -0,0 +1,128 @@
+//! The protection of the battery against over-discharge.
+
+use xmaxx_messages::{CentiRpm, Log, MilliVolts};
+
+use crate::overcurrent::FULL_SPEED;
+
+/// The share of the speed limit left when the battery is low (‰).
+pub const LOW_BATTERY_SPEED: i32 = 500;
+/// How far above a threshold the battery must go back to leave it.
+pub const BATTERY_HYSTERESIS: MilliVolts = MilliVolts(400);
+/// How long the battery must stay below a threshold to cross it, so the
+/// sag of an acceleration does not trip it (ms).
+pub const BATTERY_DELAY: u32 = 1000;
+
+/// The charge of the battery, from the thresholds it crossed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Normal,
+    /// Below the warning voltage.
+    Low,
+    /// Below the cutoff voltage.
+    Cutoff,
+}
+
+/// Limits the speed when the battery is low and disarms the motors when it
+/// is empty.
+///
+/// A threshold is only crossed downwards after [`BATTERY_DELAY`], and
+/// upwards [`BATTERY_HYSTERESIS`] above it. The disarmed motors stay
+/// disarmed until the battery recovers and every speed is commanded to
+/// zero, so they do not restart by surprise.
+pub struct LowVoltage {
+    level: Level,
+    /// When the battery went below the threshold of a lower level, if it is
+    /// below.
+    below_since: Option<u32>,
+    disarmed: bool,
+}
+
+impl LowVoltage {
+    /// Returns the protection of a charged battery.
+    pub fn new() -> Self {
+        Self {
+            level: Level::Normal,
+            below_since: None,
+            disarmed: false,
+        }
+    }
+
+    /// Updates the protection with the voltage of the battery at `now` (ms)
+    /// and returns a log when it crosses a threshold downwards.
+    pub fn update(
+        &mut self,
+        now: u32,
+        voltage: MilliVolts,
+        warning: MilliVolts,
+        cutoff: MilliVolts,
+    ) -> Option<Log> {
+        let lower = if voltage < cutoff {
+            Level::Cutoff
+        } else if voltage < warning {
+            Level::Low
+        } else {
+            Level::Normal
+        };
+
+        if lower <= self.level {
+            self.below_since = None;
+            let higher = if voltage.0 > warning.0 + BATTERY_HYSTERESIS.0 {
+                Level::Normal
+            } else if voltage.0 > cutoff.0 + BATTERY_HYSTERESIS.0 {
+                Level::Low
+            } else {
+                Level::Cutoff
+            };
+            self.level = self.level.min(higher);
+            return None;
+        }
+
+        let since = *self.below_since.get_or_insert(now);
+        if now.wrapping_sub(since) < BATTERY_DELAY {
+            return None;
+        }
+        self.below_since = None;
+        self.level = lower;
+        match lower {
+            Level::Cutoff => {
+                self.disarmed = true;
+                Some(Log::BatteryCutoff)
+            }
+            _ => Some(Log::LowBattery),
+        }
+    }
+
+    /// Rearms the motors if the battery recovered and every speed is
+    /// commanded to zero: fl, fr, rl, rr.
+    pub fn rearm(&mut self, speeds: [CentiRpm; 4]) {
+        if self.level != Level::Cutoff && speeds.iter().all(|speed| speed.0 == 0) {
+            self.disarmed = false;
+        }
+    }
+
+    /// Returns the share of the speed limit left (‰).
+    pub fn speed(&self) -> i32 {
+        match self.level {
+            _ if self.disarmed => 0,
+            Level::Normal => FULL_SPEED,
+            Level::Low => LOW_BATTERY_SPEED,
+            Level::Cutoff => 0,
+        }
+    }
+
+    /// Returns the charge of the battery.
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    /// Returns whether the motors are disarmed.
+    pub fn is_disarmed(&self) -> bool {
+        self.disarmed
+    }
+}
+
+impl Default for LowVoltage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
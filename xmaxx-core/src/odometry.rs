@@ -0,0 +1,181 @@
+//! The odometry, the pose of the Xmaxx dead-reckoned from its wheels.
+//!
+//! The Xmaxx moves like a bicycle around the center of its rear axle, at
+//! the mean speed of the rear wheels and turning with the steering
+//! commanded. The pose is integrated in steps of [`ODOMETRY_PERIOD`], with
+//! the speeds held between two updates, so each pose is at an exact time
+//! whatever the jitter of the main loop.
+//!
+//! The firmware has no floating-point unit, so the positions are integers
+//! of nanometers and the heading a fraction of a turn on 32 bits, which
+//! wraps around by itself.
+
+use xmaxx_messages::{CentiDegrees, CentiDegreesPerSecond, CentiRpm, MilliMeters};
+
+use crate::conversion::*;
+
+/// The step of the integration (ms).
+///
+/// It is a multiple of the 1 ms tick of the clock of the firmware, so the
+/// steps line up with the ticks.
+pub const ODOMETRY_PERIOD: u32 = 10;
+
+/// The sines from 0 to 90 deg in 64 steps, scaled by [`ONE`].
+const SINES: [i32; 65] = [
+    0, 402, 804, 1205, 1606, 2006, 2404, 2801, 3196, 3590, 3981, 4370, 4756, 5139, 5520, 5897,
+    6270, 6639, 7005, 7366, 7723, 8076, 8423, 8765, 9102, 9434, 9760, 10080, 10394, 10702, 11003,
+    11297, 11585, 11866, 12140, 12406, 12665, 12916, 13160, 13395, 13623, 13842, 14053, 14256,
+    14449, 14635, 14811, 14978, 15137, 15286, 15426, 15557, 15679, 15791, 15893, 15986, 16069,
+    16143, 16207, 16261, 16305, 16340, 16364, 16379, 16384,
+];
+/// The scale of the sines.
+const ONE: i64 = 1 << 14;
+/// A quarter turn.
+const QUARTER_TURN: u32 = 1 << 30;
+/// The turns per radian, scaled by 2^32 / [`ONE`]: 2^18 / 2π.
+const TURNS_PER_RADIAN: i64 = 41_722;
+/// The hundredths of degrees in a radian.
+const CENTIDEGREES_PER_RADIAN: i64 = 5730;
+
+/// Returns the sine of `angle` (turns on 32 bits), scaled by [`ONE`].
+fn sin(angle: u32) -> i64 {
+    // mirrored into the first quadrant
+    let position = angle % QUARTER_TURN;
+    let position = if (angle / QUARTER_TURN) % 2 == 1 {
+        QUARTER_TURN - position
+    } else {
+        position
+    };
+
+    // linear between the sines of the table
+    let index = (position >> 24) as usize;
+    let fraction = i64::from(position >> 8 & 0xFFFF);
+    let (low, high) = (SINES[index], SINES[(index + 1).min(64)]);
+    let sine = i64::from(low) + i64::from(high - low) * fraction / 0x1_0000;
+
+    if angle / QUARTER_TURN >= 2 {
+        -sine
+    } else {
+        sine
+    }
+}
+
+/// Returns the cosine of `angle` (turns on 32 bits), scaled by [`ONE`].
+fn cos(angle: u32) -> i64 {
+    sin(angle.wrapping_add(QUARTER_TURN))
+}
+
+/// Returns the angle of the front wheels, to the left, for the steering
+/// (turns on 32 bits).
+fn wheel_angle(steering: CentiDegrees) -> u32 {
+    let angle = (STEERING_ANGLE_ZERO.0 - steering.0) * STEERING_RATIO_10 / 10;
+    // wraps the negative angles to the end of the turn
+    (i64::from(angle) * (1 << 32) / 36_000) as u32
+}
+
+/// Returns the yaw rate of the Xmaxx turning without slipping, counterclockwise,
+/// at the speeds of the wheels: fl, fr, rl, rr, and the steering.
+pub fn kinematic_yaw_rate(speeds: [CentiRpm; 4], steering: CentiDegrees) -> CentiDegreesPerSecond {
+    let rpm = (i64::from(speeds[2].0) + i64::from(speeds[3].0)) / 2;
+    let angle = wheel_angle(steering);
+    let tan = sin(angle) * ONE / cos(angle);
+    // from centi-rpm to mm/s, and across the wheelbase
+    let rate = rpm * i64::from(WHEEL_CIRCUMFERENCE) * tan * CENTIDEGREES_PER_RADIAN
+        / (6000 * ONE * i64::from(WHEELBASE));
+    // safe to cast: clamped to i16
+    CentiDegreesPerSecond(rate.clamp(i16::MIN.into(), i16::MAX.into()) as i16)
+}
+
+/// Dead-reckons the pose of the Xmaxx.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeadReckoning {
+    /// The position forwards from the origin (nm).
+    x: i64,
+    /// The position to the left of the origin (nm).
+    y: i64,
+    /// The heading counterclockwise from the origin (turns on 32 bits).
+    heading: u32,
+    /// The distance traveled, forwards and backwards (nm).
+    distance: i64,
+    /// The time of the pose (ms), from the first update.
+    time: Option<u32>,
+}
+
+impl DeadReckoning {
+    /// Returns the odometry at the origin.
+    pub fn new() -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            heading: 0,
+            distance: 0,
+            time: None,
+        }
+    }
+
+    /// Moves the pose back to the origin and the distance to zero, at the
+    /// time of the pose.
+    pub fn reset(&mut self) {
+        *self = Self {
+            time: self.time,
+            ..Self::new()
+        };
+    }
+
+    /// Integrates the pose up to `now` (ms) with the speeds of the wheels:
+    /// fl, fr, rl, rr, and the steering.
+    pub fn update(&mut self, now: u32, speeds: [CentiRpm; 4], steering: CentiDegrees) {
+        let Some(mut time) = self.time else {
+            self.time = Some(now);
+            return;
+        };
+
+        // the mean speed of the rear wheels, which do not steer
+        let rpm = (i64::from(speeds[2].0) + i64::from(speeds[3].0)) / 2;
+        // over a step (nm), from centi-rpm and ms
+        let step = rpm * i64::from(WHEEL_CIRCUMFERENCE) * i64::from(ODOMETRY_PERIOD) / 6;
+        let angle = wheel_angle(steering);
+        let tan = sin(angle) * ONE / cos(angle);
+        // the heading turns by much less than a turn in a step
+        let turn = step * tan * TURNS_PER_RADIAN / (i64::from(WHEELBASE) * 1_000_000);
+
+        while now.wrapping_sub(time) >= ODOMETRY_PERIOD {
+            // along the heading in the middle of the step, and the casts wrap
+            // the negative turns to the end of the turn
+            let heading = self.heading.wrapping_add((turn / 2) as u32);
+            self.x += step * cos(heading) / ONE;
+            self.y += step * sin(heading) / ONE;
+            self.heading = self.heading.wrapping_add(turn as u32);
+            self.distance += step.abs();
+            time = time.wrapping_add(ODOMETRY_PERIOD);
+        }
+        self.time = Some(time);
+    }
+
+    /// Returns the time of the pose (ms).
+    pub fn time(&self) -> u32 {
+        self.time.unwrap_or(0)
+    }
+
+    /// Returns the position: forwards and to the left of the origin.
+    pub fn position(&self) -> (MilliMeters, MilliMeters) {
+        // safe to cast: unless it travels farther than 2000 km
+        (
+            MilliMeters((self.x / 1_000_000) as i32),
+            MilliMeters((self.y / 1_000_000) as i32),
+        )
+    }
+
+    /// Returns the heading counterclockwise from the origin, from -180 to
+    /// 180 deg.
+    pub fn heading(&self) -> CentiDegrees {
+        // safe to cast: less than half a turn
+        CentiDegrees(((i64::from(self.heading as i32) * 36_000) >> 32) as i32)
+    }
+
+    /// Returns the distance traveled, forwards and backwards.
+    pub fn distance(&self) -> MilliMeters {
+        // safe to cast: unless it travels farther than 2000 km
+        MilliMeters((self.distance / 1_000_000) as i32)
+    }
+}
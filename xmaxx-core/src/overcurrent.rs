@@ -0,0 +1,108 @@
+//! The protection of the motors against overcurrent.
+
+use xmaxx_messages::{CentiRpm, Log, MilliAmps};
+
+/// The share of the commanded speed of a motor that is not derated (‰).
+pub const FULL_SPEED: i32 = 1000;
+/// How long a motor can stay above its current limit before it is cut (ms).
+pub const OVERCURRENT_DELAY: u32 = 500;
+/// The share of the speed recovered by a derated motor in each iteration
+/// below its limit (‰).
+const RECOVERY: i32 = 5;
+
+/// The state of the protection of a motor.
+#[derive(Clone, Copy)]
+struct Motor {
+    /// The share of the commanded speed it can have (‰).
+    speed: i32,
+    /// When it went above its limit, if it is above.
+    over_since: Option<u32>,
+    /// Whether it is cut.
+    cut: bool,
+}
+
+/// Derates the motors drawing more than their limit and cuts them if they
+/// stay above it.
+///
+/// A derated motor recovers its speed slowly once below its limit. A cut
+/// motor stays cut until its speed is commanded to zero, so it does not
+/// restart by surprise.
+pub struct Overcurrent {
+    /// The motors: fl, fr, rl, rr.
+    motors: [Motor; 4],
+}
+
+impl Overcurrent {
+    /// Returns the protection with no motor derated.
+    pub fn new() -> Self {
+        Self {
+            motors: [Motor {
+                speed: FULL_SPEED,
+                over_since: None,
+                cut: false,
+            }; 4],
+        }
+    }
+
+    /// Updates the protection with the currents of the motors at `now` (ms)
+    /// and returns [`Log::Overcurrent`] when a motor is cut.
+    pub fn update(
+        &mut self,
+        now: u32,
+        currents: [MilliAmps; 4],
+        limits: [MilliAmps; 4],
+    ) -> Option<Log> {
+        let mut log = None;
+
+        for ((motor, current), limit) in self.motors.iter_mut().zip(currents).zip(limits) {
+            let current = current.0.saturating_abs();
+            if current > limit.0 {
+                // slow down in proportion to the excess
+                motor.speed = motor.speed.min(FULL_SPEED * limit.0 / current);
+                let since = *motor.over_since.get_or_insert(now);
+                if !motor.cut && now.wrapping_sub(since) >= OVERCURRENT_DELAY {
+                    motor.cut = true;
+                    log = Some(Log::Overcurrent);
+                }
+            } else {
+                motor.over_since = None;
+                motor.speed = (motor.speed + RECOVERY).min(FULL_SPEED);
+            }
+        }
+
+        log
+    }
+
+    /// Reconnects the cut motors whose speed is commanded to zero: fl, fr,
+    /// rl, rr.
+    pub fn rearm(&mut self, speeds: [CentiRpm; 4]) {
+        for (motor, speed) in self.motors.iter_mut().zip(speeds) {
+            if speed.0 == 0 {
+                motor.cut = false;
+            }
+        }
+    }
+
+    /// Returns the share of the commanded speed each motor can have (‰): fl,
+    /// fr, rl, rr.
+    pub fn speeds(&self) -> [i32; 4] {
+        self.motors
+            .map(|motor| if motor.cut { 0 } else { motor.speed })
+    }
+
+    /// Returns whether each motor is cut: fl, fr, rl, rr.
+    pub fn cuts(&self) -> [bool; 4] {
+        self.motors.map(|motor| motor.cut)
+    }
+
+    /// Returns whether any motor is cut.
+    pub fn is_cut(&self) -> bool {
+        self.motors.iter().any(|motor| motor.cut)
+    }
+}
+
+impl Default for Overcurrent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,169 @@
+use xmaxx_messages::{CentiRpm, Log, MilliAmps, MilliMeters, MilliVolts, Param, PerMille};
+
+use crate::collision::{RANGE_MAX, SLOW_DISTANCE, STOP_DISTANCE};
+use crate::conversion::{BATTERY_MAX, CURRENT_MAX, RPM_MAX};
+use crate::encoder::{ENCODER_RESOLUTION, MAX_ENCODER_RESOLUTION};
+use crate::filter::MAX_SHIFT;
+use crate::imu::{IMU_RATE, MAX_IMU_RATE, MIN_IMU_RATE};
+use crate::rc::RC_SPEED;
+use crate::traction::{MAX_SLIP_THRESHOLD, SLIP_THRESHOLD};
+
+/// The default current limit of the motors.
+pub const CURRENT_LIMIT: MilliAmps = MilliAmps(6000);
+/// The default warning voltage of the battery, 3.5 V a cell of an 8S LiPo.
+pub const BATTERY_WARNING: MilliVolts = MilliVolts(28_000);
+/// The default cutoff voltage of the battery, 3.3 V a cell of an 8S LiPo.
+pub const BATTERY_CUTOFF: MilliVolts = MilliVolts(26_400);
+/// The default number of readings averaged into each wheel speed.
+pub const SPEED_OVERSAMPLING: u8 = 4;
+/// The most readings averaged into each wheel speed.
+pub const MAX_OVERSAMPLING: u8 = 16;
+/// The default smoothing of the wheel speeds, see [`crate::filter::LowPass`].
+pub const SPEED_FILTER: u8 = 2;
+
+/// Where the wheel speeds come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeedSource {
+    /// The analog outputs of the motor drivers.
+    Analog = 0,
+    /// The wheel encoders.
+    Encoders = 1,
+}
+
+/// Parameters of the firmware, see [`Param`].
+pub struct Params {
+    pub rpm_limit: CentiRpm,
+    /// The current limits of the motors: fl, fr, rl, rr.
+    pub current_limits: [MilliAmps; 4],
+    pub battery_warning: MilliVolts,
+    pub battery_cutoff: MilliVolts,
+    pub speed_oversampling: u8,
+    pub speed_filter: u8,
+    pub encoder_resolution: i32,
+    pub speed_source: SpeedSource,
+    /// The sample rate of the IMU (Hz).
+    pub imu_rate: u16,
+    /// The wheel speed at full throttle on the RC transmitter.
+    pub rc_speed: CentiRpm,
+    /// The distance to an obstacle at which the wheels stop towards it.
+    pub stop_distance: MilliMeters,
+    /// The distance to an obstacle from which the speed towards it is
+    /// limited.
+    pub slow_distance: MilliMeters,
+    /// Whether the speed of the slipping wheels is reduced.
+    pub traction_control: bool,
+    /// The slip above which a wheel is slipping.
+    pub slip_threshold: PerMille,
+    /// Whether the speed is reduced when the Xmaxx does not turn as
+    /// steered.
+    pub stability_control: bool,
+    /// Whether the Xmaxx has an IMU, which the stability control needs.
+    /// It is not a parameter but set by the firmware.
+    pub has_imu: bool,
+}
+
+impl Params {
+    /// Returns the default parameters.
+    pub fn new() -> Self {
+        Self {
+            rpm_limit: RPM_MAX,
+            current_limits: [CURRENT_LIMIT; 4],
+            battery_warning: BATTERY_WARNING,
+            battery_cutoff: BATTERY_CUTOFF,
+            speed_oversampling: SPEED_OVERSAMPLING,
+            speed_filter: SPEED_FILTER,
+            encoder_resolution: ENCODER_RESOLUTION,
+            speed_source: SpeedSource::Analog,
+            imu_rate: IMU_RATE,
+            rc_speed: RC_SPEED,
+            stop_distance: STOP_DISTANCE,
+            slow_distance: SLOW_DISTANCE,
+            traction_control: false,
+            slip_threshold: SLIP_THRESHOLD,
+            stability_control: false,
+            has_imu: false,
+        }
+    }
+
+    /// Returns the value of the parameter.
+    pub fn get(&self, param: Param) -> i32 {
+        match param {
+            Param::RpmLimit => self.rpm_limit.0,
+            Param::FlCurrentLimit => self.current_limits[0].0,
+            Param::FrCurrentLimit => self.current_limits[1].0,
+            Param::RlCurrentLimit => self.current_limits[2].0,
+            Param::RrCurrentLimit => self.current_limits[3].0,
+            Param::BatteryWarning => self.battery_warning.0,
+            Param::BatteryCutoff => self.battery_cutoff.0,
+            Param::SpeedOversampling => self.speed_oversampling.into(),
+            Param::SpeedFilter => self.speed_filter.into(),
+            Param::EncoderResolution => self.encoder_resolution,
+            Param::SpeedSource => self.speed_source as i32,
+            Param::ImuRate => self.imu_rate.into(),
+            Param::RcSpeed => self.rc_speed.0,
+            Param::StopDistance => self.stop_distance.0,
+            Param::SlowDistance => self.slow_distance.0,
+            Param::TractionControl => self.traction_control.into(),
+            Param::SlipThreshold => self.slip_threshold.0.into(),
+            Param::StabilityControl => self.stability_control.into(),
+        }
+    }
+
+    /// Sets the parameter if the value is valid.
+    pub fn set(&mut self, param: Param, value: i32) -> Result<(), Log> {
+        let speed = (0..=RPM_MAX.0).contains(&value);
+        let current = (0..=CURRENT_MAX.0).contains(&value);
+        let voltage = (0..=BATTERY_MAX.0).contains(&value);
+        let oversampling = (1..=MAX_OVERSAMPLING.into()).contains(&value);
+        let filter = (0..=MAX_SHIFT.into()).contains(&value);
+        let resolution = (1..=MAX_ENCODER_RESOLUTION).contains(&value);
+        let distance = (0..=RANGE_MAX.0).contains(&value);
+        let imu_rate = (MIN_IMU_RATE.into()..=MAX_IMU_RATE.into()).contains(&value);
+        let slip = (0..=MAX_SLIP_THRESHOLD.0.into()).contains(&value);
+
+        match param {
+            Param::RpmLimit if speed => self.rpm_limit = CentiRpm(value),
+            Param::FlCurrentLimit if current => self.current_limits[0] = MilliAmps(value),
+            Param::FrCurrentLimit if current => self.current_limits[1] = MilliAmps(value),
+            Param::RlCurrentLimit if current => self.current_limits[2] = MilliAmps(value),
+            Param::RrCurrentLimit if current => self.current_limits[3] = MilliAmps(value),
+            // the warning stays above the cutoff, so the speed is limited
+            // before the motors are disarmed
+            Param::BatteryWarning if voltage && value > self.battery_cutoff.0 => {
+                self.battery_warning = MilliVolts(value)
+            }
+            Param::BatteryCutoff if voltage && value < self.battery_warning.0 => {
+                self.battery_cutoff = MilliVolts(value)
+            }
+            Param::SpeedOversampling if oversampling => self.speed_oversampling = value as u8,
+            Param::SpeedFilter if filter => self.speed_filter = value as u8,
+            Param::EncoderResolution if resolution => self.encoder_resolution = value,
+            Param::SpeedSource if value == 0 => self.speed_source = SpeedSource::Analog,
+            Param::SpeedSource if value == 1 => self.speed_source = SpeedSource::Encoders,
+            Param::ImuRate if imu_rate => self.imu_rate = value as u16,
+            Param::RcSpeed if speed => self.rc_speed = CentiRpm(value),
+            // the speed is limited from the slow distance down to zero at
+            // the stop distance, further
+            Param::StopDistance if distance && value <= self.slow_distance.0 => {
+                self.stop_distance = MilliMeters(value)
+            }
+            Param::SlowDistance if distance && value >= self.stop_distance.0 => {
+                self.slow_distance = MilliMeters(value)
+            }
+            Param::TractionControl if value == 0 => self.traction_control = false,
+            Param::TractionControl if value == 1 => self.traction_control = true,
+            Param::SlipThreshold if slip => self.slip_threshold = PerMille(value as i16),
+            Param::StabilityControl if value == 0 => self.stability_control = false,
+            Param::StabilityControl if value == 1 && self.has_imu => self.stability_control = true,
+            _ => return Err(Log::InvalidParam),
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,129 @@
+//! The manual control with a hobby RC receiver.
+//!
+//! The receiver outputs a pulse on each channel about every 20 ms, whose
+//! width sets the channel from [`RC_PULSE_MIN`] to [`RC_PULSE_MAX`]. Its
+//! channels are the throttle, the steering and the mode switch.
+
+use xmaxx_messages::{CentiDegrees, CentiRpm, ControlMode, Drive};
+
+use crate::conversion::{STEERING_ANGLE_MAX, STEERING_ANGLE_ZERO};
+
+/// The width of a pulse at one end of a channel (µs).
+pub const RC_PULSE_MIN: u16 = 1000;
+/// The width of a pulse at the center of a channel (µs).
+pub const RC_PULSE_CENTER: u16 = 1500;
+/// The width of a pulse at the other end of a channel (µs).
+pub const RC_PULSE_MAX: u16 = 2000;
+/// The widths of the pulses accepted, a little beyond the ends for the trims
+/// of the transmitter (µs). The other pulses are glitches.
+pub const RC_PULSE_VALID: core::ops::RangeInclusive<u16> = 800..=2200;
+/// How far from the center a stick is still centered, so the Xmaxx does not
+/// creep (µs).
+pub const RC_DEADBAND: u16 = 25;
+/// How long a channel holds its last pulse, after which the signal is lost
+/// (ms).
+pub const RC_TIMEOUT: u32 = 100;
+/// How long the host is considered alive after its last drive command, after
+/// which the wheels stop, or the RC transmitter drives in
+/// [`ControlMode::Auto`] (ms).
+pub const HOST_TIMEOUT: u32 = 500;
+/// The default wheel speed at full throttle.
+pub const RC_SPEED: CentiRpm = CentiRpm(200_000);
+
+/// The channels of the receiver: throttle, steering and mode switch.
+pub const RC_CHANNELS: usize = 3;
+
+/// The last pulses of an RC receiver.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RcReceiver {
+    /// The width (µs) and the time (ms) of the last valid pulse of each
+    /// channel.
+    pulses: [Option<(u16, u32)>; RC_CHANNELS],
+    now: u32,
+}
+
+impl RcReceiver {
+    /// Returns a receiver without signal.
+    pub fn new() -> Self {
+        Self {
+            pulses: [None; RC_CHANNELS],
+            now: 0,
+        }
+    }
+
+    /// Updates the receiver with the widths of the pulses received since the
+    /// last update, if any, at `now` (ms).
+    pub fn update(&mut self, now: u32, pulses: [Option<u16>; RC_CHANNELS]) {
+        self.now = now;
+        for (last, pulse) in self.pulses.iter_mut().zip(pulses) {
+            match pulse {
+                Some(width) if RC_PULSE_VALID.contains(&width) => *last = Some((width, now)),
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns the width of the last pulse of the channel, unless the signal
+    /// is lost.
+    fn channel(&self, channel: usize) -> Option<u16> {
+        let (width, time) = self.pulses[channel]?;
+        (self.now.wrapping_sub(time) < RC_TIMEOUT).then_some(width)
+    }
+
+    /// Returns whether every channel has a signal.
+    pub fn has_signal(&self) -> bool {
+        (0..RC_CHANNELS).all(|channel| self.channel(channel).is_some())
+    }
+
+    /// Returns the mode of the mode switch, if it has a signal.
+    ///
+    /// A two-position switch selects [`ControlMode::Host`] or
+    /// [`ControlMode::Rc`], and a three-position one [`ControlMode::Auto`]
+    /// in the middle.
+    pub fn mode(&self) -> Option<ControlMode> {
+        let width = self.channel(2)?;
+        let third = (RC_PULSE_MAX - RC_PULSE_MIN) / 3;
+        Some(if width < RC_PULSE_MIN + third {
+            ControlMode::Host
+        } else if width > RC_PULSE_MAX - third {
+            ControlMode::Rc
+        } else {
+            ControlMode::Auto
+        })
+    }
+
+    /// Returns the command of the sticks, if they have a signal, with `speed`
+    /// at full throttle.
+    ///
+    /// Pushing the throttle drives forwards and the steering to the right
+    /// turns right; reverse the channels on the transmitter otherwise.
+    pub fn drive(&self, speed: CentiRpm) -> Option<Drive> {
+        let throttle = stick(self.channel(0)?);
+        let steering = stick(self.channel(1)?);
+
+        let rpm = CentiRpm(speed.0 * throttle / i32::from(RC_PULSE_MAX - RC_PULSE_CENTER));
+        let half_range = STEERING_ANGLE_MAX.0 - STEERING_ANGLE_ZERO.0;
+        let angle = half_range * steering / i32::from(RC_PULSE_MAX - RC_PULSE_CENTER);
+        Some(Drive {
+            steering: CentiDegrees(STEERING_ANGLE_ZERO.0 + angle),
+            fl_whl_rpm: rpm,
+            fr_whl_rpm: rpm,
+            rl_whl_rpm: rpm,
+            rr_whl_rpm: rpm,
+        })
+    }
+}
+
+/// Returns the position of a stick from the width of its pulse (µs), from
+/// the center, clamped to the ends and without the deadband.
+fn stick(width: u16) -> i32 {
+    let offset = i32::from(width.clamp(RC_PULSE_MIN, RC_PULSE_MAX)) - i32::from(RC_PULSE_CENTER);
+    let deadband = i32::from(RC_DEADBAND);
+    if offset.abs() <= deadband {
+        return 0;
+    }
+
+    // stretched so the ends are still reached
+    let half = i32::from(RC_PULSE_MAX - RC_PULSE_CENTER);
+    offset.signum() * (offset.abs() - deadband) * half / (half - deadband)
+}
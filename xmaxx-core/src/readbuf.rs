@@ -1,3 +1,5 @@
+use xmaxx_messages::Log;
+
 /// A lightweight reading buffer.
 pub struct ReadBuf<const N: usize> {
     idx: usize,
@@ -14,13 +16,15 @@ impl<const N: usize> ReadBuf<{ N }> {
     }
 
     /// Push the given byte to the buffer.
-    pub fn push(&mut self, value: u8) -> Result<(), ()> {
+    ///
+    /// It fails with [`Log::ReadBufferOverflow`] when the buffer is full.
+    pub fn push(&mut self, value: u8) -> Result<(), Log> {
         if self.idx < N {
             self.buffer[self.idx] = value;
             self.idx += 1;
             Ok(())
         } else {
-            Err(())
+            Err(Log::ReadBufferOverflow)
         }
     }
 
@@ -36,3 +40,9 @@ impl<const N: usize> ReadBuf<{ N }> {
         self.idx = 0;
     }
 }
+
+impl<const N: usize> Default for ReadBuf<{ N }> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
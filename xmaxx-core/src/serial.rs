@@ -0,0 +1,49 @@
+//! Reading commands and writing information on the serial port.
+
+use embedded_hal_v0::serial::{Read, Write};
+
+use xmaxx_messages::*;
+
+use crate::readbuf::ReadBuf;
+
+/// Read a command from serial.
+///
+/// **Note:** for this function to work properly, each byte must be sent slowly
+/// enough for the microcontroller to read them on time.
+///
+pub fn read_command<const N: usize>(
+    read_buf: &mut ReadBuf<{ N }>,
+    serial: &mut impl Read<u8>,
+) -> Result<Option<Command>, Log> {
+    while let Ok(byte) = serial.read() {
+        // reset on overflow or it will always fail
+        read_buf.push(byte).inspect_err(|_| read_buf.reset())?;
+
+        // null char is the separator in cobs encoding
+        if byte == b'\0' {
+            // reset buffer on deserialization error or will fail forever after
+            let command: Command = deserialize(read_buf.as_mut_slice()).map_err(|_| {
+                read_buf.reset();
+                Log::DeserializationError
+            })?;
+
+            read_buf.reset();
+            return Ok(Some(command));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Write information to serial.
+pub fn write_event(
+    info: &Info,
+    write_buf: &mut [u8],
+    serial: &mut impl Write<u8>,
+) -> Result<(), Log> {
+    let msg = serialize(info, write_buf).map_err(|_| Log::SerializationError)?;
+    for b in msg {
+        let _ = nb::block!(serial.write(*b)); // should be infallible, cannot .expect() because some trait is not implemented
+    }
+    Ok(())
+}
@@ -0,0 +1,65 @@
+//! The stability control, against the Xmaxx spinning out or plowing on in
+//! a turn.
+//!
+//! With grip, the Xmaxx turns at the yaw rate of its bicycle model, from
+//! the speed of its rear wheels and the steering, see
+//! [`crate::odometry::kinematic_yaw_rate`]. Turning faster, the rear slides
+//! out; slower, the front slides on. Either way the tires lost their grip,
+//! so the speed of every wheel is reduced in proportion to the error of the
+//! yaw rate measured by the IMU, above a margin.
+
+use xmaxx_messages::{CentiDegreesPerSecond, CentiRpm};
+
+/// How far the yaw rate can be from the one of the steering, above the
+/// noise of the gyroscope and the lag of the steering servo.
+pub const YAW_RATE_MARGIN: CentiDegreesPerSecond = CentiDegreesPerSecond(2000);
+
+/// Reduces the speed of the wheels when the Xmaxx does not turn as steered.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StabilityControl {
+    /// The yaw rate measured minus the one of the steering.
+    error: i32,
+    /// The fraction of their command that the wheels are limited to, if
+    /// the error is above the margin (thousandths).
+    cap: Option<i32>,
+}
+
+impl StabilityControl {
+    /// Returns the stability control without error.
+    pub fn new() -> Self {
+        Self {
+            error: 0,
+            cap: None,
+        }
+    }
+
+    /// Updates the error with the yaw rate measured and the one of the
+    /// steering.
+    pub fn update(&mut self, measured: CentiDegreesPerSecond, expected: CentiDegreesPerSecond) {
+        self.error = i32::from(measured.0) - i32::from(expected.0);
+        let error = self.error.abs();
+        let margin = i32::from(YAW_RATE_MARGIN.0);
+        // no division by zero: the error is above the margin
+        self.cap = (error > margin).then(|| 1000 * margin / error);
+    }
+
+    /// Reduces the speeds of the wheels: fl, fr, rl, rr.
+    pub fn limit(&self, rpms: &mut [CentiRpm; 4]) {
+        if let Some(cap) = self.cap {
+            for rpm in rpms {
+                // safe to cast: smaller than the speed
+                *rpm = CentiRpm((i64::from(rpm.0) * i64::from(cap) / 1000) as i32);
+            }
+        }
+    }
+
+    /// Returns the yaw rate measured minus the one of the steering.
+    pub fn error(&self) -> i32 {
+        self.error
+    }
+
+    /// Returns whether the Xmaxx does not turn as steered.
+    pub fn is_unstable(&self) -> bool {
+        self.cap.is_some()
+    }
+}
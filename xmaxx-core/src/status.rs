@@ -0,0 +1,73 @@
+//! The status of the Xmaxx on the LED of the Arduino, so the pit crew can
+//! tell what is wrong without a laptop.
+//!
+//! Every [`BLINK_CYCLE`], the LED blinks once while waiting for the host,
+//! twice when a failsafe stopped the wheels, three times when the battery
+//! is low and four times on a fault. It stays on while armed. A panic
+//! blinks it fast and without pause.
+
+use embedded_hal::digital::OutputPin;
+
+/// The length of a blink or of a pause between two blinks (ms).
+pub const BLINK_SLOT: u32 = 125;
+/// The period of the patterns, 16 slots (ms).
+pub const BLINK_CYCLE: u32 = 16 * BLINK_SLOT;
+
+/// The state of the Xmaxx shown on the LED.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// The host does not drive, it is not sending commands.
+    WaitingForHost,
+    /// The host or the RC transmitter drives.
+    Armed,
+    /// The source of the command was lost and the wheels are stopped.
+    Timeout,
+    /// The battery is below its warning voltage, or its cutoff.
+    LowBattery,
+    /// A motor drew too much current and is cut.
+    Fault,
+}
+
+impl Status {
+    /// Returns the pattern of the LED over a [`BLINK_CYCLE`], a slot per
+    /// bit from the highest.
+    pub fn pattern(self) -> u16 {
+        match self {
+            Status::WaitingForHost => 0b1000_0000_0000_0000,
+            Status::Armed => 0b1111_1111_1111_1111,
+            Status::Timeout => 0b1010_0000_0000_0000,
+            Status::LowBattery => 0b1010_1000_0000_0000,
+            Status::Fault => 0b1010_1010_0000_0000,
+        }
+    }
+
+    /// Returns whether the LED is on at `now` (ms).
+    pub fn is_lit(self, now: u32) -> bool {
+        let slot = now % BLINK_CYCLE / BLINK_SLOT;
+        self.pattern() >> (15 - slot) & 1 == 1
+    }
+}
+
+/// Shows the status on a LED, without blocking.
+pub struct StatusLed<P> {
+    pin: P,
+}
+
+impl<P: OutputPin> StatusLed<P> {
+    /// Returns the status LED on this pin.
+    pub fn new(pin: P) -> Self {
+        Self { pin }
+    }
+
+    /// Lights the LED or not for the status at `now` (ms).
+    pub fn update(&mut self, now: u32, status: Status) {
+        self.pin
+            .set_state(status.is_lit(now).into())
+            .expect("LED pin should be settable");
+    }
+
+    /// Returns the pin of the LED.
+    pub fn release(self) -> P {
+        self.pin
+    }
+}
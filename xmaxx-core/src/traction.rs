@@ -0,0 +1,100 @@
+//! The traction control, against the wheels slipping.
+//!
+//! A wheel slips when it turns faster than the ground goes under it. The
+//! speed of the ground is estimated from the wheel turning the slowest for
+//! its command, so the outer wheels commanded faster in a turn are not
+//! taken as slipping. When every wheel slips as much, like on ice, it is
+//! not detected.
+//!
+//! The speed commanded to a slipping wheel is reduced in proportion to its
+//! slip above the threshold, so it settles a little above the threshold.
+//! The slips are ratios, so the speeds measured and commanded need not be
+//! in the same units.
+
+use xmaxx_messages::{CentiRpm, PerMille};
+
+/// The default slip above which a wheel is slipping.
+pub const SLIP_THRESHOLD: PerMille = PerMille(200);
+/// The highest slip threshold, of a wheel spinning in place.
+pub const MAX_SLIP_THRESHOLD: PerMille = PerMille(1000);
+/// How much faster than the ground a wheel must turn to slip, above the
+/// noise of the speed sensors.
+pub const SLIP_MARGIN: CentiRpm = CentiRpm(1000);
+
+/// Reduces the speed of the slipping wheels.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TractionControl {
+    /// The slips of the wheels: fl, fr, rl, rr.
+    slips: [PerMille; 4],
+    /// The fraction of its command that each wheel is limited to, if it
+    /// slips (thousandths).
+    caps: [Option<i32>; 4],
+}
+
+impl TractionControl {
+    /// Returns the traction control without slip.
+    pub fn new() -> Self {
+        Self {
+            slips: [PerMille(0); 4],
+            caps: [None; 4],
+        }
+    }
+
+    /// Updates the slips with the speeds of the wheels and their commands:
+    /// fl, fr, rl, rr, and the slip above which a wheel slips.
+    pub fn update(&mut self, speeds: [CentiRpm; 4], commands: [CentiRpm; 4], threshold: PerMille) {
+        // the fraction of its command that the slowest wheel turns at
+        // (thousandths), in the direction commanded
+        let ground = speeds
+            .iter()
+            .zip(&commands)
+            .filter(|(_, command)| command.0 != 0)
+            .map(|(speed, command)| (i64::from(speed.0) * 1000 / i64::from(command.0)).max(0))
+            .min()
+            .unwrap_or(0);
+
+        for i in 0..4 {
+            let (speed, command) = (i64::from(speeds[i].0), i64::from(commands[i].0));
+            let ground = command * ground / 1000;
+            let excess = match command.signum() {
+                1 => speed - ground,
+                -1 => ground - speed,
+                _ => 0,
+            };
+            // no division by zero: the speed is at least the excess
+            let slip = if excess > 0 {
+                excess * 1000 / speed.abs()
+            } else {
+                0
+            };
+            // safe to cast: at most 1000
+            self.slips[i] = PerMille(slip as i16);
+
+            let threshold = i64::from(threshold.0);
+            let slipping = slip > threshold && excess > SLIP_MARGIN.0.into();
+            // no division by zero: the threshold is below the slip, at most
+            // 1000, and safe to cast: less than 1000
+            self.caps[i] = slipping.then(|| ((1000 - slip) * 1000 / (1000 - threshold)) as i32);
+        }
+    }
+
+    /// Reduces the speeds of the slipping wheels: fl, fr, rl, rr.
+    pub fn limit(&self, rpms: &mut [CentiRpm; 4]) {
+        for (rpm, cap) in rpms.iter_mut().zip(self.caps) {
+            if let Some(cap) = cap {
+                // safe to cast: smaller than the speed
+                *rpm = CentiRpm((i64::from(rpm.0) * i64::from(cap) / 1000) as i32);
+            }
+        }
+    }
+
+    /// Returns the slips of the wheels: fl, fr, rl, rr.
+    pub fn slips(&self) -> [PerMille; 4] {
+        self.slips
+    }
+
+    /// Returns whether a wheel slips.
+    pub fn is_slipping(&self) -> bool {
+        self.caps.iter().any(Option::is_some)
+    }
+}
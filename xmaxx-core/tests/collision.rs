@@ -0,0 +1,70 @@
+//! The collision stop with the range sensors.
+
+use xmaxx_core::collision::*;
+use xmaxx_core::conversion::RPM_MAX;
+use xmaxx_messages::{CentiRpm, Log, MilliMeters};
+
+const FORWARDS: [CentiRpm; 4] = [CentiRpm(100_000); 4];
+const BACKWARDS: [CentiRpm; 4] = [CentiRpm(-100_000); 4];
+
+#[test]
+fn echoes_are_converted_to_distances_within_range() {
+    // 1 m there and back at 343 m/s
+    assert_eq!(echo_to_distance(5831), Some(MilliMeters(1000)));
+    assert_eq!(echo_to_distance(0), Some(MilliMeters(0)));
+    // the timeout of the sensor without obstacle
+    assert_eq!(echo_to_distance(38_000), None);
+}
+
+#[test]
+fn the_speed_limit_goes_down_to_zero_at_the_stop_distance() {
+    let limit = |distance| speed_limit(MilliMeters(distance), STOP_DISTANCE, SLOW_DISTANCE);
+    assert_eq!(limit(3000), RPM_MAX);
+    assert_eq!(limit(SLOW_DISTANCE.0), RPM_MAX);
+    assert!(limit(1200).0 > 0 && limit(1200).0 < RPM_MAX.0);
+    assert!(limit(1000) < limit(1200));
+    assert_eq!(limit(STOP_DISTANCE.0), CentiRpm(0));
+    assert_eq!(limit(100), CentiRpm(0));
+}
+
+#[test]
+fn only_the_direction_of_the_obstacle_is_stopped() {
+    let mut collision = Collision::new();
+    // an obstacle at 0.3 m in front, nothing behind
+    collision.update(0, [Some(1750), Some(38_000)]);
+
+    let mut rpms = FORWARDS;
+    assert_eq!(
+        collision.limit(&mut rpms, STOP_DISTANCE, SLOW_DISTANCE),
+        Some(Log::CollisionStop)
+    );
+    assert_eq!(rpms, [CentiRpm(0); 4]);
+    // logged once
+    let mut rpms = FORWARDS;
+    assert_eq!(
+        collision.limit(&mut rpms, STOP_DISTANCE, SLOW_DISTANCE),
+        None
+    );
+
+    let mut rpms = BACKWARDS;
+    assert_eq!(
+        collision.limit(&mut rpms, STOP_DISTANCE, SLOW_DISTANCE),
+        None
+    );
+    assert_eq!(rpms, BACKWARDS);
+}
+
+#[test]
+fn old_measurements_do_not_limit_the_speed() {
+    let mut collision = Collision::new();
+    collision.update(0, [Some(1750), None]);
+    collision.update(RANGE_TIMEOUT, [None, None]);
+
+    assert_eq!(collision.distances(), [None, None]);
+    let mut rpms = FORWARDS;
+    assert_eq!(
+        collision.limit(&mut rpms, STOP_DISTANCE, SLOW_DISTANCE),
+        None
+    );
+    assert_eq!(rpms, FORWARDS);
+}
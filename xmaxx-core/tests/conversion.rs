@@ -0,0 +1,48 @@
+//! The conversions between the physical units and the hardware's units.
+
+use xmaxx_core::conversion::*;
+use xmaxx_messages::{CentiDegrees, CentiRpm};
+
+#[test]
+fn the_steering_range_spans_the_duty_range() {
+    assert_eq!(angle_to_duty(STEERING_ANGLE_MIN), STEERING_DUTY_MIN as u16);
+    assert_eq!(angle_to_duty(STEERING_ANGLE_MAX), STEERING_DUTY_MAX as u16);
+    // 90 deg goes straight
+    assert_eq!(STEERING_ANGLE_ZERO, CentiDegrees(9000));
+    assert_eq!(
+        angle_to_duty(STEERING_ANGLE_ZERO),
+        STEERING_DUTY_ZERO as u16
+    );
+
+    let duties: Vec<u16> = (STEERING_ANGLE_MIN.0..=STEERING_ANGLE_MAX.0)
+        .step_by(100)
+        .map(|angle| angle_to_duty(CentiDegrees(angle)))
+        .collect();
+    assert!(duties.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert!(duties.iter().all(|&duty| duty <= DUTY_CYCLE_DENOM));
+}
+
+#[test]
+fn the_analog_speeds_are_signed_around_the_zero() {
+    assert_eq!(analog_to_rpm(ANALOG_ZERO_RPM), CentiRpm(0));
+    // half the analog range is the top speed of the motor at the wheel,
+    // 4500 / 10.6 rpm
+    assert_eq!(analog_to_rpm(ANALOG_ZERO_RPM + ANALOG), CentiRpm(42_452));
+    assert_eq!(analog_to_rpm(ANALOG_ZERO_RPM - ANALOG), CentiRpm(-42_452));
+    assert_eq!(analog_to_rpm(413), CentiRpm(103));
+    assert_eq!(analog_to_rpm(411), CentiRpm(-103));
+}
+
+#[test]
+fn the_analog_sums_keep_the_fraction_of_the_mean() {
+    assert_eq!(analog_sum_to_rpm(4 * 822, 4), analog_to_rpm(822));
+    // a quarter of an analog unit
+    assert_eq!(analog_sum_to_rpm(4 * ANALOG_ZERO_RPM + 1, 4), CentiRpm(25));
+    assert_eq!(analog_sum_to_rpm(4 * ANALOG_ZERO_RPM - 1, 4), CentiRpm(-25));
+    // without overflow at the end of the range
+    assert_eq!(
+        analog_sum_to_rpm(8 * ANALOG_MAX, 8),
+        analog_to_rpm(ANALOG_MAX)
+    );
+    assert_eq!(analog_sum_to_rpm(0, 8), analog_to_rpm(0));
+}
@@ -0,0 +1,102 @@
+//! The wheel encoders.
+
+use xmaxx_core::encoder::*;
+use xmaxx_messages::CentiRpm;
+
+/// The states of the channels forwards, as `A << 1 | B`.
+const FORWARDS: [u8; 4] = [0b00, 0b01, 0b11, 0b10];
+
+#[test]
+fn the_quadrature_steps_follow_the_direction() {
+    for i in 0..4 {
+        let (current, next) = (FORWARDS[i], FORWARDS[(i + 1) % 4]);
+        assert_eq!(quadrature_step(current, next), 1);
+        assert_eq!(quadrature_step(next, current), -1);
+        assert_eq!(quadrature_step(current, current), 0);
+    }
+
+    // two cycles of the channels
+    let ticks: i32 = (0..8)
+        .map(|i| quadrature_step(FORWARDS[i % 4], FORWARDS[(i + 1) % 4]))
+        .sum();
+    assert_eq!(ticks, 8);
+}
+
+#[test]
+fn both_channels_changing_at_once_do_not_move() {
+    for state in FORWARDS {
+        assert_eq!(quadrature_step(state, state ^ 0b11), 0);
+    }
+}
+
+#[test]
+fn only_the_two_channel_bits_count() {
+    assert_eq!(quadrature_step(0b1100, 0b0101), 1);
+    assert_eq!(quadrature_step(0b1001, 0b1100), -1);
+}
+
+#[test]
+fn the_first_reading_only_starts_the_window() {
+    let mut speed = EncoderSpeed::new();
+    assert_eq!(speed.update(1000, 12_345, ENCODER_RESOLUTION), CentiRpm(0));
+    assert_eq!(speed.update(1001, 12_345, ENCODER_RESOLUTION), CentiRpm(0));
+}
+
+#[test]
+fn a_fast_wheel_is_measured_after_the_shortest_window() {
+    let mut speed = EncoderSpeed::new();
+    speed.update(0, 0, ENCODER_RESOLUTION);
+
+    // enough ticks, but too early
+    assert_eq!(
+        speed.update(MIN_SPEED_WINDOW - 1, SPEED_TICKS, ENCODER_RESOLUTION),
+        CentiRpm(0)
+    );
+    // 16 ticks in 20 ms, 94.3 rpm
+    assert_eq!(
+        speed.update(MIN_SPEED_WINDOW, SPEED_TICKS, ENCODER_RESOLUTION),
+        CentiRpm(9430)
+    );
+
+    // the next window starts at the end of the previous one, and counts
+    // backwards too
+    assert_eq!(
+        speed.update(2 * MIN_SPEED_WINDOW, -SPEED_TICKS, ENCODER_RESOLUTION),
+        CentiRpm(-18_860)
+    );
+}
+
+#[test]
+fn a_slow_wheel_is_measured_over_more_time() {
+    let mut speed = EncoderSpeed::new();
+    speed.update(0, 0, ENCODER_RESOLUTION);
+
+    // too few ticks, the window goes on
+    assert_eq!(speed.update(100, 2, ENCODER_RESOLUTION), CentiRpm(0));
+    assert_eq!(
+        speed.update(MAX_SPEED_WINDOW - 1, 4, ENCODER_RESOLUTION),
+        CentiRpm(0)
+    );
+    // 4 ticks in 500 ms, 0.94 rpm
+    assert_eq!(
+        speed.update(MAX_SPEED_WINDOW, 4, ENCODER_RESOLUTION),
+        CentiRpm(94)
+    );
+
+    // a stopped wheel goes back to zero
+    assert_eq!(
+        speed.update(2 * MAX_SPEED_WINDOW, 4, ENCODER_RESOLUTION),
+        CentiRpm(0)
+    );
+}
+
+#[test]
+fn the_ticks_and_the_time_wrap_around() {
+    let mut speed = EncoderSpeed::new();
+    speed.update(u32::MAX - 9, i32::MAX - 7, ENCODER_RESOLUTION);
+
+    assert_eq!(
+        speed.update(MIN_SPEED_WINDOW - 10, i32::MIN + 8, ENCODER_RESOLUTION),
+        CentiRpm(9430)
+    );
+}
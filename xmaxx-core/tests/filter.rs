@@ -0,0 +1,60 @@
+//! The filtering of the noisy readings of the sensors.
+
+use xmaxx_core::filter::*;
+
+#[test]
+fn the_output_starts_at_the_first_sample() {
+    let mut filter = LowPass::new();
+    assert_eq!(filter.update(-1234, 4), -1234);
+    assert_eq!(filter.update(-1234, 4), -1234);
+}
+
+#[test]
+fn a_step_is_followed_exponentially() {
+    let mut filter = LowPass::new();
+    filter.update(0, 2);
+
+    // a quarter of the way left each sample
+    let mut expected = 0.0;
+    for _ in 0..40 {
+        expected += (1000.0 - expected) / 4.0;
+        let output = filter.update(1000, 2);
+        assert!((f64::from(output) - expected).abs() <= 1.0);
+    }
+    assert_eq!(filter.update(1000, 2), 1000);
+
+    // and back down the same way
+    let mut filter = LowPass::new();
+    filter.update(1000, 2);
+    assert_eq!(filter.update(0, 2), 750);
+    assert_eq!(filter.update(0, 2), 563);
+}
+
+#[test]
+fn a_larger_shift_smooths_more() {
+    let step = |shift| {
+        let mut filter = LowPass::new();
+        filter.update(0, shift);
+        filter.update(1000, shift)
+    };
+
+    // a shift of 0 lets the samples through
+    assert_eq!(step(0), 1000);
+    assert_eq!(step(1), 500);
+    assert_eq!(step(4), 63);
+    assert_eq!(step(MAX_SHIFT), 4);
+    assert_eq!(step(MAX_SHIFT + 4), step(MAX_SHIFT));
+}
+
+#[test]
+fn small_changes_are_not_lost() {
+    let mut filter = LowPass::new();
+    filter.update(0, 4);
+
+    // each sample moves the output by less than a unit
+    let mut output = 0;
+    for _ in 0..100 {
+        output = filter.update(1, 4);
+    }
+    assert_eq!(output, 1);
+}
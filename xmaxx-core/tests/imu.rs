@@ -0,0 +1,187 @@
+//! The IMU driver against a mock I2C bus.
+
+use embedded_hal::i2c::ErrorKind;
+use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+
+use xmaxx_core::imu::*;
+use xmaxx_messages::{CentiDegreesPerSecond, Log, MilliG};
+
+const ADDRESS: u8 = IMU_ADDRESS;
+
+/// The transactions to set the sample rate to 100 Hz.
+fn set_rate_100_hz() -> [Transaction; 2] {
+    [
+        // 42 Hz low-pass filter, below the Nyquist frequency
+        Transaction::write(ADDRESS, vec![0x1A, 3]),
+        // 1 kHz / (1 + 9)
+        Transaction::write(ADDRESS, vec![0x19, 9]),
+    ]
+}
+
+#[test]
+fn init_wakes_the_imu_up_and_configures_it() {
+    let mut expectations = vec![
+        Transaction::write_read(ADDRESS, vec![0x75], vec![0x68]),
+        Transaction::write(ADDRESS, vec![0x6B, 0x01]),
+        Transaction::write(ADDRESS, vec![0x1B, 0x00]),
+        Transaction::write(ADDRESS, vec![0x1C, 0x08]),
+    ];
+    expectations.extend(set_rate_100_hz());
+    let mut imu = Mpu6050::new(Mock::new(&expectations), ADDRESS);
+
+    assert_eq!(imu.init(100), Ok(()));
+
+    imu.release().done();
+}
+
+#[test]
+fn init_rejects_another_device() {
+    let expectations = [Transaction::write_read(ADDRESS, vec![0x75], vec![0x12])];
+    let mut imu = Mpu6050::new(Mock::new(&expectations), ADDRESS);
+
+    assert_eq!(imu.init(100), Err(Error::UnknownDevice(0x12)));
+
+    imu.release().done();
+}
+
+#[test]
+fn bus_errors_are_returned() {
+    let expectations =
+        [Transaction::write_read(ADDRESS, vec![0x75], vec![0]).with_error(ErrorKind::Other)];
+    let mut imu = Mpu6050::new(Mock::new(&expectations), ADDRESS);
+
+    assert_eq!(imu.init(100), Err(Error::I2c(ErrorKind::Other)));
+
+    imu.release().done();
+}
+
+#[test]
+fn set_rate_configures_the_divider_and_filter() {
+    let mut imu = Mpu6050::new(Mock::new(&set_rate_100_hz()), ADDRESS);
+
+    assert_eq!(imu.set_rate(100), Ok(()));
+
+    imu.release().done();
+}
+
+#[test]
+fn read_converts_to_physical_units() {
+    let bytes = [
+        [0x20, 0x00], // accel x: 8192 LSB, 1 g
+        [0xF0, 0x00], // accel y: -4096 LSB, -0.5 g
+        [0x7F, 0xFF], // accel z: the end of the ±4 g range
+        [0x12, 0x34], // temperature, ignored
+        [0x00, 0x83], // gyro x: 131 LSB, 1 deg/s
+        [0xFF, 0x7D], // gyro y: -131 LSB
+        [0x80, 0x00], // gyro z: the end of the ±250 deg/s range
+    ]
+    .concat();
+    let expectations = [Transaction::write_read(ADDRESS, vec![0x3B], bytes)];
+    let mut imu = Mpu6050::new(Mock::new(&expectations), ADDRESS);
+
+    let reading = imu.read().unwrap();
+
+    assert_eq!(reading.accel, [MilliG(1000), MilliG(-500), MilliG(3999)]);
+    assert_eq!(
+        reading.gyro,
+        [
+            CentiDegreesPerSecond(100),
+            CentiDegreesPerSecond(-100),
+            CentiDegreesPerSecond(-25_013),
+        ]
+    );
+    imu.release().done();
+}
+
+/// The transactions to initialize the IMU at 100 Hz.
+fn init_100_hz() -> Vec<Transaction> {
+    let mut transactions = vec![
+        Transaction::write_read(ADDRESS, vec![0x75], vec![0x68]),
+        Transaction::write(ADDRESS, vec![0x6B, 0x01]),
+        Transaction::write(ADDRESS, vec![0x1B, 0x00]),
+        Transaction::write(ADDRESS, vec![0x1C, 0x08]),
+    ];
+    transactions.extend(set_rate_100_hz());
+    transactions
+}
+
+/// The transaction to read a sample of zeros.
+fn read_zeros() -> Transaction {
+    Transaction::write_read(ADDRESS, vec![0x3B], vec![0; 14])
+}
+
+/// The transaction to read a sample, failing.
+fn read_error() -> Transaction {
+    Transaction::write_read(ADDRESS, vec![0x3B], vec![0; 14]).with_error(ErrorKind::Other)
+}
+
+#[test]
+fn the_reader_logs_an_error_once_and_retries_the_init() {
+    let mut expectations =
+        vec![Transaction::write_read(ADDRESS, vec![0x75], vec![0]).with_error(ErrorKind::Other)];
+    // retried after a while
+    expectations.extend(init_100_hz());
+    expectations.extend([read_zeros(), read_error(), read_error(), read_zeros()]);
+    let mut reader = ImuReader::new(Mpu6050::new(Mock::new(&expectations), ADDRESS), 100);
+
+    assert_eq!(reader.update(0, 100), (None, Some(Log::ImuError)));
+    assert_eq!(reader.update(IMU_RETRY - 1, 100), (None, None));
+    let reading = Some(ImuReading::default());
+    assert_eq!(reader.update(IMU_RETRY, 100), (reading, None));
+
+    // a failed read is logged once
+    assert_eq!(
+        reader.update(IMU_RETRY + 10, 100),
+        (None, Some(Log::ImuError))
+    );
+    assert_eq!(reader.update(IMU_RETRY + 20, 100), (None, None));
+    assert_eq!(reader.update(IMU_RETRY + 30, 100), (reading, None));
+
+    reader.release().release().done();
+}
+
+#[test]
+fn the_reader_retries_a_new_rate_later() {
+    let mut expectations = init_100_hz();
+    expectations.push(read_zeros());
+    // 200 Hz fails, and the IMU is still read at 100 Hz
+    expectations.push(Transaction::write(ADDRESS, vec![0x1A, 2]).with_error(ErrorKind::Other));
+    expectations.extend([read_zeros(), read_zeros()]);
+    expectations.extend([
+        Transaction::write(ADDRESS, vec![0x1A, 2]),
+        Transaction::write(ADDRESS, vec![0x19, 4]),
+        read_zeros(),
+    ]);
+    let mut reader = ImuReader::new(Mpu6050::new(Mock::new(&expectations), ADDRESS), 100);
+    let reading = Some(ImuReading::default());
+
+    assert_eq!(reader.update(0, 100), (reading, None));
+    assert_eq!(reader.update(10, 200), (reading, Some(Log::ImuError)));
+    assert_eq!(reader.update(20, 200), (reading, None));
+    assert_eq!(reader.update(10 + IMU_RETRY, 200), (reading, None));
+
+    reader.release().release().done();
+}
+
+#[test]
+fn sample_rates_are_clamped_to_the_range() {
+    assert_eq!(sample_rate_divider(MAX_IMU_RATE), 0);
+    assert_eq!(sample_rate_divider(MIN_IMU_RATE), 249);
+    assert_eq!(sample_rate_divider(0), 249);
+    assert_eq!(low_pass_config(MAX_IMU_RATE), 1);
+    assert_eq!(low_pass_config(MIN_IMU_RATE), 6);
+}
+
+#[test]
+fn yaw_rate_removes_the_bias_measured_at_rest() {
+    let mut yaw_rate = YawRate::new();
+    for _ in 0..1000 {
+        yaw_rate.update(CentiDegreesPerSecond(50), true);
+    }
+    assert_eq!(yaw_rate.bias(), CentiDegreesPerSecond(50));
+
+    // the bias is kept while moving
+    let rate = yaw_rate.update(CentiDegreesPerSecond(3050), false);
+    assert_eq!(rate, CentiDegreesPerSecond(3000));
+    assert_eq!(yaw_rate.bias(), CentiDegreesPerSecond(50));
+}
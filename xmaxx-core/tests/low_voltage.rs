@@ -0,0 +1,95 @@
+//! The protection of the battery against over-discharge.
+
+use xmaxx_core::low_voltage::*;
+use xmaxx_core::overcurrent::FULL_SPEED;
+use xmaxx_messages::{CentiRpm, Log, MilliVolts};
+
+const WARNING: MilliVolts = MilliVolts(14_000);
+const CUTOFF: MilliVolts = MilliVolts(13_200);
+const STOPPED: [CentiRpm; 4] = [CentiRpm(0); 4];
+const DRIVING: [CentiRpm; 4] = [CentiRpm(10_000); 4];
+
+/// Holds the battery at `voltage` from `start` to `end` (ms) and returns
+/// the logs.
+fn hold(battery: &mut LowVoltage, start: u32, end: u32, voltage: i32) -> Vec<Log> {
+    (start..=end)
+        .step_by(10)
+        .filter_map(|now| battery.update(now, MilliVolts(voltage), WARNING, CUTOFF))
+        .collect()
+}
+
+#[test]
+fn a_sag_shorter_than_the_delay_is_ignored() {
+    let mut battery = LowVoltage::new();
+    assert!(hold(&mut battery, 0, BATTERY_DELAY - 10, 12_000).is_empty());
+    assert_eq!(battery.level(), Level::Normal);
+    assert_eq!(battery.speed(), FULL_SPEED);
+
+    // back up, the delay starts again
+    assert!(hold(&mut battery, BATTERY_DELAY, BATTERY_DELAY, 16_000).is_empty());
+    assert!(hold(&mut battery, BATTERY_DELAY + 10, 2 * BATTERY_DELAY, 12_000).is_empty());
+    assert_eq!(battery.level(), Level::Normal);
+}
+
+#[test]
+fn a_low_battery_limits_the_speed() {
+    let mut battery = LowVoltage::new();
+    let logs = hold(&mut battery, 0, 2 * BATTERY_DELAY, 13_600);
+
+    assert_eq!(logs, [Log::LowBattery]);
+    assert_eq!(battery.level(), Level::Low);
+    assert_eq!(battery.speed(), LOW_BATTERY_SPEED);
+    assert!(!battery.is_disarmed());
+}
+
+#[test]
+fn an_empty_battery_disarms_the_motors() {
+    let mut battery = LowVoltage::new();
+    let logs = hold(&mut battery, 0, 2 * BATTERY_DELAY, 12_000);
+
+    // straight to the cutoff
+    assert_eq!(logs, [Log::BatteryCutoff]);
+    assert_eq!(battery.level(), Level::Cutoff);
+    assert!(battery.is_disarmed());
+    assert_eq!(battery.speed(), 0);
+
+    // not rearmed while empty
+    battery.rearm(STOPPED);
+    assert!(battery.is_disarmed());
+}
+
+#[test]
+fn the_battery_must_recover_above_the_hysteresis() {
+    let mut battery = LowVoltage::new();
+    hold(&mut battery, 0, 2 * BATTERY_DELAY, 13_600);
+
+    // above the warning, within the hysteresis
+    hold(&mut battery, 3000, 3000, WARNING.0 + BATTERY_HYSTERESIS.0);
+    assert_eq!(battery.level(), Level::Low);
+
+    hold(
+        &mut battery,
+        3010,
+        3010,
+        WARNING.0 + BATTERY_HYSTERESIS.0 + 1,
+    );
+    assert_eq!(battery.level(), Level::Normal);
+    assert_eq!(battery.speed(), FULL_SPEED);
+}
+
+#[test]
+fn the_motors_are_rearmed_once_recovered_and_stopped() {
+    let mut battery = LowVoltage::new();
+    hold(&mut battery, 0, 2 * BATTERY_DELAY, 12_000);
+
+    // back above the cutoff and its hysteresis
+    hold(&mut battery, 3000, 3000, 13_800);
+    assert_eq!(battery.level(), Level::Low);
+    battery.rearm(DRIVING);
+    assert!(battery.is_disarmed());
+    assert_eq!(battery.speed(), 0);
+
+    battery.rearm(STOPPED);
+    assert!(!battery.is_disarmed());
+    assert_eq!(battery.speed(), LOW_BATTERY_SPEED);
+}
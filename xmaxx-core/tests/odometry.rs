@@ -0,0 +1,92 @@
+//! The dead-reckoning of the pose from the wheels.
+
+use xmaxx_core::conversion::*;
+use xmaxx_core::odometry::*;
+use xmaxx_messages::{CentiDegrees, CentiDegreesPerSecond, CentiRpm, MilliMeters};
+
+/// 100 rpm, about 1.05 m/s.
+const SPEEDS: [CentiRpm; 4] = [CentiRpm(10_000); 4];
+const SPEED: f64 = 100.0 / 60.0 * WHEEL_CIRCUMFERENCE as f64 / 1000.0;
+
+/// Drives at the speeds and steering from 0 to `duration` (ms), updating
+/// like a jittery main loop.
+fn drive(speeds: [CentiRpm; 4], steering: CentiDegrees, duration: u32) -> DeadReckoning {
+    let mut odometry = DeadReckoning::new();
+    for now in (0..=duration).step_by(8) {
+        odometry.update(now, speeds, steering);
+    }
+    odometry.update(duration, speeds, steering);
+    odometry
+}
+
+#[test]
+fn straight_ahead_only_moves_forwards() {
+    let odometry = drive(SPEEDS, STEERING_ANGLE_ZERO, 1000);
+
+    let (x, y) = odometry.position();
+    assert_eq!(x, MilliMeters((SPEED * 1000.0) as i32));
+    assert_eq!(y, MilliMeters(0));
+    assert_eq!(odometry.heading(), CentiDegrees(0));
+    assert_eq!(odometry.distance(), x);
+}
+
+#[test]
+fn turning_follows_a_circle() {
+    // the wheels turned 30 deg to the left, past half a circle
+    let odometry = drive(SPEEDS, STEERING_ANGLE_MIN, 3000);
+
+    let radius = WHEELBASE as f64 / 1000.0 / 30f64.to_radians().tan();
+    let angle = SPEED * 3.0 / radius;
+    let (x, y) = odometry.position();
+    assert!((x.meters() as f64 - radius * angle.sin()).abs() < 0.005);
+    assert!((y.meters() as f64 - radius * (1.0 - angle.cos())).abs() < 0.005);
+    // wrapped around to the right
+    let heading = angle.to_degrees() - 360.0;
+    assert!((odometry.heading().degrees() as f64 - heading).abs() < 0.1);
+}
+
+#[test]
+fn the_distance_counts_backwards_too() {
+    let backwards = SPEEDS.map(|rpm| CentiRpm(-rpm.0));
+    let odometry = drive(backwards, STEERING_ANGLE_ZERO, 1000);
+
+    let (x, _) = odometry.position();
+    assert!(x.0 < -1000);
+    assert_eq!(odometry.distance(), MilliMeters(-x.0));
+}
+
+#[test]
+fn the_pose_is_at_the_last_step_and_the_reset_keeps_it() {
+    let mut odometry = drive(SPEEDS, STEERING_ANGLE_MIN, 1000);
+    odometry.update(1025, SPEEDS, STEERING_ANGLE_MIN);
+    assert_eq!(odometry.time(), 1020);
+
+    odometry.reset();
+    assert_eq!(odometry.time(), 1020);
+    assert_eq!(odometry.position(), (MilliMeters(0), MilliMeters(0)));
+    assert_eq!(odometry.heading(), CentiDegrees(0));
+    assert_eq!(odometry.distance(), MilliMeters(0));
+}
+
+#[test]
+fn the_yaw_rate_follows_the_steering_and_the_speed() {
+    assert_eq!(
+        kinematic_yaw_rate(SPEEDS, STEERING_ANGLE_ZERO),
+        CentiDegreesPerSecond(0)
+    );
+
+    // the wheels turned 30 deg to the left
+    let rate = SPEED / (WHEELBASE as f64 / 1000.0) * 30f64.to_radians().tan();
+    let left = kinematic_yaw_rate(SPEEDS, STEERING_ANGLE_MIN);
+    assert!((left.degrees_per_second() as f64 - rate.to_degrees()).abs() < 0.1);
+    assert_eq!(
+        kinematic_yaw_rate(SPEEDS, STEERING_ANGLE_MAX),
+        CentiDegreesPerSecond(-left.0)
+    );
+    // backwards, it turns the other way
+    let backwards = SPEEDS.map(|rpm| CentiRpm(-rpm.0));
+    assert_eq!(
+        kinematic_yaw_rate(backwards, STEERING_ANGLE_MIN),
+        CentiDegreesPerSecond(-left.0)
+    );
+}
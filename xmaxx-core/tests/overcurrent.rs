@@ -0,0 +1,133 @@
+//! The protection of the motors against overcurrent.
+
+use xmaxx_core::overcurrent::*;
+use xmaxx_messages::{CentiRpm, Log, MilliAmps};
+
+const LIMITS: [MilliAmps; 4] = [MilliAmps(10_000); 4];
+const NOMINAL: [MilliAmps; 4] = [MilliAmps(5000); 4];
+/// The front left motor 25% above its limit.
+const OVER: [MilliAmps; 4] = [
+    MilliAmps(12_500),
+    MilliAmps(5000),
+    MilliAmps(5000),
+    MilliAmps(5000),
+];
+
+#[test]
+fn a_motor_above_its_limit_is_derated_in_proportion() {
+    let mut overcurrent = Overcurrent::new();
+    assert_eq!(overcurrent.update(0, NOMINAL, LIMITS), None);
+    assert_eq!(overcurrent.speeds(), [FULL_SPEED; 4]);
+
+    // at the limit, not above
+    assert_eq!(overcurrent.update(10, LIMITS, LIMITS), None);
+    assert_eq!(overcurrent.speeds(), [FULL_SPEED; 4]);
+
+    assert_eq!(overcurrent.update(20, OVER, LIMITS), None);
+    assert_eq!(
+        overcurrent.speeds(),
+        [800, FULL_SPEED, FULL_SPEED, FULL_SPEED]
+    );
+    assert!(!overcurrent.is_cut());
+
+    // the currents backwards count too
+    let backwards = OVER.map(|current| MilliAmps(-current.0));
+    let mut overcurrent = Overcurrent::new();
+    overcurrent.update(0, backwards, LIMITS);
+    assert_eq!(overcurrent.speeds()[0], 800);
+}
+
+#[test]
+fn a_derated_motor_recovers_slowly_below_its_limit() {
+    let mut overcurrent = Overcurrent::new();
+    overcurrent.update(0, OVER, LIMITS);
+
+    overcurrent.update(10, NOMINAL, LIMITS);
+    let recovered = overcurrent.speeds()[0];
+    assert!(recovered > 800 && recovered < FULL_SPEED);
+
+    for now in (20..2000).step_by(10) {
+        overcurrent.update(now, NOMINAL, LIMITS);
+    }
+    assert_eq!(overcurrent.speeds(), [FULL_SPEED; 4]);
+}
+
+#[test]
+fn a_motor_staying_above_its_limit_is_cut_once() {
+    let mut overcurrent = Overcurrent::new();
+    assert_eq!(overcurrent.update(0, OVER, LIMITS), None);
+    assert_eq!(
+        overcurrent.update(OVERCURRENT_DELAY - 1, OVER, LIMITS),
+        None
+    );
+    assert!(!overcurrent.is_cut());
+
+    assert_eq!(
+        overcurrent.update(OVERCURRENT_DELAY, OVER, LIMITS),
+        Some(Log::Overcurrent)
+    );
+    assert_eq!(overcurrent.cuts(), [true, false, false, false]);
+    assert_eq!(
+        overcurrent.speeds(),
+        [0, FULL_SPEED, FULL_SPEED, FULL_SPEED]
+    );
+    // logged once
+    assert_eq!(
+        overcurrent.update(OVERCURRENT_DELAY + 10, OVER, LIMITS),
+        None
+    );
+}
+
+#[test]
+fn going_below_the_limit_restarts_the_delay() {
+    let mut overcurrent = Overcurrent::new();
+    overcurrent.update(0, OVER, LIMITS);
+    overcurrent.update(OVERCURRENT_DELAY - 10, NOMINAL, LIMITS);
+
+    assert_eq!(overcurrent.update(OVERCURRENT_DELAY, OVER, LIMITS), None);
+    assert_eq!(
+        overcurrent.update(2 * OVERCURRENT_DELAY - 1, OVER, LIMITS),
+        None
+    );
+    assert!(!overcurrent.is_cut());
+    assert_eq!(
+        overcurrent.update(2 * OVERCURRENT_DELAY, OVER, LIMITS),
+        Some(Log::Overcurrent)
+    );
+}
+
+#[test]
+fn each_motor_is_protected_with_its_own_limit() {
+    let mut overcurrent = Overcurrent::new();
+    // the rear right motor has a lower limit
+    let limits = [10_000, 10_000, 10_000, 4000].map(MilliAmps);
+    overcurrent.update(0, NOMINAL, limits);
+    overcurrent.update(OVERCURRENT_DELAY, NOMINAL, limits);
+
+    assert_eq!(overcurrent.cuts(), [false, false, false, true]);
+    assert_eq!(
+        overcurrent.speeds(),
+        [FULL_SPEED, FULL_SPEED, FULL_SPEED, 0]
+    );
+}
+
+#[test]
+fn a_cut_motor_is_rearmed_when_its_speed_is_commanded_to_zero() {
+    let mut overcurrent = Overcurrent::new();
+    overcurrent.update(0, OVER, LIMITS);
+    overcurrent.update(OVERCURRENT_DELAY, OVER, LIMITS);
+    overcurrent.update(OVERCURRENT_DELAY + 10, NOMINAL, LIMITS);
+
+    // still commanded: stays cut, back below its limit or not
+    overcurrent.rearm([CentiRpm(10_000); 4]);
+    assert!(overcurrent.is_cut());
+    // only the other motors are commanded to zero
+    overcurrent.rearm([10_000, 0, 0, 0].map(CentiRpm));
+    assert!(overcurrent.is_cut());
+
+    overcurrent.rearm([0, 10_000, 10_000, 10_000].map(CentiRpm));
+    assert!(!overcurrent.is_cut());
+    // still derated, recovering
+    let speed = overcurrent.speeds()[0];
+    assert!(speed > 800 && speed < FULL_SPEED);
+}
@@ -0,0 +1,48 @@
+//! The validation of the parameters.
+
+use xmaxx_core::collision::{SLOW_DISTANCE, STOP_DISTANCE};
+use xmaxx_core::params::*;
+use xmaxx_messages::{Log, MilliMeters, MilliVolts, Param};
+
+#[test]
+fn the_battery_warning_stays_above_the_cutoff() {
+    let mut params = Params::new();
+    let cutoff = params.battery_cutoff.0;
+
+    assert_eq!(
+        params.set(Param::BatteryWarning, cutoff),
+        Err(Log::InvalidParam)
+    );
+    assert_eq!(
+        params.set(Param::BatteryCutoff, params.battery_warning.0),
+        Err(Log::InvalidParam)
+    );
+    assert_eq!(params.battery_warning, BATTERY_WARNING);
+    assert_eq!(params.battery_cutoff, BATTERY_CUTOFF);
+
+    // lowered together, the cutoff first
+    assert_eq!(params.set(Param::BatteryCutoff, 20_000), Ok(()));
+    assert_eq!(params.set(Param::BatteryWarning, 21_000), Ok(()));
+    assert_eq!(params.battery_warning, MilliVolts(21_000));
+    assert_eq!(params.battery_cutoff, MilliVolts(20_000));
+}
+
+#[test]
+fn the_slow_distance_stays_beyond_the_stop_distance() {
+    let mut params = Params::new();
+
+    assert_eq!(
+        params.set(Param::SlowDistance, STOP_DISTANCE.0 - 1),
+        Err(Log::InvalidParam)
+    );
+    assert_eq!(
+        params.set(Param::StopDistance, SLOW_DISTANCE.0 + 1),
+        Err(Log::InvalidParam)
+    );
+    assert_eq!(params.stop_distance, STOP_DISTANCE);
+    assert_eq!(params.slow_distance, SLOW_DISTANCE);
+
+    // stopping without slowing down first
+    assert_eq!(params.set(Param::SlowDistance, STOP_DISTANCE.0), Ok(()));
+    assert_eq!(params.slow_distance, MilliMeters(STOP_DISTANCE.0));
+}
@@ -0,0 +1,56 @@
+//! The reading of the RC receiver.
+
+use xmaxx_core::conversion::{STEERING_ANGLE_MAX, STEERING_ANGLE_ZERO};
+use xmaxx_core::rc::*;
+use xmaxx_messages::{CentiRpm, ControlMode};
+
+const SPEED: CentiRpm = CentiRpm(100_000);
+
+#[test]
+fn the_mode_switch_has_three_positions() {
+    let mut receiver = RcReceiver::new();
+    for (width, mode) in [
+        (1000, ControlMode::Host),
+        (1500, ControlMode::Auto),
+        (2000, ControlMode::Rc),
+    ] {
+        receiver.update(0, [Some(1500), Some(1500), Some(width)]);
+        assert_eq!(receiver.mode(), Some(mode));
+    }
+}
+
+#[test]
+fn the_sticks_have_a_deadband_and_reach_the_ends() {
+    let mut receiver = RcReceiver::new();
+
+    receiver.update(0, [Some(1510), Some(1490), Some(1000)]);
+    let drive = receiver.drive(SPEED).unwrap();
+    assert_eq!(drive.fl_whl_rpm, CentiRpm(0));
+    assert_eq!(drive.steering, STEERING_ANGLE_ZERO);
+
+    receiver.update(0, [Some(2100), Some(2000), Some(1000)]);
+    let drive = receiver.drive(SPEED).unwrap();
+    assert_eq!(drive.rr_whl_rpm, SPEED);
+    assert_eq!(drive.steering, STEERING_ANGLE_MAX);
+
+    receiver.update(0, [Some(1000), Some(1500), Some(1000)]);
+    assert_eq!(
+        receiver.drive(SPEED).unwrap().fl_whl_rpm,
+        CentiRpm(-SPEED.0)
+    );
+}
+
+#[test]
+fn the_signal_is_lost_without_valid_pulses() {
+    let mut receiver = RcReceiver::new();
+    assert!(!receiver.has_signal());
+
+    receiver.update(0, [Some(1500), Some(1500), Some(2000)]);
+    assert!(receiver.has_signal());
+
+    // glitches do not refresh the channels
+    receiver.update(RC_TIMEOUT, [Some(100), Some(3000), None]);
+    assert!(!receiver.has_signal());
+    assert_eq!(receiver.mode(), None);
+    assert_eq!(receiver.drive(SPEED), None);
+}
@@ -0,0 +1,143 @@
+//! The stability control with the yaw rate.
+
+use std::cell::Cell;
+use std::convert::Infallible;
+use std::rc::Rc;
+
+use embedded_hal::digital::{self, OutputPin};
+use embedded_hal::pwm::{ErrorType, SetDutyCycle};
+
+use xmaxx_core::conversion::{rpm_to_duty, DUTY_CYCLE_DENOM, STEERING_ANGLE_ZERO};
+use xmaxx_core::imu::ImuReading;
+use xmaxx_core::stability::*;
+use xmaxx_core::{Controller, Drivetrain, Readings};
+use xmaxx_messages::{CentiDegreesPerSecond, CentiRpm, Command, Drive, Log, MilliVolts, Param};
+
+const COMMANDS: [CentiRpm; 4] = [CentiRpm(100_000); 4];
+
+#[test]
+fn turning_as_steered_does_not_limit_the_speed() {
+    let mut stability = StabilityControl::new();
+    stability.update(CentiDegreesPerSecond(8000), CentiDegreesPerSecond(7200));
+
+    assert_eq!(stability.error(), 800);
+    assert!(!stability.is_unstable());
+    let mut rpms = COMMANDS;
+    stability.limit(&mut rpms);
+    assert_eq!(rpms, COMMANDS);
+}
+
+#[test]
+fn spinning_out_reduces_the_speed_in_proportion() {
+    let mut stability = StabilityControl::new();
+    // twice the margin faster than steered
+    stability.update(
+        CentiDegreesPerSecond(7200 + 2 * YAW_RATE_MARGIN.0),
+        CentiDegreesPerSecond(7200),
+    );
+
+    assert!(stability.is_unstable());
+    let mut rpms = COMMANDS;
+    stability.limit(&mut rpms);
+    assert_eq!(rpms, [CentiRpm(50_000); 4]);
+}
+
+#[test]
+fn plowing_on_reduces_the_speed_too() {
+    let mut stability = StabilityControl::new();
+    // going straight while steered to the right
+    stability.update(CentiDegreesPerSecond(0), CentiDegreesPerSecond(-8000));
+
+    assert_eq!(stability.error(), 8000);
+    let mut rpms = COMMANDS.map(|rpm| CentiRpm(-rpm.0));
+    stability.limit(&mut rpms);
+    assert_eq!(rpms, [CentiRpm(-25_000); 4]);
+
+    // and recovering lets it go
+    stability.update(CentiDegreesPerSecond(-7000), CentiDegreesPerSecond(-8000));
+    assert!(!stability.is_unstable());
+}
+
+/// A PWM output keeping its last duty cycle.
+#[derive(Clone, Default)]
+struct Pwm(Rc<Cell<u16>>);
+
+impl ErrorType for Pwm {
+    type Error = Infallible;
+}
+
+impl SetDutyCycle for Pwm {
+    fn max_duty_cycle(&self) -> u16 {
+        DUTY_CYCLE_DENOM
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+        self.0.set(duty);
+        Ok(())
+    }
+}
+
+/// A digital output going nowhere.
+struct NoPin;
+
+impl digital::ErrorType for NoPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for NoPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[test]
+fn the_controller_ignores_a_missing_imu() {
+    let motor = Pwm::default();
+    let mut controller = Controller::new(Drivetrain {
+        steering: Pwm::default(),
+        motor_fl: motor.clone(),
+        motor_fr: Pwm::default(),
+        motor_rl: Pwm::default(),
+        motor_rr: Pwm::default(),
+        enable_front: NoPin,
+        enable_rear: NoPin,
+    });
+    // no stability control without an IMU
+    assert_eq!(
+        controller.params.set(Param::StabilityControl, 1),
+        Err(Log::InvalidParam)
+    );
+    controller.params.has_imu = true;
+    assert_eq!(controller.params.set(Param::StabilityControl, 1), Ok(()));
+
+    controller.handle(Ok(Some(Command::Drive(Drive {
+        steering: STEERING_ANGLE_ZERO,
+        fl_whl_rpm: CentiRpm(10_000),
+        ..Default::default()
+    }))));
+    // spinning while going straight, five times the margin
+    let spinning = ImuReading {
+        gyro: [
+            CentiDegreesPerSecond(0),
+            CentiDegreesPerSecond(0),
+            CentiDegreesPerSecond(5 * YAW_RATE_MARGIN.0),
+        ],
+        ..Default::default()
+    };
+    let mut readings = Readings {
+        voltage: MilliVolts(32_000),
+        imu: Some(spinning),
+        ..Default::default()
+    };
+    controller.update(0, readings).for_each(drop);
+    assert_eq!(motor.0.get(), rpm_to_duty(CentiRpm(2000)));
+
+    // the last reading is stale once the IMU stops answering
+    readings.imu = None;
+    controller.update(10, readings).for_each(drop);
+    assert_eq!(motor.0.get(), rpm_to_duty(CentiRpm(10_000)));
+}
@@ -0,0 +1,151 @@
+//! The blink codes of the status LED.
+
+use std::cell::Cell;
+use std::convert::Infallible;
+use std::rc::Rc;
+
+use embedded_hal::digital::{self, OutputPin};
+use embedded_hal::pwm::{ErrorType, SetDutyCycle};
+use embedded_hal_mock::eh1::digital::{Mock, State, Transaction};
+
+use xmaxx_core::conversion::{rpm_to_duty, DUTY_CYCLE_DENOM, STEERING_ANGLE_ZERO};
+use xmaxx_core::rc::HOST_TIMEOUT;
+use xmaxx_core::status::*;
+use xmaxx_core::{Controller, Drivetrain, Readings};
+use xmaxx_messages::{CentiRpm, Command, ControlSource, Drive, MilliVolts};
+
+const STATUSES: [Status; 5] = [
+    Status::WaitingForHost,
+    Status::Armed,
+    Status::Timeout,
+    Status::LowBattery,
+    Status::Fault,
+];
+
+/// Returns how many times the LED turns on in a cycle.
+fn blinks(status: Status) -> usize {
+    let lit: Vec<bool> = (0..BLINK_CYCLE)
+        .step_by(BLINK_SLOT as usize)
+        .map(|now| status.is_lit(now))
+        .collect();
+    (0..lit.len())
+        .filter(|&i| lit[i] && (i == 0 || !lit[i - 1]))
+        .count()
+}
+
+#[test]
+fn the_statuses_blink_a_different_number_of_times() {
+    assert_eq!(STATUSES.map(blinks), [1, 1, 2, 3, 4]);
+    // the armed LED stays on
+    assert!((0..BLINK_CYCLE).all(|now| Status::Armed.is_lit(now)));
+    assert!(!(0..BLINK_CYCLE).all(|now| Status::WaitingForHost.is_lit(now)));
+}
+
+#[test]
+fn the_patterns_repeat_every_cycle() {
+    for status in STATUSES {
+        for now in (0..BLINK_CYCLE).step_by(25) {
+            assert_eq!(status.is_lit(now), status.is_lit(now + 3 * BLINK_CYCLE));
+        }
+    }
+}
+
+#[test]
+fn the_led_follows_the_pattern() {
+    let expectations = [
+        Transaction::set(State::High),
+        Transaction::set(State::Low),
+        Transaction::set(State::High),
+        Transaction::set(State::Low),
+    ];
+    let mut led = StatusLed::new(Mock::new(&expectations));
+
+    for now in [0, BLINK_SLOT, 2 * BLINK_SLOT, 5 * BLINK_SLOT] {
+        led.update(now, Status::Timeout);
+    }
+
+    led.release().done();
+}
+
+/// A PWM output keeping its last duty cycle.
+#[derive(Clone, Default)]
+struct Pwm(Rc<Cell<u16>>);
+
+impl ErrorType for Pwm {
+    type Error = Infallible;
+}
+
+impl SetDutyCycle for Pwm {
+    fn max_duty_cycle(&self) -> u16 {
+        DUTY_CYCLE_DENOM
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+        self.0.set(duty);
+        Ok(())
+    }
+}
+
+/// A digital output going nowhere.
+struct NoPin;
+
+impl digital::ErrorType for NoPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for NoPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[test]
+fn the_wheels_stop_when_the_host_stops_sending_commands() {
+    let motor = Pwm::default();
+    let mut controller = Controller::new(Drivetrain {
+        steering: Pwm::default(),
+        motor_fl: motor.clone(),
+        motor_fr: Pwm::default(),
+        motor_rl: Pwm::default(),
+        motor_rr: Pwm::default(),
+        enable_front: NoPin,
+        enable_rear: NoPin,
+    });
+    let readings = Readings {
+        voltage: MilliVolts(32_000),
+        ..Default::default()
+    };
+    let forwards = Drive {
+        steering: STEERING_ANGLE_ZERO,
+        fl_whl_rpm: CentiRpm(10_000),
+        ..Default::default()
+    };
+
+    controller.update(0, readings).for_each(drop);
+    assert_eq!(controller.status(0), Status::WaitingForHost);
+
+    controller.handle(Ok(Some(Command::Drive(forwards.clone()))));
+    controller.update(10, readings).for_each(drop);
+    assert_eq!(controller.status(10), Status::Armed);
+    assert_eq!(motor.0.get(), rpm_to_duty(CentiRpm(10_000)));
+
+    // the last command is followed until the timeout
+    let timeout = 10 + HOST_TIMEOUT;
+    controller.update(timeout - 1, readings).for_each(drop);
+    assert_eq!(motor.0.get(), rpm_to_duty(CentiRpm(10_000)));
+
+    controller.update(timeout, readings).for_each(drop);
+    assert_eq!(controller.control().source, ControlSource::Stopped);
+    assert_eq!(controller.status(timeout), Status::Timeout);
+    assert_eq!(motor.0.get(), rpm_to_duty(CentiRpm(0)));
+
+    // until the host sends again
+    controller.handle(Ok(Some(Command::Drive(forwards))));
+    controller.update(timeout + 10, readings).for_each(drop);
+    assert_eq!(controller.status(timeout + 10), Status::Armed);
+    assert_eq!(motor.0.get(), rpm_to_duty(CentiRpm(10_000)));
+}
@@ -0,0 +1,105 @@
+//! The telemetry sent in each iteration of the main loop.
+
+use std::convert::Infallible;
+
+use embedded_hal::digital::{self, OutputPin};
+use embedded_hal::pwm::{ErrorType, SetDutyCycle};
+
+use xmaxx_core::conversion::DUTY_CYCLE_DENOM;
+use xmaxx_core::{Controller, Drivetrain, Telemetry};
+use xmaxx_messages::Info;
+
+/// A PWM output going nowhere.
+struct NoPwm;
+
+impl ErrorType for NoPwm {
+    type Error = Infallible;
+}
+
+impl SetDutyCycle for NoPwm {
+    fn max_duty_cycle(&self) -> u16 {
+        DUTY_CYCLE_DENOM
+    }
+
+    fn set_duty_cycle(&mut self, _duty: u16) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// A digital output going nowhere.
+struct NoPin;
+
+impl digital::ErrorType for NoPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for NoPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+fn controller() -> Controller<NoPwm, NoPwm, NoPwm, NoPwm, NoPwm, NoPin, NoPin> {
+    Controller::new(Drivetrain {
+        steering: NoPwm,
+        motor_fl: NoPwm,
+        motor_fr: NoPwm,
+        motor_rl: NoPwm,
+        motor_rr: NoPwm,
+        enable_front: NoPin,
+        enable_rear: NoPin,
+    })
+}
+
+/// Returns the names of the messages of an iteration.
+fn names(telemetry: impl Iterator<Item = Info>) -> Vec<&'static str> {
+    telemetry
+        .map(|info| match info {
+            Info::Sensors(_) => "sensors",
+            Info::Odometry(_) => "odometry",
+            Info::Currents(_) => "currents",
+            Info::Battery(_) => "battery",
+            Info::Axles(_) => "axles",
+            Info::Imu(_) => "imu",
+            _ => "other",
+        })
+        .collect()
+}
+
+#[test]
+fn the_slow_telemetry_is_sent_in_turn() {
+    let mut controller = controller();
+    // without encoders and range sensors, like a firmware without them
+    let slow = [
+        Telemetry::Currents,
+        Telemetry::Battery,
+        Telemetry::Axles,
+        Telemetry::Imu,
+    ];
+
+    let sent: Vec<_> = (0..5).map(|_| names(controller.telemetry(&slow))).collect();
+
+    assert_eq!(
+        sent,
+        [
+            ["sensors", "odometry", "currents"],
+            ["sensors", "odometry", "battery"],
+            ["sensors", "odometry", "axles"],
+            ["sensors", "odometry", "imu"],
+            ["sensors", "odometry", "currents"],
+        ]
+    );
+}
+
+#[test]
+fn the_odometry_is_sent_every_iteration() {
+    let mut controller = controller();
+
+    for _ in 0..3 {
+        assert_eq!(names(controller.telemetry(&[])), ["sensors", "odometry"]);
+    }
+}
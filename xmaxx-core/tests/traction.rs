@@ -0,0 +1,61 @@
+//! The detection of the slipping wheels.
+
+use xmaxx_core::traction::*;
+use xmaxx_messages::{CentiRpm, PerMille};
+
+const COMMANDS: [CentiRpm; 4] = [CentiRpm(100_000); 4];
+
+#[test]
+fn a_wheel_faster_than_the_others_slips() {
+    let mut traction = TractionControl::new();
+    let speeds = [8000, 5000, 5000, 5000].map(CentiRpm);
+    traction.update(speeds, COMMANDS, SLIP_THRESHOLD);
+
+    assert_eq!(
+        traction.slips(),
+        [PerMille(375), PerMille(0), PerMille(0), PerMille(0)]
+    );
+    assert!(traction.is_slipping());
+
+    // reduced in proportion to the slip above the threshold, by
+    // (1 - 0.375) / (1 - 0.2)
+    let mut rpms = COMMANDS;
+    traction.limit(&mut rpms);
+    assert_eq!(rpms[0], CentiRpm(78_100));
+    assert_eq!(rpms[1..], COMMANDS[1..]);
+}
+
+#[test]
+fn the_wheels_turning_for_their_command_do_not_slip() {
+    let mut traction = TractionControl::new();
+    // the outer wheels are commanded faster in a turn, and the speeds are
+    // measured in other units than the commands
+    let commands = [120_000, 80_000, 120_000, 80_000].map(CentiRpm);
+    let speeds = [6000, 4000, 6000, 4000].map(CentiRpm);
+    traction.update(speeds, commands, SLIP_THRESHOLD);
+
+    assert_eq!(traction.slips(), [PerMille(0); 4]);
+    assert!(!traction.is_slipping());
+}
+
+#[test]
+fn slow_wheels_do_not_slip_within_the_noise() {
+    let mut traction = TractionControl::new();
+    // backwards, from rest
+    let commands = [CentiRpm(-100_000); 4];
+    let speeds = [-900, -100, -100, -100].map(CentiRpm);
+    traction.update(speeds, commands, SLIP_THRESHOLD);
+
+    assert!(traction.slips()[0] > SLIP_THRESHOLD);
+    assert!(!traction.is_slipping());
+}
+
+#[test]
+fn nothing_slips_without_command() {
+    let mut traction = TractionControl::new();
+    let speeds = [8000, 5000, 5000, 5000].map(CentiRpm);
+    traction.update(speeds, [CentiRpm(0); 4], SLIP_THRESHOLD);
+
+    assert_eq!(traction.slips(), [PerMille(0); 4]);
+    assert!(!traction.is_slipping());
+}
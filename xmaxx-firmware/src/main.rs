@@ -2,153 +2,44 @@
 #![no_main]
 #![feature(abi_avr_interrupt)]
 
-use core::ops::RangeInclusive;
-
 use arduino_hal::simple_pwm::*;
-use embedded_hal::pwm::SetDutyCycle;
-use embedded_hal_v0::serial::{Read, Write};
 
+#[cfg(feature = "imu")]
+use xmaxx_core::imu::{ImuReader, Mpu6050, IMU_ADDRESS};
+use xmaxx_core::readbuf::ReadBuf;
+use xmaxx_core::serial::{read_command, write_event};
+use xmaxx_core::status::StatusLed;
+use xmaxx_core::{Controller, Drivetrain, Telemetry};
 use xmaxx_messages::*;
 
 mod utils;
+use utils::adc::*;
 use utils::debug::*;
-use utils::readbuf::ReadBuf;
+#[cfg(feature = "encoders")]
+use utils::encoders::*;
+#[cfg(feature = "proximity")]
+use utils::proximity::*;
+#[cfg(feature = "rc")]
+use utils::rc::*;
+#[cfg(any(feature = "rc", feature = "proximity"))]
+use utils::time::init_pulse_clock;
 use utils::time::{init_millis, millis};
 
-/// Read a command from serial.
-///
-/// **Note:** for this function to work properly, each byte must be sent slowly
-/// enough for the microcontroller to read them on time.
-///
-fn read_command<const N: usize>(
-    read_buf: &mut ReadBuf<{ N }>,
-    serial: &mut impl Read<u8>,
-) -> Result<Option<Command>, Log> {
-    while let Ok(byte) = serial.read() {
-        // reset on overflow or it will always fail
-        read_buf.push(byte).or_else(|_| {
-            read_buf.reset();
-            Err(Log::ReadBufferOverflow)
-        })?;
-
-        // null char is the separator in cobs encoding
-        if byte == '\0' as u8 {
-            // reset buffer on deserialization error or will fail forever after
-            let command: Command = deserialize(read_buf.as_mut_slice()).or_else(|_| {
-                read_buf.reset();
-                Err(Log::DeserializationError)
-            })?;
-
-            read_buf.reset();
-            return Ok(Some(command));
-        }
-    }
-
-    Ok(None)
-}
-
-/// Write information to serial.
-fn write_event(
-    info: &Info,
-    write_buf: &mut [u8],
-    serial: &mut impl Write<u8>,
-) -> Result<(), Log> {
-    let msg = serialize(info, write_buf).or_else(|_| Err(Log::SerializationError))?;
-    for b in msg {
-        let _ = nb::block!(serial.write(*b)); // should be infallible, cannot .expect() because some trait is not implemented
-    }
-    Ok(())
-}
-
-const SCALE: i32 = 100;
-const DUTY_CYCLE_DENOM: u16 = 1000;
-
-const STEERING_DUTY_MIN: i32 = 510; // 130 / 255 * 1000
-const STEERING_DUTY_ZERO: i32 = 745; // 190 / 255 * 1000
-const STEERING_DUTY_MAX: i32 = 980; // 250 / 255 * 1000
-const STEERING_ANGLE_MIN: i32 = 35 * SCALE; // SCALE-deg
-const STEERING_ANGLE_MAX: i32 = 135 * SCALE; // SCALE-deg
-const STEERING_ANGLE_RANGE: RangeInclusive<i32> = STEERING_ANGLE_MIN..=STEERING_ANGLE_MAX; // SCALE-deg
-
-/// Compute the duty cycle to achieve the desired angle (SCALE-degrees).
-///
-/// It assumes that `angle` is in the steering range of motion.
-fn angle_to_duty(angle: i32) -> u16 {
-    let delta_duty = STEERING_DUTY_MAX - STEERING_DUTY_MIN;
-    let delta_angle = STEERING_ANGLE_MAX - STEERING_ANGLE_MIN;
-
-    // safe to cast: all positive and in range of u16
-    (delta_duty * angle / delta_angle + STEERING_DUTY_ZERO) as u16
-}
-
-const MOTOR_DUTY_NUM_MIN: i32 = 100;
-const MOTOR_DUTY_NUM_ZERO: i32 = 500;
-const MOTOR_DUTY_NUM_MAX: i32 = 900;
-const RPM_MIN: i32 = -4500 * SCALE; // SCALE-RPM
-const RPM_MAX: i32 = 4500 * SCALE; // SCALE-RPM
-const RPM_RANGE: RangeInclusive<i32> = RPM_MIN..=RPM_MAX; // SCALE-RPM
-
-/// Computes the duty cycle to achieve the wheel RPM (SCALE-RPM).
-///
-/// It assumes that `rpm` is in the range.
-fn rpm_to_duty(rpm: i32) -> u16 {
-    let delta_duty = MOTOR_DUTY_NUM_MAX - MOTOR_DUTY_NUM_MIN;
-    let delta_rpm = RPM_MAX - RPM_MIN;
-
-    (delta_duty * rpm / delta_rpm + MOTOR_DUTY_NUM_ZERO) as u16
-}
-
-const ANALOG_ZERO_RPM: i32 = 412; // analog_unit
-                                  // const MAX_RPM: u16 = 4500;
-const ANALOG: i32 = 410; // half analog range
-const GEARING_10: i32 = 106; // 10.6 (motor) : 1 (wheel)
-                             // const WHEEL_RADIUS: f32 = 0.1; // m
-                             // const CURRENT_RANGE: RangeInclusive<f32> = -8.0..=8.0; // A
-
-/// Computes the wheel RPM from the analog reading.
-fn analog_to_rpm(analog: i32) -> i32 {
-    //     (Fxp::from_num(MAX_RPM * (analog - ANALOG_ZERO_RPM))
-    //         / Fxp::from_num(GEARING)
-    //         / Fxp::from_num(ANALOG))
-    //     .to_num::<f32>()
-    RPM_MAX / SCALE * (analog - ANALOG_ZERO_RPM) / (ANALOG * GEARING_10 / 10)
-}
-
-fn execute(
-    command: Command,
-    steering: &mut impl SetDutyCycle,
-    motor_fl: &mut impl SetDutyCycle,
-    motor_fr: &mut impl SetDutyCycle,
-    motor_rl: &mut impl SetDutyCycle,
-    motor_rr: &mut impl SetDutyCycle,
-) -> Result<(), Log> {
-    if !(STEERING_ANGLE_RANGE.contains(&command.steering))
-        || !(RPM_RANGE.contains(&command.fl_whl_rpm))
-        || !(RPM_RANGE.contains(&command.fr_whl_rpm))
-        || !(RPM_RANGE.contains(&command.rl_whl_rpm))
-        || !(RPM_RANGE.contains(&command.rr_whl_rpm))
-    {
-        return Err(Log::InvalidCommand);
-    }
-
-    steering
-        .set_duty_cycle_fraction(angle_to_duty(command.steering), DUTY_CYCLE_DENOM)
-        .expect("duty cycle should not be too large");
-    motor_fl
-        .set_duty_cycle_fraction(rpm_to_duty(command.fl_whl_rpm), DUTY_CYCLE_DENOM)
-        .expect("duty cycle should not be too large");
-    motor_fr
-        .set_duty_cycle_fraction(rpm_to_duty(command.fr_whl_rpm), DUTY_CYCLE_DENOM)
-        .expect("duty cycle should not be too large");
-    motor_rl
-        .set_duty_cycle_fraction(rpm_to_duty(command.rl_whl_rpm), DUTY_CYCLE_DENOM)
-        .expect("duty cycle should not be too large");
-    motor_rr
-        .set_duty_cycle_fraction(rpm_to_duty(command.rr_whl_rpm), DUTY_CYCLE_DENOM)
-        .expect("duty cycle should not be too large");
-
-    Ok(())
-}
+/// The telemetry sent one an iteration in turn, after the wheel speeds and
+/// the odometry, of the features compiled in.
+const SLOW_TELEMETRY: &[Telemetry] = &[
+    Telemetry::Currents,
+    Telemetry::Battery,
+    Telemetry::Control,
+    Telemetry::Traction,
+    Telemetry::Axles,
+    #[cfg(feature = "encoders")]
+    Telemetry::Encoders,
+    #[cfg(feature = "imu")]
+    Telemetry::Imu,
+    #[cfg(feature = "proximity")]
+    Telemetry::Proximity,
+];
 
 #[arduino_hal::entry]
 fn main() -> ! {
@@ -161,6 +52,8 @@ fn main() -> ! {
     init_debug(debug);
 
     init_millis(dp.TC0);
+    #[cfg(any(feature = "rc", feature = "proximity"))]
+    init_pulse_clock(dp.TC5);
 
     unsafe { avr_device::interrupt::enable() };
 
@@ -174,11 +67,9 @@ fn main() -> ! {
     let mut steering = pins.d12.into_output().into_pwm(&mut timer1);
     steering.enable(); // really important
 
-    // motors setup
-    let mut enable_front = pins.d8.into_output();
-    let mut enable_rear = pins.d11.into_output();
-    enable_front.set_high();
-    enable_rear.set_high();
+    // motors setup, their drivers are enabled by the controller
+    let enable_front = pins.d8.into_output();
+    let enable_rear = pins.d11.into_output();
 
     let timer3 = Timer3Pwm::new(dp.TC3, Prescaler::Prescale64);
     let timer4 = Timer4Pwm::new(dp.TC4, Prescaler::Prescale64);
@@ -191,56 +82,142 @@ fn main() -> ! {
     motor_rl.enable();
     motor_rr.enable();
 
-    // motor speed sensor setup
+    // motor speed, motor current and battery voltage (through a voltage
+    // divider) sensor setup
+    #[cfg(not(feature = "free-running-adc"))]
     let mut adc = arduino_hal::Adc::new(dp.ADC, Default::default());
-    let speed_fl = pins.a3.into_analog_input(&mut adc); // current a0?
-    let speed_fr = pins.a2.into_analog_input(&mut adc); // a1?
-    let speed_rl = pins.a7.into_analog_input(&mut adc); // a4?
-    let speed_rr = pins.a6.into_analog_input(&mut adc); // a5?
+    #[cfg(not(feature = "free-running-adc"))]
+    let channels = [
+        pins.a3.into_analog_input(&mut adc).into_channel(),
+        pins.a2.into_analog_input(&mut adc).into_channel(),
+        pins.a7.into_analog_input(&mut adc).into_channel(),
+        pins.a6.into_analog_input(&mut adc).into_channel(),
+        pins.a0.into_analog_input(&mut adc).into_channel(),
+        pins.a1.into_analog_input(&mut adc).into_channel(),
+        pins.a4.into_analog_input(&mut adc).into_channel(),
+        pins.a5.into_analog_input(&mut adc).into_channel(),
+        pins.a8.into_analog_input(&mut adc).into_channel(),
+    ];
+    #[cfg(feature = "free-running-adc")]
+    init_adc(dp.ADC);
+
+    // wheel encoders setup, channels A on a9 to a12 and B on d53 to d50
+    #[cfg(feature = "encoders")]
+    {
+        pins.a9.into_pull_up_input();
+        pins.a10.into_pull_up_input();
+        pins.a11.into_pull_up_input();
+        pins.a12.into_pull_up_input();
+        pins.d53.into_pull_up_input();
+        pins.d52.into_pull_up_input();
+        pins.d51.into_pull_up_input();
+        pins.d50.into_pull_up_input();
+        init_encoders(&dp.EXINT);
+    }
 
-    let _led = pins.d13.into_output();
+    // RC receiver setup, throttle on a13, steering on a14 and mode switch on
+    // a15
+    #[cfg(feature = "rc")]
+    {
+        pins.a13.into_floating_input();
+        pins.a14.into_floating_input();
+        pins.a15.into_floating_input();
+        init_rc(&dp.EXINT);
+    }
+
+    // range sensors setup, front triggered on d22 with its echo on d14 and
+    // rear on d23 and d15
+    #[cfg(feature = "proximity")]
+    let mut triggers = {
+        pins.d14.into_floating_input();
+        pins.d15.into_floating_input();
+        init_proximity(&dp.EXINT);
+        Triggers::new([
+            pins.d22.into_output().downgrade(),
+            pins.d23.into_output().downgrade(),
+        ])
+    };
+
+    let mut led = StatusLed::new(pins.d13.into_output());
+
+    let mut controller = Controller::new(Drivetrain {
+        steering,
+        motor_fl,
+        motor_fr,
+        motor_rl,
+        motor_rr,
+        enable_front,
+        enable_rear,
+    });
+
+    // the stability control needs the IMU
+    controller.params.has_imu = cfg!(feature = "imu");
+
+    // IMU setup, on the I2C bus (SDA on d20, SCL on d21), initialized at the
+    // first iteration
+    #[cfg(feature = "imu")]
+    let mut imu = ImuReader::new(
+        Mpu6050::new(
+            arduino_hal::I2c::new(
+                dp.TWI,
+                pins.d20.into_pull_up_input(),
+                pins.d21.into_pull_up_input(),
+                400_000,
+            ),
+            IMU_ADDRESS,
+        ),
+        controller.params.imu_rate,
+    );
 
     loop {
-        // read from serial
-        match read_command(&mut read_buf, &mut serial) {
-            Ok(Some(command)) => {
-                // execute the command
-                if let Err(log) = execute(
-                    command,
-                    &mut steering,
-                    &mut motor_fl,
-                    &mut motor_fr,
-                    &mut motor_rl,
-                    &mut motor_rr,
-                ) {
-                    write_event(&Info::Log(log), &mut write_buf, &mut serial)
-                        .expect("should work because valid message and big enough buffer");
-                }
+        // read from serial and answer
+        let command = read_command(&mut read_buf, &mut serial);
+        if let Some(info) = controller.handle(command) {
+            write_event(&info, &mut write_buf, &mut serial)
+                .expect("should work because valid message and big enough buffer");
+        }
+
+        // read the sensors and follow the command within the limits
+        #[cfg(not(feature = "free-running-adc"))]
+        let samples = sample(&mut adc, &channels, controller.params.speed_oversampling);
+        #[cfg(feature = "free-running-adc")]
+        let samples = take_samples();
+        #[allow(unused_mut)]
+        let mut readings = samples.readings();
+        #[cfg(feature = "encoders")]
+        {
+            readings.ticks = ticks();
+        }
+        #[cfg(feature = "rc")]
+        {
+            readings.rc = take_pulses();
+        }
+        #[cfg(feature = "proximity")]
+        {
+            readings.echoes = take_echoes();
+            triggers.update(millis());
+        }
+        // keep driving without the IMU, which is set up again later
+        #[cfg(feature = "imu")]
+        {
+            let (reading, log) = imu.update(millis(), controller.params.imu_rate);
+            readings.imu = reading;
+            if let Some(log) = log {
+                write_event(&Info::Log(log), &mut write_buf, &mut serial)
+                    .expect("should work because valid message and big enough buffer");
             }
-            // there was no command
-            Ok(None) => write_event(
-                &Info::Log(Log::NoCommandReceived),
-                &mut write_buf,
-                &mut serial,
-            )
-            .expect("should work because valid message and big enough buffer"),
-            // could not read a command
-            Err(log) => write_event(&Info::Log(log), &mut write_buf, &mut serial)
-                .expect("should work because valid message and big enough buffer"),
-        };
-
-        // write Sensors to serial
-        let fl_whl_rpm = analog_to_rpm(speed_fl.analog_read(&mut adc).into());
-        let fr_whl_rpm = analog_to_rpm(speed_fr.analog_read(&mut adc).into());
-        let rl_whl_rpm = analog_to_rpm(speed_rl.analog_read(&mut adc).into());
-        let rr_whl_rpm = analog_to_rpm(speed_rr.analog_read(&mut adc).into());
-        let sensors = Sensors {
-            fl_whl_rpm,
-            fr_whl_rpm,
-            rl_whl_rpm,
-            rr_whl_rpm,
-        };
-        write_event(&Info::Sensors(sensors), &mut write_buf, &mut serial)
-            .expect("should work because valid message and big enough buffer");
+        }
+        for log in controller.update(millis(), readings) {
+            write_event(&Info::Log(log), &mut write_buf, &mut serial)
+                .expect("should work because valid message and big enough buffer");
+        }
+        let now = millis();
+        led.update(now, controller.status(now));
+
+        // write the telemetry to serial
+        for info in controller.telemetry(SLOW_TELEMETRY) {
+            write_event(&info, &mut write_buf, &mut serial)
+                .expect("should work because valid message and big enough buffer");
+        }
     }
 }
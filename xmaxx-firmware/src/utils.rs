@@ -1,4 +1,10 @@
+pub mod adc;
 pub mod debug;
+#[cfg(feature = "encoders")]
+pub mod encoders;
 pub mod panic;
-pub mod readbuf;
+#[cfg(feature = "proximity")]
+pub mod proximity;
+#[cfg(feature = "rc")]
+pub mod rc;
 pub mod time;
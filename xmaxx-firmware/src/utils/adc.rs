@@ -0,0 +1,180 @@
+//! The sampling of the analog sensors.
+//!
+//! By default, the main loop reads the channels one after the other with the
+//! blocking ADC of the HAL. With the `free-running-adc` feature, the ADC
+//! samples them in the background from its interrupt instead, and the main
+//! loop takes the sum of the samples since its previous iteration.
+
+use xmaxx_core::conversion::{analog_sum_to_rpm, analog_to_current, analog_to_voltage};
+use xmaxx_core::Readings;
+
+/// The number of analog channels: the speeds fl, fr, rl, rr, the currents
+/// fl, fr, rl, rr and the battery voltage.
+pub const CHANNELS: usize = 9;
+
+/// The sums of the samples of each channel with their number.
+pub struct Samples {
+    pub sums: [i32; CHANNELS],
+    pub counts: [i32; CHANNELS],
+}
+
+impl Samples {
+    const EMPTY: Self = Self {
+        sums: [0; CHANNELS],
+        counts: [0; CHANNELS],
+    };
+
+    /// Converts the means of the samples.
+    pub fn readings(&self) -> Readings {
+        let mean = |i: usize| self.sums[i] / self.counts[i];
+        Readings {
+            speeds: [0, 1, 2, 3].map(|i| analog_sum_to_rpm(self.sums[i], self.counts[i])),
+            currents: [4, 5, 6, 7].map(|i| analog_to_current(mean(i))),
+            voltage: analog_to_voltage(mean(8)),
+            // the other sensors are not analog
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(not(feature = "free-running-adc"))]
+pub use blocking::*;
+#[cfg(feature = "free-running-adc")]
+pub use free_running::*;
+
+#[cfg(not(feature = "free-running-adc"))]
+mod blocking {
+    use arduino_hal::adc::Channel;
+    use arduino_hal::Adc;
+
+    use super::*;
+
+    /// Reads the speeds `oversampling` times and the other channels once.
+    pub fn sample(adc: &mut Adc, channels: &[Channel; CHANNELS], oversampling: u8) -> Samples {
+        let mut samples = Samples::EMPTY;
+        for (i, channel) in channels.iter().enumerate() {
+            let n = if i < 4 { oversampling } else { 1 };
+            for _ in 0..n {
+                samples.sums[i] += i32::from(adc.read_blocking(channel));
+            }
+            samples.counts[i] = n.into();
+        }
+        samples
+    }
+}
+
+#[cfg(feature = "free-running-adc")]
+mod free_running {
+    use core::cell::RefCell;
+    use core::mem;
+
+    use arduino_hal::pac::ADC;
+    use avr_device::interrupt::Mutex;
+
+    use super::*;
+
+    /// The multiplexer channels in the order of [`Samples`]: a3, a2, a7, a6,
+    /// a0, a1, a4, a5 and a8.
+    const MUX: [u8; CHANNELS] = [3, 2, 7, 6, 0, 1, 4, 5, 8];
+
+    struct Sampler {
+        adc: ADC,
+        /// The index of the channel being converted.
+        channel: usize,
+        samples: Samples,
+        /// The last sample of each channel, for the channels without new
+        /// samples.
+        last: [u16; CHANNELS],
+        /// Whether every channel was sampled once.
+        ready: bool,
+    }
+
+    static SAMPLER: Mutex<RefCell<Option<Sampler>>> = Mutex::new(RefCell::new(None));
+
+    /// Starts sampling the channels in the background.
+    ///
+    /// Each conversion is started from the interrupt of the previous one,
+    /// after switching the multiplexer, so a sample always belongs to the
+    /// channel it was taken from. A conversion takes 104 us, so each
+    /// channel is sampled about every millisecond.
+    pub fn init_adc(adc: ADC) {
+        // the analog pins do not need their digital input buffers
+        adc.didr0.write(|w| unsafe { w.bits(0xff) });
+        adc.didr2.write(|w| unsafe { w.bits(0x01) });
+        adc.admux.write(|w| w.refs().avcc());
+        set_channel(&adc, MUX[0]);
+
+        avr_device::interrupt::free(|cs| {
+            // 16 MHz / 128, the fastest clock for the full 10 bits
+            adc.adcsra.write(|w| {
+                w.aden()
+                    .set_bit()
+                    .adie()
+                    .set_bit()
+                    .adps()
+                    .prescaler_128()
+                    .adsc()
+                    .set_bit()
+            });
+            *SAMPLER.borrow(cs).borrow_mut() = Some(Sampler {
+                adc,
+                channel: 0,
+                samples: Samples::EMPTY,
+                last: [0; CHANNELS],
+                ready: false,
+            });
+        });
+    }
+
+    fn set_channel(adc: &ADC, channel: u8) {
+        adc.admux.modify(|_, w| w.mux().bits(channel & 0x07));
+        adc.adcsrb.modify(|_, w| w.mux5().bit(channel & 0x08 != 0));
+    }
+
+    #[avr_device::interrupt(atmega2560)]
+    fn ADC() {
+        avr_device::interrupt::free(|cs| {
+            if let Some(sampler) = SAMPLER.borrow(cs).borrow_mut().as_mut() {
+                let sample = sampler.adc.adc.read().bits();
+                let i = sampler.channel;
+                sampler.samples.sums[i] += i32::from(sample);
+                sampler.samples.counts[i] += 1;
+                sampler.last[i] = sample;
+                sampler.ready |= i == CHANNELS - 1;
+
+                sampler.channel = (i + 1) % CHANNELS;
+                set_channel(&sampler.adc, MUX[sampler.channel]);
+                sampler.adc.adcsra.modify(|_, w| w.adsc().set_bit());
+            }
+        })
+    }
+
+    /// Returns the samples since the previous call, with the last sample of
+    /// the channels without new ones.
+    ///
+    /// It waits for every channel to be sampled once after [`init_adc`].
+    pub fn take_samples() -> Samples {
+        loop {
+            let samples = avr_device::interrupt::free(|cs| {
+                let mut sampler = SAMPLER.borrow(cs).borrow_mut();
+                let sampler = sampler.as_mut().expect("the ADC should be initialized");
+                if !sampler.ready {
+                    return None;
+                }
+
+                let mut samples = mem::replace(&mut sampler.samples, Samples::EMPTY);
+                for (i, last) in sampler.last.iter().enumerate() {
+                    if samples.counts[i] == 0 {
+                        samples.sums[i] = i32::from(*last);
+                        samples.counts[i] = 1;
+                    }
+                }
+                Some(samples)
+            });
+
+            if let Some(samples) = samples {
+                return samples;
+            }
+        }
+    }
+}
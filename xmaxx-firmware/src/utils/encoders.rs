@@ -0,0 +1,77 @@
+//! The counting of the ticks of the wheel encoders.
+//!
+//! The encoders are quadrature encoders, or hall-effect sensors with two
+//! outputs in quadrature. Their channels A are on a9 to a12 (PK1 to PK4)
+//! and their channels B on d53 to d50 (PB0 to PB3): fl, fr, rl, rr. Every
+//! edge of both channels is counted from the pin-change interrupts of the
+//! two ports.
+
+use core::cell::Cell;
+
+use arduino_hal::pac::{EXINT, PORTB, PORTK};
+use avr_device::interrupt::Mutex;
+
+use xmaxx_core::encoder::quadrature_step;
+
+/// The ticks of the wheels: fl, fr, rl, rr.
+static TICKS: Mutex<Cell<[i32; 4]>> = Mutex::new(Cell::new([0; 4]));
+/// The last states of the channels of the encoders, as `A << 1 | B`.
+static STATES: Mutex<Cell<[u8; 4]>> = Mutex::new(Cell::new([0; 4]));
+
+/// Starts counting the ticks.
+///
+/// The pins must be inputs, with pull-ups for open-collector sensors.
+pub fn init_encoders(exint: &EXINT) {
+    avr_device::interrupt::free(|cs| STATES.borrow(cs).set(states()));
+
+    // PCINT0 to PCINT3 on port B and PCINT17 to PCINT20 on port K, which
+    // the RC receiver may share
+    exint
+        .pcmsk0
+        .modify(|r, w| unsafe { w.bits(r.bits() | 0b0000_1111) });
+    exint
+        .pcmsk2
+        .modify(|r, w| unsafe { w.bits(r.bits() | 0b0001_1110) });
+    exint
+        .pcicr
+        .modify(|r, w| unsafe { w.bits(r.bits() | 0b101) });
+}
+
+/// Returns the states of the channels of the encoders.
+fn states() -> [u8; 4] {
+    // only reads the input registers, which the HAL does not hold
+    let a = unsafe { (*PORTK::ptr()).pink.read().bits() } >> 1;
+    let b = unsafe { (*PORTB::ptr()).pinb.read().bits() };
+    [0, 1, 2, 3].map(|i| (a >> i & 1) << 1 | (b >> i & 1))
+}
+
+fn count() {
+    avr_device::interrupt::free(|cs| {
+        let states = states();
+        let previous = STATES.borrow(cs).replace(states);
+
+        let ticks = TICKS.borrow(cs);
+        let mut counts = ticks.get();
+        for ((count, previous), state) in counts.iter_mut().zip(previous).zip(states) {
+            *count = count.wrapping_add(quadrature_step(previous, state));
+        }
+        ticks.set(counts);
+    })
+}
+
+#[avr_device::interrupt(atmega2560)]
+fn PCINT0() {
+    count()
+}
+
+#[avr_device::interrupt(atmega2560)]
+fn PCINT2() {
+    count();
+    #[cfg(feature = "rc")]
+    super::rc::capture();
+}
+
+/// Returns the ticks of the wheels since [`init_encoders`]: fl, fr, rl, rr.
+pub fn ticks() -> [i32; 4] {
+    avr_device::interrupt::free(|cs| TICKS.borrow(cs).get())
+}
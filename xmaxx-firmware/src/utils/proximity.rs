@@ -0,0 +1,106 @@
+//! The measurement of the ultrasonic range sensors, like the HC-SR04.
+//!
+//! The front sensor is triggered on d22 and echoes on d14 (PJ1), the rear
+//! one on d23 and d15 (PJ0). The edges of the echoes are timed from the
+//! pin-change interrupt of the port, against the pulse clock.
+
+use core::cell::Cell;
+
+use arduino_hal::pac::{EXINT, PORTJ};
+use arduino_hal::port::{mode::Output, Pin};
+use avr_device::interrupt::Mutex;
+
+use xmaxx_core::collision::RANGE_PERIOD;
+
+use super::time::{pulse_clock, pulse_width};
+
+/// The times (counts) of the last rising edges of the echoes.
+static RISES: Mutex<Cell<[u16; 2]>> = Mutex::new(Cell::new([0; 2]));
+/// The widths (µs) of the echoes since the last [`take_echoes`].
+static ECHOES: Mutex<Cell<[Option<u32>; 2]>> = Mutex::new(Cell::new([None; 2]));
+/// The last levels of the echoes.
+static LEVELS: Mutex<Cell<u8>> = Mutex::new(Cell::new(0));
+
+/// Starts timing the echoes.
+///
+/// The pins must be inputs, and the pulse clock started.
+pub fn init_proximity(exint: &EXINT) {
+    avr_device::interrupt::free(|cs| LEVELS.borrow(cs).set(levels()));
+
+    // PCINT9 and PCINT10 on port J
+    exint
+        .pcmsk1
+        .modify(|r, w| unsafe { w.bits(r.bits() | 0b110) });
+    exint
+        .pcicr
+        .modify(|r, w| unsafe { w.bits(r.bits() | 0b10) });
+}
+
+/// Returns the levels of the echoes, in their first bits: front and rear.
+fn levels() -> u8 {
+    // only reads the input register, which the HAL does not hold
+    let pins = unsafe { (*PORTJ::ptr()).pinj.read().bits() };
+    (pins >> 1 & 1) | (pins & 1) << 1
+}
+
+#[avr_device::interrupt(atmega2560)]
+fn PCINT1() {
+    avr_device::interrupt::free(|cs| {
+        let now = pulse_clock();
+        let levels = levels();
+        let previous = LEVELS.borrow(cs).replace(levels);
+
+        let rises = RISES.borrow(cs);
+        let echoes = ECHOES.borrow(cs);
+        let (mut times, mut widths) = (rises.get(), echoes.get());
+        for sensor in 0..2 {
+            match (previous >> sensor & 1, levels >> sensor & 1) {
+                (0, 1) => times[sensor] = now,
+                (1, 0) => widths[sensor] = Some(pulse_width(times[sensor], now)),
+                _ => {}
+            }
+        }
+        rises.set(times);
+        echoes.set(widths);
+    })
+}
+
+/// Returns the widths of the echoes since the last call, if any (µs): front
+/// and rear.
+pub fn take_echoes() -> [Option<u32>; 2] {
+    avr_device::interrupt::free(|cs| ECHOES.borrow(cs).replace([None; 2]))
+}
+
+/// The triggers of the range sensors.
+pub struct Triggers {
+    /// The pins: front and rear.
+    pins: [Pin<Output>; 2],
+    /// The time of the last trigger (ms).
+    last: u32,
+}
+
+impl Triggers {
+    /// Returns the triggers on these pins: front and rear.
+    pub fn new(pins: [Pin<Output>; 2]) -> Self {
+        Self { pins, last: 0 }
+    }
+
+    /// Triggers both sensors every [`RANGE_PERIOD`], at `now` (ms).
+    ///
+    /// They face opposite ways, so they do not hear each other.
+    pub fn update(&mut self, now: u32) {
+        if now.wrapping_sub(self.last) < RANGE_PERIOD {
+            return;
+        }
+        self.last = now;
+
+        for pin in &mut self.pins {
+            pin.set_high();
+        }
+        // the sensors need a pulse of at least 10 µs
+        arduino_hal::delay_us(10);
+        for pin in &mut self.pins {
+            pin.set_low();
+        }
+    }
+}
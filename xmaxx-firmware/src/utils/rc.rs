@@ -0,0 +1,81 @@
+//! The measurement of the pulses of a hobby RC receiver.
+//!
+//! The channels of the receiver are on a13 to a15 (PK5 to PK7): throttle,
+//! steering and mode switch. Their edges are timed from the pin-change
+//! interrupt of the port, against the pulse clock.
+
+use core::cell::Cell;
+
+use arduino_hal::pac::{EXINT, PORTK};
+use avr_device::interrupt::Mutex;
+
+use xmaxx_core::rc::RC_CHANNELS;
+
+use super::time::{pulse_clock, pulse_width};
+
+/// The times (counts) of the last rising edges of the channels.
+static RISES: Mutex<Cell<[u16; RC_CHANNELS]>> = Mutex::new(Cell::new([0; RC_CHANNELS]));
+/// The widths (µs) of the pulses since the last [`take_pulses`].
+static PULSES: Mutex<Cell<[Option<u16>; RC_CHANNELS]>> = Mutex::new(Cell::new([None; RC_CHANNELS]));
+/// The last levels of the channels.
+static LEVELS: Mutex<Cell<u8>> = Mutex::new(Cell::new(0));
+
+/// Starts measuring the pulses.
+///
+/// The pins must be inputs, and the pulse clock started.
+pub fn init_rc(exint: &EXINT) {
+    avr_device::interrupt::free(|cs| LEVELS.borrow(cs).set(levels()));
+
+    // PCINT21 to PCINT23 on port K, which the encoders may share
+    exint
+        .pcmsk2
+        .modify(|r, w| unsafe { w.bits(r.bits() | 0b1110_0000) });
+    exint
+        .pcicr
+        .modify(|r, w| unsafe { w.bits(r.bits() | 0b100) });
+}
+
+/// Returns the levels of the channels, in their first bits.
+fn levels() -> u8 {
+    // only reads the input register, which the HAL does not hold
+    unsafe { (*PORTK::ptr()).pink.read().bits() >> 5 }
+}
+
+/// Times the edges of the channels, from the pin-change interrupt.
+pub fn capture() {
+    avr_device::interrupt::free(|cs| {
+        let now = pulse_clock();
+        let levels = levels();
+        let previous = LEVELS.borrow(cs).replace(levels);
+
+        let rises = RISES.borrow(cs);
+        let pulses = PULSES.borrow(cs);
+        let (mut times, mut widths) = (rises.get(), pulses.get());
+        for channel in 0..RC_CHANNELS {
+            match (previous >> channel & 1, levels >> channel & 1) {
+                (0, 1) => times[channel] = now,
+                (1, 0) => {
+                    // too long to be valid if it does not fit
+                    let width = pulse_width(times[channel], now);
+                    widths[channel] = Some(u16::try_from(width).unwrap_or(u16::MAX));
+                }
+                _ => {}
+            }
+        }
+        rises.set(times);
+        pulses.set(widths);
+    })
+}
+
+// the encoders call `capture` from their interrupt when they share it
+#[cfg(not(feature = "encoders"))]
+#[avr_device::interrupt(atmega2560)]
+fn PCINT2() {
+    capture()
+}
+
+/// Returns the widths of the pulses since the last call, if any (µs):
+/// throttle, steering and mode switch.
+pub fn take_pulses() -> [Option<u16>; RC_CHANNELS] {
+    avr_device::interrupt::free(|cs| PULSES.borrow(cs).replace([None; RC_CHANNELS]))
+}
@@ -15,8 +15,8 @@ use core::cell::Cell;
 // ║      1024 ║          125 ║              8 ms ║
 // ║      1024 ║          250 ║             16 ms ║
 // ╚═══════════╩══════════════╩═══════════════════╝
-const PRESCALER: u32 = 1024;
-const TIMER_COUNTS: u32 = 125;
+const PRESCALER: u32 = 64;
+const TIMER_COUNTS: u32 = 250;
 
 const MILLIS_INCREMENT: u32 = PRESCALER * TIMER_COUNTS / 16000;
 
@@ -34,7 +34,8 @@ pub fn init_millis(tc0: arduino_hal::pac::TC0) {
     // Configure the timer for the above interval (in CTC mode)
     // and enable its interrupt.
     tc0.tccr0a.write(|w| w.wgm0().ctc());
-    tc0.ocr0a.write(|w| w.bits(TIMER_COUNTS as u8));
+    // counts from 0 to OCR0A included
+    tc0.ocr0a.write(|w| w.bits((TIMER_COUNTS - 1) as u8));
     tc0.tccr0b.write(|w| match PRESCALER {
         8 => w.cs0().prescale_8(),
         64 => w.cs0().prescale_64(),
@@ -64,3 +65,26 @@ fn TIMER0_COMPA() {
 pub fn millis() -> u32 {
     avr_device::interrupt::free(|cs| MILLIS_COUNTER.borrow(cs).get())
 }
+
+/// The microseconds in a count of the pulse clock.
+const MICROS_PER_COUNT: u32 = 4;
+
+/// Starts the clock timing the pulses of the RC receiver and the range
+/// sensors, on timer 5.
+///
+/// In normal mode, it wraps every 262 ms, longer than any pulse.
+pub fn init_pulse_clock(tc5: arduino_hal::pac::TC5) {
+    tc5.tccr5b.write(|w| w.cs5().prescale_64());
+}
+
+/// Returns the count of the pulse clock.
+pub fn pulse_clock() -> u16 {
+    // only reads the counter, which the HAL does not hold
+    unsafe { (*arduino_hal::pac::TC5::ptr()).tcnt5.read().bits() }
+}
+
+/// Returns the width of a pulse from the counts of the pulse clock at its
+/// edges (µs).
+pub fn pulse_width(rise: u16, fall: u16) -> u32 {
+    u32::from(fall.wrapping_sub(rise)) * MICROS_PER_COUNT
+}
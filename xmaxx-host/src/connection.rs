@@ -0,0 +1,174 @@
+use std::fmt;
+use std::time::Duration;
+
+use serialport::{SerialPortType, UsbPortInfo};
+
+use crate::Error;
+
+/// Where to find the serial port of the firmware.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortSpec {
+    /// A path to the serial port, like `/dev/ttyACM0`.
+    Path(String),
+    /// A USB device, whatever the path the OS gave it.
+    Usb(UsbId),
+}
+
+impl fmt::Display for PortSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Path(path) => write!(f, "{path}"),
+            Self::Usb(id) => write!(f, "{id}"),
+        }
+    }
+}
+
+/// The identity of a USB serial device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsbId {
+    /// Vendor ID.
+    pub vid: u16,
+    /// Product ID.
+    pub pid: u16,
+    /// Serial number, to tell apart two identical boards.
+    pub serial_number: Option<String>,
+}
+
+impl UsbId {
+    /// Checks if the USB device has this identity.
+    ///
+    /// The serial number is only compared when this identity has one.
+    pub fn matches(&self, info: &UsbPortInfo) -> bool {
+        self.vid == info.vid
+            && self.pid == info.pid
+            && (self.serial_number.is_none() || self.serial_number == info.serial_number)
+    }
+
+    /// Returns the path of the first port having this identity.
+    pub fn find(&self) -> Result<String, Error> {
+        serialport::available_ports()?
+            .into_iter()
+            .find(|port| match &port.port_type {
+                SerialPortType::UsbPort(info) => self.matches(info),
+                _ => false,
+            })
+            .map(|port| port.port_name)
+            .ok_or_else(|| {
+                Error::Serial(serialport::Error::new(
+                    serialport::ErrorKind::NoDevice,
+                    format!("no serial port matches {self}"),
+                ))
+            })
+    }
+
+    /// Returns the identity of the USB device at `path`, if any.
+    pub fn of_path(path: &str) -> Option<Self> {
+        serialport::available_ports()
+            .ok()?
+            .into_iter()
+            .find(|port| port.port_name == path)
+            .and_then(|port| match port.port_type {
+                SerialPortType::UsbPort(info) => Some(info.into()),
+                _ => None,
+            })
+    }
+}
+
+impl From<UsbPortInfo> for UsbId {
+    fn from(info: UsbPortInfo) -> Self {
+        Self {
+            vid: info.vid,
+            pid: info.pid,
+            serial_number: info.serial_number,
+        }
+    }
+}
+
+impl fmt::Display for UsbId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "usb {:04x}:{:04x}", self.vid, self.pid)?;
+        if let Some(serial_number) = &self.serial_number {
+            write!(f, " ({serial_number})")?;
+        }
+        Ok(())
+    }
+}
+
+/// How to reconnect when the serial port dies.
+///
+/// The port dies when the Arduino resets or when the USB cable is
+/// unplugged. It is then reopened, first at the same path, then
+/// wherever the same USB device reappeared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// Whether to reconnect at all.
+    pub enabled: bool,
+    /// The number of attempts before giving up (`None` -> never give up).
+    pub max_attempts: Option<u32>,
+    /// The delay between two attempts.
+    pub retry_delay: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_attempts: Some(10),
+            retry_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+/// The state of the connection with the firmware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// The port is open and the firmware answered.
+    Connected,
+    /// The port died and is being reopened.
+    Reconnecting,
+    /// The port died and could not be reopened.
+    Disconnected,
+    /// The connection was closed by the caller.
+    Closed,
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self {
+            Self::Connected => "connected",
+            Self::Reconnecting => "reconnecting",
+            Self::Disconnected => "disconnected",
+            Self::Closed => "closed",
+        };
+        write!(f, "{state}")
+    }
+}
+
+/// Something that happened to the connection with the firmware.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// The port was opened for the first time.
+    Connected { port: String },
+    /// The port died.
+    Disconnected { error: String },
+    /// An attempt to reopen the port failed.
+    ReconnectFailed { attempt: u32, error: String },
+    /// The port was reopened, maybe at another path.
+    Reconnected { port: String },
+    /// The connection was closed by the caller.
+    Closed,
+}
+
+impl fmt::Display for ConnectionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connected { port } => write!(f, "connected to {port}"),
+            Self::Disconnected { error } => write!(f, "disconnected: {error}"),
+            Self::ReconnectFailed { attempt, error } => {
+                write!(f, "reconnection attempt {attempt} failed: {error}")
+            }
+            Self::Reconnected { port } => write!(f, "reconnected to {port}"),
+            Self::Closed => write!(f, "closed"),
+        }
+    }
+}
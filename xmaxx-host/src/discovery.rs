@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use serialport::SerialPortType;
+
+use crate::{Error, Firmware, PortSpec, ReconnectPolicy, Settings, UsbId};
+
+/// USB ids of the boards that can run the firmware: (vid, pid, name).
+pub const KNOWN_BOARDS: &[(u16, u16, &str)] = &[
+    (0x2341, 0x0010, "Arduino Mega 2560"),
+    (0x2341, 0x0042, "Arduino Mega 2560 R3"),
+    (0x2a03, 0x0010, "Arduino Mega 2560"),
+    (0x2a03, 0x0042, "Arduino Mega 2560 R3"),
+    (0x1a86, 0x7523, "CH340 (Mega clone)"),
+    (0x0403, 0x6001, "FTDI FT232 (Mega clone)"),
+];
+
+/// A serial port that might be connected to the firmware.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    /// The path of the port.
+    pub path: String,
+    /// The identity of the USB device.
+    pub usb: UsbId,
+    /// The manufacturer string of the USB device.
+    pub manufacturer: Option<String>,
+    /// The product string of the USB device.
+    pub product: Option<String>,
+    /// The name of the board if it is one of the [`KNOWN_BOARDS`].
+    pub board: Option<&'static str>,
+    /// Whether the firmware answered a ping (`None` -> not probed).
+    pub firmware: Option<bool>,
+}
+
+impl Candidate {
+    /// Checks if the port runs the firmware by pinging it.
+    ///
+    /// The port is opened, which resets the Arduino, so `timeout` must be
+    /// long enough for it to boot.
+    pub fn probe(&mut self, timeout: Duration) -> bool {
+        let settings = Settings {
+            handshake_timeout: Some(timeout),
+            reconnect: ReconnectPolicy {
+                enabled: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let answered = Firmware::open(PortSpec::Path(self.path.clone()), settings)
+            .and_then(|mut firmware| firmware.ping(timeout))
+            .is_ok();
+        self.firmware = Some(answered);
+
+        answered
+    }
+}
+
+/// Lists the serial ports that might be connected to the firmware.
+///
+/// Only the [`KNOWN_BOARDS`] are listed, unless `all` is set, in which case
+/// every USB serial port is listed. When `probe` is given, each port is
+/// pinged with this timeout (see [`Candidate::probe`]).
+pub fn list_firmwares(all: bool, probe: Option<Duration>) -> Result<Vec<Candidate>, Error> {
+    let mut candidates: Vec<Candidate> = serialport::available_ports()?
+        .into_iter()
+        .filter_map(|port| match port.port_type {
+            SerialPortType::UsbPort(info) => {
+                let board = KNOWN_BOARDS
+                    .iter()
+                    .find(|(vid, pid, _)| *vid == info.vid && *pid == info.pid)
+                    .map(|(_, _, name)| *name);
+                Some(Candidate {
+                    path: port.port_name,
+                    manufacturer: info.manufacturer.clone(),
+                    product: info.product.clone(),
+                    usb: info.into(),
+                    board,
+                    firmware: None,
+                })
+            }
+            _ => None,
+        })
+        .filter(|candidate| all || candidate.board.is_some())
+        .collect();
+
+    if let Some(timeout) = probe {
+        for candidate in &mut candidates {
+            candidate.probe(timeout);
+        }
+    }
+
+    Ok(candidates)
+}
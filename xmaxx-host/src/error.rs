@@ -0,0 +1,49 @@
+use std::fmt;
+use std::io;
+
+/// Errors that can happen while talking with the firmware.
+#[derive(Debug)]
+pub enum Error {
+    /// An io operation on the serial port failed.
+    Io(io::Error),
+    /// The serial port could not be opened or found.
+    Serial(serialport::Error),
+    /// The firmware did not answer during the handshake.
+    Handshake,
+    /// The firmware did not answer in time.
+    Timeout,
+    /// The firmware refused the value of a parameter.
+    InvalidParam,
+    /// The port died and reconnection is disabled.
+    Disconnected,
+    /// The connection was closed by the caller.
+    Closed,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "io error: {err}"),
+            Self::Serial(err) => write!(f, "serial port error: {}", err.description),
+            Self::Handshake => write!(f, "the firmware did not answer the handshake"),
+            Self::Timeout => write!(f, "the firmware did not answer in time"),
+            Self::InvalidParam => write!(f, "the firmware refused the value"),
+            Self::Disconnected => write!(f, "the port died"),
+            Self::Closed => write!(f, "serial port closed"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serialport::Error> for Error {
+    fn from(err: serialport::Error) -> Self {
+        Self::Serial(err)
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde_json::{json, Map, Value};
+
+use xmaxx_messages::*;
+
+use crate::mcap::McapWriter;
+use crate::{Message, Recording};
+
+/// A message flattened into the row of a table.
+struct Row {
+    /// The name of the table, one per message type.
+    table: &'static str,
+    columns: &'static [&'static str],
+    values: Vec<Value>,
+    /// Whether the values may be null, when nothing was measured.
+    nullable: bool,
+}
+
+impl Row {
+    fn new(table: &'static str, columns: &'static [&'static str], values: Vec<Value>) -> Self {
+        Self {
+            table,
+            columns,
+            values,
+            nullable: false,
+        }
+    }
+
+    /// Marks the values as possibly null.
+    fn nullable(mut self) -> Self {
+        self.nullable = true;
+        self
+    }
+}
+
+impl From<&Message> for Row {
+    fn from(message: &Message) -> Self {
+        match message {
+            Message::Received(Info::Sensors(sensors)) => Row::new(
+                "sensors",
+                &["fl_whl_rpm", "fr_whl_rpm", "rl_whl_rpm", "rr_whl_rpm"],
+                vec![
+                    sensors.fl_whl_rpm.0.into(),
+                    sensors.fr_whl_rpm.0.into(),
+                    sensors.rl_whl_rpm.0.into(),
+                    sensors.rr_whl_rpm.0.into(),
+                ],
+            ),
+            Message::Received(Info::Log(log)) => {
+                Row::new("log", &["log"], vec![format!("{log:?}").into()])
+            }
+            Message::Received(Info::Pong(id)) => Row::new("pong", &["id"], vec![(*id).into()]),
+            Message::Received(Info::Param(param, value)) => Row::new(
+                "param",
+                &["param", "value"],
+                vec![param.name().into(), (*value).into()],
+            ),
+            Message::Received(Info::Currents(currents)) => Row::new(
+                "currents",
+                &[
+                    "fl_milliamps",
+                    "fr_milliamps",
+                    "rl_milliamps",
+                    "rr_milliamps",
+                ],
+                vec![
+                    currents.fl.0.into(),
+                    currents.fr.0.into(),
+                    currents.rl.0.into(),
+                    currents.rr.0.into(),
+                ],
+            ),
+            Message::Received(Info::Encoders(encoders)) => Row::new(
+                "encoders",
+                &["fl_ticks", "fr_ticks", "rl_ticks", "rr_ticks"],
+                vec![
+                    encoders.fl.into(),
+                    encoders.fr.into(),
+                    encoders.rl.into(),
+                    encoders.rr.into(),
+                ],
+            ),
+            Message::Received(Info::Battery(battery)) => {
+                Row::new("battery", &["millivolts"], vec![battery.voltage.0.into()])
+            }
+            Message::Received(Info::Control(control)) => Row::new(
+                "control",
+                &["mode", "source"],
+                vec![
+                    format!("{:?}", control.mode).into(),
+                    format!("{:?}", control.source).into(),
+                ],
+            ),
+            Message::Received(Info::Imu(imu)) => Row::new(
+                "imu",
+                &[
+                    "accel_x_millig",
+                    "accel_y_millig",
+                    "accel_z_millig",
+                    "gyro_x",
+                    "gyro_y",
+                    "gyro_z",
+                    "yaw_rate",
+                ],
+                vec![
+                    imu.accel_x.0.into(),
+                    imu.accel_y.0.into(),
+                    imu.accel_z.0.into(),
+                    imu.gyro_x.0.into(),
+                    imu.gyro_y.0.into(),
+                    imu.gyro_z.0.into(),
+                    imu.yaw_rate.0.into(),
+                ],
+            ),
+            Message::Received(Info::Proximity(proximity)) => Row::new(
+                "proximity",
+                &["front_millimeters", "rear_millimeters"],
+                vec![
+                    proximity.front.map(|distance| distance.0).into(),
+                    proximity.rear.map(|distance| distance.0).into(),
+                ],
+            )
+            .nullable(),
+            Message::Received(Info::Traction(traction)) => Row::new(
+                "traction",
+                &[
+                    "fl_slip_permille",
+                    "fr_slip_permille",
+                    "rl_slip_permille",
+                    "rr_slip_permille",
+                    "active",
+                ],
+                vec![
+                    traction.fl.0.into(),
+                    traction.fr.0.into(),
+                    traction.rl.0.into(),
+                    traction.rr.0.into(),
+                    traction.active.into(),
+                ],
+            ),
+            Message::Received(Info::Odometry(odometry)) => Row::new(
+                "odometry",
+                &[
+                    "time_millis",
+                    "x_millimeters",
+                    "y_millimeters",
+                    "heading",
+                    "distance_millimeters",
+                ],
+                vec![
+                    odometry.time.into(),
+                    odometry.x.0.into(),
+                    odometry.y.0.into(),
+                    odometry.heading.0.into(),
+                    odometry.distance.0.into(),
+                ],
+            ),
+            Message::Received(Info::Axles(axles)) => Row::new(
+                "axles",
+                &["mode", "front", "rear"],
+                vec![
+                    format!("{:?}", axles.mode).into(),
+                    axles.front.into(),
+                    axles.rear.into(),
+                ],
+            ),
+            Message::Sent(Command::Drive(drive)) => Row::new(
+                "drive",
+                &[
+                    "steering",
+                    "fl_whl_rpm",
+                    "fr_whl_rpm",
+                    "rl_whl_rpm",
+                    "rr_whl_rpm",
+                ],
+                vec![
+                    drive.steering.0.into(),
+                    drive.fl_whl_rpm.0.into(),
+                    drive.fr_whl_rpm.0.into(),
+                    drive.rl_whl_rpm.0.into(),
+                    drive.rr_whl_rpm.0.into(),
+                ],
+            ),
+            Message::Sent(Command::Ping(id)) => Row::new("ping", &["id"], vec![(*id).into()]),
+            Message::Sent(Command::GetParam(param)) => {
+                Row::new("get_param", &["param"], vec![param.name().into()])
+            }
+            Message::Sent(Command::SetParam(param, value)) => Row::new(
+                "set_param",
+                &["param", "value"],
+                vec![param.name().into(), (*value).into()],
+            ),
+            Message::Sent(Command::ResetOdometry) => Row::new("reset_odometry", &[], vec![]),
+            Message::Sent(Command::SetDriveMode(mode)) => Row::new(
+                "set_drive_mode",
+                &["mode"],
+                vec![format!("{mode:?}").into()],
+            ),
+        }
+    }
+}
+
+/// Exports the recording to one CSV file per message type in `dir`.
+///
+/// The first column is the time since the start of the recording (s). The
+/// values not measured, like the distance without obstacle in range, are
+/// empty. The frames that cannot be decoded are skipped.
+///
+/// Returns the paths of the files written.
+pub fn export_csv(recording: &Recording, dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+
+    let mut files = HashMap::<&str, BufWriter<File>>::new();
+    let mut paths = Vec::new();
+
+    for record in &recording.records {
+        let Some(message) = record.decode() else {
+            continue;
+        };
+        let row = Row::from(&message);
+
+        let file = match files.get_mut(row.table) {
+            Some(file) => file,
+            None => {
+                let path = dir.join(format!("{}.csv", row.table));
+                let mut file = BufWriter::new(File::create(&path)?);
+                writeln!(file, "time,{}", row.columns.join(","))?;
+                paths.push(path);
+                files.entry(row.table).or_insert(file)
+            }
+        };
+
+        let values: Vec<String> = row
+            .values
+            .iter()
+            .map(|value| match value {
+                Value::String(s) => s.clone(),
+                Value::Null => String::new(),
+                value => value.to_string(),
+            })
+            .collect();
+        writeln!(
+            file,
+            "{:.6},{}",
+            record.time().as_secs_f64(),
+            values.join(",")
+        )?;
+    }
+
+    for file in files.values_mut() {
+        file.flush()?;
+    }
+
+    Ok(paths)
+}
+
+/// Exports the recording to an MCAP file at `path`.
+///
+/// There is one topic per message type, encoded in JSON with a JSON schema,
+/// stamped with the time at which the frame was recorded. The frames that
+/// cannot be decoded are skipped.
+pub fn export_mcap(recording: &Recording, path: impl AsRef<Path>) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut mcap = McapWriter::new(file, concat!("xmaxx-host ", env!("CARGO_PKG_VERSION")))?;
+
+    // the channel id and the number of messages of each table
+    let mut channels = HashMap::<&str, (u16, u32)>::new();
+    let start = recording
+        .start
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+
+    for record in &recording.records {
+        let Some(message) = record.decode() else {
+            continue;
+        };
+        let row = Row::from(&message);
+
+        let (channel_id, sequence) = match channels.get_mut(row.table) {
+            Some(channel) => channel,
+            None => {
+                // channel ids start at 1, 0 is reserved
+                let id = channels.len() as u16 + 1;
+                let schema = serde_json::to_vec(&json_schema(&row))?;
+                mcap.schema(id, row.table, "jsonschema", &schema)?;
+                mcap.channel(id, id, &format!("/{}", row.table), "json")?;
+                channels.entry(row.table).or_insert((id, 0))
+            }
+        };
+
+        let data: Map<String, Value> = row
+            .columns
+            .iter()
+            .map(|column| column.to_string())
+            .zip(row.values)
+            .collect();
+        let log_time = (start + record.time()).as_nanos() as u64;
+        mcap.message(
+            *channel_id,
+            *sequence,
+            log_time,
+            &serde_json::to_vec(&data)?,
+        )?;
+        *sequence += 1;
+    }
+
+    mcap.finish()?;
+
+    Ok(())
+}
+
+/// Returns the JSON schema of the table of the row.
+fn json_schema(row: &Row) -> Value {
+    let properties: Map<String, Value> = row
+        .columns
+        .iter()
+        .zip(&row.values)
+        .map(|(column, value)| {
+            let kind = match value {
+                Value::String(_) => json!("string"),
+                Value::Bool(_) => json!("boolean"),
+                _ if row.nullable => json!(["integer", "null"]),
+                _ => json!("integer"),
+            };
+            (column.to_string(), json!({ "type": kind }))
+        })
+        .collect();
+
+    json!({
+        "title": row.table,
+        "type": "object",
+        "properties": properties,
+    })
+}
@@ -0,0 +1,333 @@
+use std::collections::VecDeque;
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::Transport;
+
+/// The frames are passed as they are after this many bytes without a null
+/// byte, so a stream without delimiters is not held forever.
+const MAX_FRAME_LEN: usize = 256;
+
+/// The probabilities of each fault, from 0 to 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FaultRates {
+    /// Probability to lose it.
+    pub drop: f64,
+    /// Probability to send it twice.
+    pub duplicate: f64,
+    /// Probability to swap it with the next one.
+    pub reorder: f64,
+    /// Probability to flip some of its bits.
+    pub corrupt: f64,
+    /// Probability to hold it, and what follows it, for [`Faults::delay`].
+    pub delay: f64,
+}
+
+impl FaultRates {
+    fn is_none(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// The faults injected in one direction of a [`FaultyTransport`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Faults {
+    /// The faults on each byte.
+    pub bytes: FaultRates,
+    /// The faults on each frame, delimited by the null bytes.
+    pub frames: FaultRates,
+    /// How long a delayed byte or frame is held.
+    pub delay: Duration,
+}
+
+/// The number of faults injected in one direction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FaultStats {
+    pub dropped: u64,
+    pub duplicated: u64,
+    pub reordered: u64,
+    pub corrupted: u64,
+    pub delayed: u64,
+}
+
+/// A transport injecting faults in the bytes going through another one.
+///
+/// It stands for a noisy link, to check how the host and the firmware cope
+/// with it. The faults are drawn from a seeded generator, so a test
+/// injects the same faults every time it runs.
+///
+/// The faults on the frames need the whole frame, so the bytes are held
+/// until the null byte. A byte or a frame held for reordering is only let
+/// through by the next one.
+pub struct FaultyTransport<T> {
+    inner: T,
+    read: Injector,
+    write: Injector,
+}
+
+impl<T: Transport> FaultyTransport<T> {
+    /// Wraps `inner`, with the faults on what is read from it and what is
+    /// written to it.
+    pub fn new(inner: T, read: Faults, write: Faults, seed: u64) -> Self {
+        // a different stream of faults for each direction
+        Self {
+            inner,
+            read: Injector::new(read, seed),
+            write: Injector::new(write, seed.wrapping_add(1)),
+        }
+    }
+
+    /// Returns the faults injected in what was read.
+    pub fn read_stats(&self) -> FaultStats {
+        self.read.stats
+    }
+
+    /// Returns the faults injected in what was written.
+    pub fn write_stats(&self) -> FaultStats {
+        self.write.stats
+    }
+
+    /// Returns the wrapped transport.
+    pub fn inner(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Writes to the wrapped transport what is due.
+    fn write_due(&mut self) -> io::Result<()> {
+        let due = self.write.pop_due(Instant::now());
+        if !due.is_empty() {
+            self.inner.write_all(&due)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Transport> io::Read for FaultyTransport<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let n = self.read.read_due(Instant::now(), buf);
+            if n > 0 {
+                return Ok(n);
+            }
+
+            // wait for the delayed bytes rather than reading more
+            if let Some(due) = self.read.next_due() {
+                thread::sleep(due.saturating_duration_since(Instant::now()));
+                continue;
+            }
+
+            let mut received = [0u8; 64];
+            let n = self.inner.read(&mut received)?;
+            if n == 0 {
+                return Ok(0);
+            }
+            for byte in &received[..n] {
+                self.read.push(*byte, Instant::now());
+            }
+        }
+    }
+}
+
+impl<T: Transport> io::Write for FaultyTransport<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for byte in buf {
+            self.write.push(*byte, Instant::now());
+        }
+        self.write_due()?;
+
+        Ok(buf.len())
+    }
+
+    /// Writes what is pending, waiting for the delayed bytes.
+    fn flush(&mut self) -> io::Result<()> {
+        while let Some(due) = self.write.next_due() {
+            thread::sleep(due.saturating_duration_since(Instant::now()));
+            self.write_due()?;
+        }
+
+        self.inner.flush()
+    }
+}
+
+impl<T: Transport> Transport for FaultyTransport<T> {
+    fn clear(&mut self) -> io::Result<()> {
+        self.read.clear();
+        self.write.clear();
+        self.inner.clear()
+    }
+}
+
+/// Injects the faults in one direction.
+struct Injector {
+    faults: Faults,
+    rng: ChaCha8Rng,
+    stats: FaultStats,
+    /// The byte held to be swapped with the next one.
+    held_byte: Option<u8>,
+    /// The bytes of the frame being received.
+    frame: Vec<u8>,
+    /// The frame held to be swapped with the next one.
+    held_frame: Option<Vec<u8>>,
+    /// The bytes let through with the time they are due.
+    queue: VecDeque<(Instant, u8)>,
+}
+
+impl Injector {
+    fn new(faults: Faults, seed: u64) -> Self {
+        Self {
+            faults,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            stats: FaultStats::default(),
+            held_byte: None,
+            frame: Vec::new(),
+            held_frame: None,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Returns whether the event of probability `p` happens.
+    fn happens(&mut self, p: f64) -> bool {
+        p > 0.0 && self.rng.gen_bool(p.min(1.0))
+    }
+
+    /// Injects the faults on a byte going through.
+    fn push(&mut self, byte: u8, now: Instant) {
+        let rates = self.faults.bytes;
+
+        if self.happens(rates.drop) {
+            self.stats.dropped += 1;
+            return;
+        }
+        let byte = if self.happens(rates.corrupt) {
+            self.stats.corrupted += 1;
+            byte ^ self.rng.gen_range(1..=u8::MAX)
+        } else {
+            byte
+        };
+        if self.held_byte.is_none() && self.happens(rates.reorder) {
+            self.stats.reordered += 1;
+            self.held_byte = Some(byte);
+            return;
+        }
+
+        let delay = self.happens(rates.delay);
+        let duplicate = self.happens(rates.duplicate);
+        let mut bytes = vec![byte];
+        if duplicate {
+            self.stats.duplicated += 1;
+            bytes.push(byte);
+        }
+        bytes.extend(self.held_byte.take());
+
+        let due = if delay {
+            self.stats.delayed += 1;
+            now + self.faults.delay
+        } else {
+            now
+        };
+        for byte in bytes {
+            self.push_frame_byte(byte, due);
+        }
+    }
+
+    /// Accumulates the frame and injects the faults on it when complete.
+    fn push_frame_byte(&mut self, byte: u8, due: Instant) {
+        if self.faults.frames.is_none() {
+            self.enqueue(&[byte], due);
+            return;
+        }
+
+        self.frame.push(byte);
+        if byte != 0 && self.frame.len() < MAX_FRAME_LEN {
+            return;
+        }
+        let mut frame = std::mem::take(&mut self.frame);
+        let rates = self.faults.frames;
+
+        if self.happens(rates.drop) {
+            self.stats.dropped += 1;
+            return;
+        }
+        if self.happens(rates.corrupt) {
+            self.stats.corrupted += 1;
+            let i = self.rng.gen_range(0..frame.len());
+            frame[i] ^= self.rng.gen_range(1..=u8::MAX);
+        }
+        if self.held_frame.is_none() && self.happens(rates.reorder) {
+            self.stats.reordered += 1;
+            self.held_frame = Some(frame);
+            return;
+        }
+
+        let due = if self.happens(rates.delay) {
+            self.stats.delayed += 1;
+            due + self.faults.delay
+        } else {
+            due
+        };
+        if self.happens(rates.duplicate) {
+            self.stats.duplicated += 1;
+            self.enqueue(&frame, due);
+        }
+        self.enqueue(&frame, due);
+        if let Some(held) = self.held_frame.take() {
+            self.enqueue(&held, due);
+        }
+    }
+
+    /// Lets the bytes through, after the ones before them like on a link.
+    fn enqueue(&mut self, bytes: &[u8], due: Instant) {
+        let due = self.queue.back().map_or(due, |(last, _)| due.max(*last));
+        self.queue.extend(bytes.iter().map(|byte| (due, *byte)));
+    }
+
+    /// Returns the time at which the next byte is due, if any.
+    fn next_due(&self) -> Option<Instant> {
+        self.queue.front().map(|(due, _)| *due)
+    }
+
+    /// Moves the bytes due at `now` to `buf` and returns how many.
+    fn read_due(&mut self, now: Instant, buf: &mut [u8]) -> usize {
+        let mut n = 0;
+        while n < buf.len() {
+            match self.queue.front() {
+                Some((due, byte)) if *due <= now => {
+                    buf[n] = *byte;
+                    n += 1;
+                    self.queue.pop_front();
+                }
+                _ => break,
+            }
+        }
+        n
+    }
+
+    /// Removes and returns the bytes due at `now`.
+    fn pop_due(&mut self, now: Instant) -> Vec<u8> {
+        let mut due = Vec::new();
+        while let Some((time, byte)) = self.queue.front() {
+            if *time > now {
+                break;
+            }
+            due.push(*byte);
+            self.queue.pop_front();
+        }
+        due
+    }
+
+    /// Discards the bytes in flight.
+    fn clear(&mut self) {
+        self.held_byte = None;
+        self.frame.clear();
+        self.held_frame = None;
+        self.queue.clear();
+    }
+}
@@ -0,0 +1,524 @@
+use std::collections::VecDeque;
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use xmaxx_messages::*;
+
+use crate::{
+    ConnectionEvent, ConnectionState, Error, FrameDecoder, PortSpec, ReconnectPolicy, Recorder,
+    RecvStats, Transport, UsbId,
+};
+
+/// Settings of the connection with the firmware.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    /// The baudrate of the communication.
+    pub baudrate: u32,
+    /// The timeout on io operations.
+    pub timeout: Duration,
+    /// The delay between each byte sent.
+    pub send_delay: Duration,
+    /// How long to wait for the firmware to talk after opening the port
+    /// (`None` -> do not wait).
+    ///
+    /// The Arduino resets when the port is opened, so it must be long enough
+    /// for the bootloader to hand over to the firmware.
+    pub handshake_timeout: Option<Duration>,
+    /// The number of consecutive timeouts after which the port is considered
+    /// dead (`None` -> never).
+    ///
+    /// The firmware talks continuously, so a silent port is a stale one. It
+    /// is reconnected, or kept if reconnection is disabled.
+    pub max_timeouts: Option<u32>,
+    /// What to do when the port dies.
+    pub reconnect: ReconnectPolicy,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            baudrate: 57600,
+            timeout: Duration::from_millis(500),
+            send_delay: Duration::from_millis(3),
+            handshake_timeout: Some(Duration::from_millis(3000)),
+            max_timeouts: Some(3),
+            reconnect: ReconnectPolicy::default(),
+        }
+    }
+}
+
+/// Opens a transport to the firmware.
+type Reopen = Box<dyn FnMut() -> io::Result<Box<dyn Transport>> + Send>;
+
+/// A connection to the Xmaxx firmware.
+///
+/// When an io operation fails because the port died, the port is reopened
+/// according to the [`ReconnectPolicy`] and the operation is tried again.
+/// What happened can be followed with [`Firmware::state`] and
+/// [`Firmware::poll_event`].
+///
+/// Corrupted frames are skipped and counted in [`Firmware::stats`].
+///
+/// **Note:** if there are problems with deserialization in the firmware,
+/// it might be because the computer is sending the next bytes too soon.
+/// Try increasing the send delay.
+pub struct Firmware {
+    /// Where to find the serial port (`None` -> a transport was given).
+    spec: Option<PortSpec>,
+    /// Opens the transport again when it dies (`None` -> a serial port or a
+    /// transport that cannot be reopened).
+    reopen: Option<Reopen>,
+    settings: Settings,
+    port: Option<Box<dyn Transport>>,
+    /// The path of the last opened port.
+    path: Option<String>,
+    /// The identity of the last opened port, to find it again if its path
+    /// changes.
+    usb: Option<UsbId>,
+    state: ConnectionState,
+    events: VecDeque<ConnectionEvent>,
+    /// The id of the last ping sent.
+    ping_id: u32,
+    decoder: FrameDecoder<Info>,
+    /// The number of consecutive timeouts.
+    timeouts: u32,
+    recorder: Option<Recorder>,
+}
+
+impl Firmware {
+    /// Opens a connection to the firmware.
+    pub fn open(spec: PortSpec, settings: Settings) -> Result<Self, Error> {
+        let usb = match &spec {
+            PortSpec::Path(_) => None,
+            PortSpec::Usb(id) => Some(id.clone()),
+        };
+        let mut firmware = Self::new(Some(spec), settings);
+        firmware.usb = usb;
+
+        firmware.connect()?;
+        let port = firmware.port_name();
+        firmware
+            .events
+            .push_back(ConnectionEvent::Connected { port });
+
+        Ok(firmware)
+    }
+
+    /// Opens a connection to the firmware over the transports returned by
+    /// `open`.
+    ///
+    /// `open` is called again when the transport dies, as the serial port
+    /// is reopened, so a fake firmware can be unplugged and plugged back.
+    pub fn with_reopen(
+        open: impl FnMut() -> io::Result<Box<dyn Transport>> + Send + 'static,
+        settings: Settings,
+    ) -> Result<Self, Error> {
+        let mut firmware = Self::new(None, settings);
+        firmware.reopen = Some(Box::new(open));
+
+        firmware.connect()?;
+        firmware.events.push_back(ConnectionEvent::Connected {
+            port: firmware.port_name(),
+        });
+
+        Ok(firmware)
+    }
+
+    /// Opens a connection to the firmware over the given transport.
+    ///
+    /// The transport cannot be reopened, so the connection is lost for good
+    /// if it dies.
+    pub fn with_transport(transport: Box<dyn Transport>, mut settings: Settings) -> Self {
+        settings.reconnect.enabled = false;
+        let mut firmware = Self::new(None, settings);
+
+        firmware.port = Some(transport);
+        firmware.state = ConnectionState::Connected;
+        firmware.events.push_back(ConnectionEvent::Connected {
+            port: "transport".into(),
+        });
+
+        firmware
+    }
+
+    fn new(spec: Option<PortSpec>, settings: Settings) -> Self {
+        Self {
+            spec,
+            reopen: None,
+            settings,
+            port: None,
+            path: None,
+            usb: None,
+            state: ConnectionState::Disconnected,
+            events: VecDeque::new(),
+            ping_id: 0,
+            decoder: FrameDecoder::new(Info::MAX_SERIAL_SIZE),
+            timeouts: 0,
+            recorder: None,
+        }
+    }
+
+    /// Returns the state of the connection.
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    /// Returns the path of the last opened port.
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// Returns the statistics about the received frames.
+    pub fn stats(&self) -> RecvStats {
+        self.decoder.stats()
+    }
+
+    /// Records the raw frames sent and received, or stops recording when
+    /// `recorder` is `None`.
+    pub fn record(&mut self, recorder: Option<Recorder>) -> Result<(), Error> {
+        if let Some(mut previous) = std::mem::replace(&mut self.recorder, recorder) {
+            previous.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the oldest connection event not yet polled.
+    pub fn poll_event(&mut self) -> Option<ConnectionEvent> {
+        self.events.pop_front()
+    }
+
+    /// Sends a command to the firmware.
+    ///
+    /// This function sleeps `send_delay` for each byte sent. Treat like a
+    /// blocking function.
+    pub fn send(&mut self, command: &Command) -> Result<(), Error> {
+        let mut buf = [0u8; Command::MAX_SERIAL_SIZE];
+        let msg = serialize(command, &mut buf).expect("serializing should just work");
+
+        self.with_port(|port, settings| {
+            for i in 0..msg.len() {
+                port.write_all(&msg[i..i + 1])?;
+                thread::sleep(settings.send_delay);
+            }
+
+            port.flush()
+        })?;
+
+        if let Some(recorder) = &mut self.recorder {
+            recorder.sent(msg)?;
+        }
+
+        Ok(())
+    }
+
+    /// Receives information from the firmware.
+    ///
+    /// The frames that cannot be deserialized are skipped. On timeout, the
+    /// partial frame is kept for the next call.
+    pub fn recv(&mut self) -> Result<Info, Error> {
+        let mut b = [0u8; 1];
+
+        loop {
+            self.with_port(|port, _| port.read_exact(&mut b))?;
+
+            if let Some(recorder) = &mut self.recorder {
+                recorder.received(b[0])?;
+            }
+
+            if let Some(info) = self.decoder.push(b[0]) {
+                return Ok(info);
+            }
+        }
+    }
+
+    /// Pings the firmware and returns the round trip time.
+    ///
+    /// The information received while waiting for the answer is discarded.
+    pub fn ping(&mut self, timeout: Duration) -> Result<Duration, Error> {
+        self.ping_id = self.ping_id.wrapping_add(1);
+        let id = self.ping_id;
+
+        let start = Instant::now();
+        self.request(&Command::Ping(id), timeout, |info| match info {
+            Info::Pong(pong) if pong == id => Some(Ok(())),
+            _ => None,
+        })?;
+
+        Ok(start.elapsed())
+    }
+
+    /// Returns the value of a runtime parameter of the firmware.
+    pub fn get_param(&mut self, param: Param, timeout: Duration) -> Result<i32, Error> {
+        self.request(&Command::GetParam(param), timeout, |info| match info {
+            Info::Param(p, value) if p == param => Some(Ok(value)),
+            _ => None,
+        })
+    }
+
+    /// Sets a runtime parameter of the firmware and returns its new value.
+    ///
+    /// The firmware keeps the value until it resets. It refuses the values
+    /// out of range with [`Error::InvalidParam`].
+    pub fn set_param(&mut self, param: Param, value: i32, timeout: Duration) -> Result<i32, Error> {
+        self.request(
+            &Command::SetParam(param, value),
+            timeout,
+            |info| match info {
+                Info::Param(p, value) if p == param => Some(Ok(value)),
+                Info::Log(Log::InvalidParam) => Some(Err(Error::InvalidParam)),
+                _ => None,
+            },
+        )
+    }
+
+    /// Moves the odometry of the firmware back to the origin and returns it.
+    pub fn reset_odometry(&mut self, timeout: Duration) -> Result<Odometry, Error> {
+        self.request(&Command::ResetOdometry, timeout, |info| match info {
+            // the odometry sent before the reset has traveled, unless the
+            // Xmaxx stood still, when it is the same
+            Info::Odometry(odometry) if odometry.distance == MilliMeters(0) => Some(Ok(odometry)),
+            _ => None,
+        })
+    }
+
+    /// Selects the axles driven by their motors and returns their state.
+    ///
+    /// The drivers follow the mode from the next iteration of the firmware,
+    /// unless a protection cut them.
+    pub fn set_drive_mode(&mut self, mode: DriveMode, timeout: Duration) -> Result<Axles, Error> {
+        self.request(&Command::SetDriveMode(mode), timeout, |info| match info {
+            Info::Axles(axles) if axles.mode == mode => Some(Ok(axles)),
+            _ => None,
+        })
+    }
+
+    /// Sends the command and receives until `answer` recognizes the answer.
+    ///
+    /// The other messages received meanwhile are dropped.
+    fn request<T>(
+        &mut self,
+        command: &Command,
+        timeout: Duration,
+        mut answer: impl FnMut(Info) -> Option<Result<T, Error>>,
+    ) -> Result<T, Error> {
+        let start = Instant::now();
+        self.send(command)?;
+
+        while start.elapsed() < timeout {
+            match self.recv() {
+                Ok(info) => {
+                    if let Some(result) = answer(info) {
+                        return result;
+                    }
+                }
+                Err(Error::Io(err)) if !is_disconnection(&err) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(Error::Timeout)
+    }
+
+    /// Closes the connection to the firmware.
+    ///
+    /// Every operation fails after.
+    pub fn close(&mut self) {
+        // dropping the serial port closes it, and the recorder flushes it
+        self.port = None;
+        self.recorder = None;
+        self.state = ConnectionState::Closed;
+        self.events.push_back(ConnectionEvent::Closed);
+    }
+
+    /// Runs the io operation on the port, reconnecting if the port died.
+    ///
+    /// The port is also considered dead after `max_timeouts` consecutive
+    /// timeouts if it can be reconnected, else they fail with
+    /// [`Error::Timeout`] and the port is kept. The operation is tried again once after a successful
+    /// reconnection.
+    fn with_port<T>(
+        &mut self,
+        mut operation: impl FnMut(&mut dyn Transport, &Settings) -> io::Result<T>,
+    ) -> Result<T, Error> {
+        match self.state {
+            ConnectionState::Closed => return Err(Error::Closed),
+            ConnectionState::Connected => {}
+            ConnectionState::Reconnecting | ConnectionState::Disconnected => {
+                if !self.settings.reconnect.enabled {
+                    return Err(Error::Disconnected);
+                }
+                self.reconnect()?
+            }
+        }
+
+        let port = self.port.as_deref_mut().expect("connected means a port");
+        let result = operation(port, &self.settings);
+        match &result {
+            Ok(_) => self.timeouts = 0,
+            Err(err) if err.kind() == io::ErrorKind::TimedOut => {
+                self.timeouts += 1;
+                self.decoder.count_timeout();
+            }
+            Err(_) => {}
+        }
+
+        // a stale port is only dropped if it can be opened again
+        let reopen = self.settings.reconnect.enabled;
+        match result {
+            Err(err) if is_disconnection(&err) || (reopen && self.is_stale()) => {
+                self.port = None;
+                self.state = ConnectionState::Disconnected;
+                self.events.push_back(ConnectionEvent::Disconnected {
+                    error: err.to_string(),
+                });
+
+                if !self.settings.reconnect.enabled {
+                    return Err(err.into());
+                }
+                self.reconnect()?;
+
+                let port = self.port.as_deref_mut().expect("connected means a port");
+                Ok(operation(port, &self.settings)?)
+            }
+            Err(err) if err.kind() == io::ErrorKind::TimedOut && self.is_stale() => {
+                Err(Error::Timeout)
+            }
+            result => Ok(result?),
+        }
+    }
+
+    /// Reopens the port according to the reconnect policy.
+    fn reconnect(&mut self) -> Result<(), Error> {
+        self.state = ConnectionState::Reconnecting;
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            match self.connect() {
+                Ok(()) => {
+                    let port = self.port_name();
+                    self.events.push_back(ConnectionEvent::Reconnected { port });
+                    return Ok(());
+                }
+                Err(err) => {
+                    self.events.push_back(ConnectionEvent::ReconnectFailed {
+                        attempt,
+                        error: err.to_string(),
+                    });
+
+                    let policy = &self.settings.reconnect;
+                    let gave_up = policy.max_attempts.is_some_and(|max| attempt >= max);
+                    if gave_up {
+                        self.state = ConnectionState::Disconnected;
+                        return Err(err);
+                    }
+                    thread::sleep(policy.retry_delay);
+                }
+            }
+        }
+    }
+
+    /// Returns the name of the port in the events.
+    fn port_name(&self) -> String {
+        // a reopened transport has no path
+        self.path.clone().unwrap_or_else(|| "transport".into())
+    }
+
+    /// Checks if the port timed out too many times in a row.
+    fn is_stale(&self) -> bool {
+        self.settings
+            .max_timeouts
+            .is_some_and(|max| self.timeouts >= max)
+    }
+
+    /// Opens the port and does the handshake.
+    fn connect(&mut self) -> Result<(), Error> {
+        let mut port = self.open_port()?;
+        if let Some(timeout) = self.settings.handshake_timeout {
+            handshake(port.as_mut(), timeout)?;
+        }
+
+        self.port = Some(port);
+        self.state = ConnectionState::Connected;
+        self.timeouts = 0;
+        // the partial frame is from the dead port
+        self.decoder.reset();
+
+        Ok(())
+    }
+
+    /// Opens the port at its last known path, or wherever it is now.
+    fn open_port(&mut self) -> Result<Box<dyn Transport>, Error> {
+        if let Some(open) = &mut self.reopen {
+            return Ok(open()?);
+        }
+
+        let mut paths = Vec::new();
+        let mut error = None;
+        if let Some(PortSpec::Path(path)) = &self.spec {
+            paths.push(path.clone());
+        }
+        if let Some(usb) = &self.usb {
+            match usb.find() {
+                Ok(path) => paths.push(path),
+                Err(err) => error = Some(err),
+            }
+        }
+
+        for path in paths {
+            // must set timeout otherwise it is 0 and every operation hits it
+            let result = serialport::new(&path, self.settings.baudrate)
+                .timeout(self.settings.timeout)
+                .open();
+            match result {
+                Ok(port) => {
+                    if self.usb.is_none() {
+                        self.usb = UsbId::of_path(&path);
+                    }
+                    self.path = Some(path);
+                    return Ok(Box::new(port));
+                }
+                Err(err) => error = Some(err.into()),
+            }
+        }
+
+        // a transport was given and it cannot be reopened
+        Err(error.unwrap_or(Error::Disconnected))
+    }
+}
+
+/// Waits for the firmware to send a valid message.
+///
+/// The messages already in the input buffer are discarded because they might
+/// be from before the port died.
+fn handshake(port: &mut dyn Transport, timeout: Duration) -> Result<(), Error> {
+    port.clear()?;
+
+    let mut decoder = FrameDecoder::<Info>::new(Info::MAX_SERIAL_SIZE);
+    let mut b = [0u8; 1];
+
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        match port.read_exact(&mut b) {
+            Ok(()) => {
+                if decoder.push(b[0]).is_some() {
+                    return Ok(());
+                }
+            }
+            Err(err) if is_disconnection(&err) => return Err(err.into()),
+            Err(_) => {}
+        }
+    }
+
+    Err(Error::Handshake)
+}
+
+/// Checks if the io error means that the port died.
+fn is_disconnection(err: &io::Error) -> bool {
+    !matches!(
+        err.kind(),
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted
+    )
+}
@@ -0,0 +1,107 @@
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+
+use xmaxx_messages::deserialize;
+
+/// Statistics about the received frames.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RecvStats {
+    /// The number of valid frames received.
+    pub frames: u64,
+    /// The number of frames that could not be deserialized.
+    pub discarded_frames: u64,
+    /// The number of bytes discarded, in or before frames.
+    pub discarded_bytes: u64,
+    /// The number of io operations that timed out.
+    pub timeouts: u64,
+}
+
+/// Splits the received bytes into messages.
+///
+/// The frames are delimited by the null byte of the cobs encoding, so the
+/// decoder resynchronizes by itself. Anything before a valid message, like
+/// a partial frame or the text printed by the panic handler, is skipped.
+/// Frames that do not end with a valid message are discarded. Both are
+/// counted in the [`RecvStats`].
+pub struct FrameDecoder<M> {
+    /// The last bytes received, at most `max_len`.
+    buf: VecDeque<u8>,
+    max_len: usize,
+    stats: RecvStats,
+    message: PhantomData<M>,
+}
+
+impl<M: DeserializeOwned> FrameDecoder<M> {
+    /// Returns a new decoder for frames of at most `max_len` bytes.
+    pub fn new(max_len: usize) -> Self {
+        Self {
+            buf: VecDeque::with_capacity(max_len),
+            max_len,
+            stats: RecvStats::default(),
+            message: PhantomData,
+        }
+    }
+
+    /// Pushes the received byte and returns the message it completes, if any.
+    pub fn push(&mut self, byte: u8) -> Option<M> {
+        if self.buf.len() == self.max_len {
+            // too far from the delimiter to be part of a valid frame
+            self.buf.pop_front();
+            self.stats.discarded_bytes += 1;
+        }
+        self.buf.push_back(byte);
+
+        // null char is the separator in cobs encoding
+        if byte != 0 {
+            return None;
+        }
+
+        let message = self.decode();
+        match message {
+            Some(_) => self.stats.frames += 1,
+            None => {
+                self.stats.discarded_frames += 1;
+                self.stats.discarded_bytes += self.buf.len() as u64;
+            }
+        }
+        self.buf.clear();
+
+        message
+    }
+
+    /// Decodes the longest valid message at the end of the buffer.
+    fn decode(&mut self) -> Option<M> {
+        let buf = self.buf.make_contiguous();
+
+        // deserializing decodes the cobs frame in place, hence the copies
+        let mut frame = Vec::with_capacity(buf.len());
+        for start in 0..buf.len() {
+            frame.clear();
+            frame.extend_from_slice(&buf[start..]);
+
+            if let Ok(message) = deserialize(frame.as_mut_slice()) {
+                self.stats.discarded_bytes += start as u64;
+                return Some(message);
+            }
+        }
+
+        None
+    }
+
+    /// Discards the partial frame, if any.
+    pub fn reset(&mut self) {
+        self.buf.clear();
+    }
+
+    /// Returns the statistics since the creation of the decoder.
+    pub fn stats(&self) -> RecvStats {
+        self.stats
+    }
+
+    /// Counts an io operation that timed out.
+    pub(crate) fn count_timeout(&mut self) {
+        self.stats.timeouts += 1;
+    }
+}
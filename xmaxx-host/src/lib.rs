@@ -0,0 +1,22 @@
+#![doc = include_str!("../README.md")]
+
+mod connection;
+mod discovery;
+mod error;
+mod export;
+mod fault;
+mod firmware;
+mod frame;
+mod mcap;
+mod record;
+mod transport;
+
+pub use connection::*;
+pub use discovery::*;
+pub use error::Error;
+pub use export::*;
+pub use fault::*;
+pub use firmware::*;
+pub use frame::*;
+pub use record::*;
+pub use transport::*;
@@ -0,0 +1,115 @@
+//! A minimal writer of [MCAP](https://mcap.dev/spec) files.
+//!
+//! It only writes the data section, without the optional summary and
+//! indexes, which is enough for the readers to open the files.
+
+use std::io::{self, Write};
+
+const MAGIC: &[u8; 8] = b"\x89MCAP0\r\n";
+
+const OP_HEADER: u8 = 0x01;
+const OP_FOOTER: u8 = 0x02;
+const OP_SCHEMA: u8 = 0x03;
+const OP_CHANNEL: u8 = 0x04;
+const OP_MESSAGE: u8 = 0x05;
+const OP_DATA_END: u8 = 0x0f;
+
+/// Writes the records of an MCAP file.
+pub(crate) struct McapWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> McapWriter<W> {
+    /// Writes the magic and the header.
+    pub fn new(mut writer: W, library: &str) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+
+        let mut mcap = Self { writer };
+        let mut content = Vec::new();
+        put_str(&mut content, ""); // profile
+        put_str(&mut content, library);
+        mcap.record(OP_HEADER, &content)?;
+
+        Ok(mcap)
+    }
+
+    /// Writes a schema, referenced by its id in the channels.
+    pub fn schema(&mut self, id: u16, name: &str, encoding: &str, data: &[u8]) -> io::Result<()> {
+        let mut content = Vec::new();
+        content.extend_from_slice(&id.to_le_bytes());
+        put_str(&mut content, name);
+        put_str(&mut content, encoding);
+        put_bytes(&mut content, data);
+
+        self.record(OP_SCHEMA, &content)
+    }
+
+    /// Writes a channel, referenced by its id in the messages.
+    pub fn channel(
+        &mut self,
+        id: u16,
+        schema_id: u16,
+        topic: &str,
+        message_encoding: &str,
+    ) -> io::Result<()> {
+        let mut content = Vec::new();
+        content.extend_from_slice(&id.to_le_bytes());
+        content.extend_from_slice(&schema_id.to_le_bytes());
+        put_str(&mut content, topic);
+        put_str(&mut content, message_encoding);
+        content.extend_from_slice(&0u32.to_le_bytes()); // empty metadata
+
+        self.record(OP_CHANNEL, &content)
+    }
+
+    /// Writes a message logged at `log_time` (ns since the Unix epoch).
+    pub fn message(
+        &mut self,
+        channel_id: u16,
+        sequence: u32,
+        log_time: u64,
+        data: &[u8],
+    ) -> io::Result<()> {
+        let mut content = Vec::new();
+        content.extend_from_slice(&channel_id.to_le_bytes());
+        content.extend_from_slice(&sequence.to_le_bytes());
+        content.extend_from_slice(&log_time.to_le_bytes());
+        content.extend_from_slice(&log_time.to_le_bytes()); // publish time
+        content.extend_from_slice(data);
+
+        self.record(OP_MESSAGE, &content)
+    }
+
+    /// Writes the end of the file and returns the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        // a crc of 0 means that it was not computed
+        self.record(OP_DATA_END, &0u32.to_le_bytes())?;
+
+        let mut footer = Vec::new();
+        footer.extend_from_slice(&0u64.to_le_bytes()); // no summary
+        footer.extend_from_slice(&0u64.to_le_bytes()); // no summary offsets
+        footer.extend_from_slice(&0u32.to_le_bytes()); // no summary crc
+        self.record(OP_FOOTER, &footer)?;
+
+        self.writer.write_all(MAGIC)?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+
+    fn record(&mut self, op: u8, content: &[u8]) -> io::Result<()> {
+        self.writer.write_all(&[op])?;
+        self.writer
+            .write_all(&(content.len() as u64).to_le_bytes())?;
+        self.writer.write_all(content)
+    }
+}
+
+fn put_str(buf: &mut Vec<u8>, s: &str) {
+    put_bytes(buf, s.as_bytes());
+}
+
+fn put_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    buf.extend_from_slice(bytes);
+}
@@ -0,0 +1,283 @@
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use xmaxx_messages::{Command, Info};
+
+use crate::{FrameDecoder, Transport};
+
+/// The first bytes of a recording file.
+const MAGIC: &[u8; 8] = b"XMAXXREC";
+/// The version of the recording format.
+const VERSION: u8 = 1;
+
+/// Whether a frame was sent to or received from the firmware.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// A raw frame with the time at which it was sent or received.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub direction: Direction,
+    /// Time since the start of the recording (µs).
+    pub micros: u64,
+    /// The bytes of the frame, including the null byte delimiter.
+    pub frame: Vec<u8>,
+}
+
+impl Record {
+    /// Returns the time since the start of the recording.
+    pub fn time(&self) -> Duration {
+        Duration::from_micros(self.micros)
+    }
+
+    /// Decodes the message in the frame, if it is valid.
+    pub fn decode(&self) -> Option<Message> {
+        fn decode<M: DeserializeOwned>(frame: &[u8]) -> Option<M> {
+            let mut decoder = FrameDecoder::new(frame.len());
+            frame.iter().filter_map(|b| decoder.push(*b)).last()
+        }
+
+        match self.direction {
+            Direction::Sent => decode(&self.frame).map(Message::Sent),
+            Direction::Received => decode(&self.frame).map(Message::Received),
+        }
+    }
+}
+
+/// A message decoded from a recorded frame.
+#[derive(Debug)]
+pub enum Message {
+    Sent(Command),
+    Received(Info),
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    version: u8,
+    /// Unix time of the start of the recording (µs).
+    start_micros: u64,
+}
+
+/// Records the raw frames of a session to a file.
+///
+/// The file starts with [`MAGIC`] and a header, followed by the
+/// [`Record`]s, all serialized with postcard.
+pub struct Recorder {
+    writer: BufWriter<File>,
+    start: Instant,
+    /// The bytes received since the last null byte.
+    received: Vec<u8>,
+}
+
+impl Recorder {
+    /// Creates the recording file, overwriting it if it exists.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        let start_micros = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        writer.write_all(MAGIC)?;
+        postcard::to_io(
+            &Header {
+                version: VERSION,
+                start_micros,
+            },
+            &mut writer,
+        )
+        .map_err(to_io_error)?;
+
+        Ok(Self {
+            writer,
+            start: Instant::now(),
+            received: Vec::new(),
+        })
+    }
+
+    /// Records a frame sent to the firmware.
+    pub fn sent(&mut self, frame: &[u8]) -> io::Result<()> {
+        self.write(Direction::Sent, frame.to_vec())
+    }
+
+    /// Records a byte received from the firmware.
+    ///
+    /// The bytes are recorded as a frame at each null byte, valid or not.
+    pub fn received(&mut self, byte: u8) -> io::Result<()> {
+        self.received.push(byte);
+
+        if byte == 0 {
+            let frame = std::mem::take(&mut self.received);
+            self.write(Direction::Received, frame)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the buffered records to the file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    fn write(&mut self, direction: Direction, frame: Vec<u8>) -> io::Result<()> {
+        let record = Record {
+            direction,
+            micros: self.start.elapsed().as_micros() as u64,
+            frame,
+        };
+
+        postcard::to_io(&record, &mut self.writer).map_err(to_io_error)?;
+        Ok(())
+    }
+}
+
+/// A recorded session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording {
+    /// The time of the start of the recording.
+    pub start: SystemTime,
+    pub records: Vec<Record>,
+}
+
+impl Recording {
+    /// Reads a recording file.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+
+        let rest = bytes
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| invalid_data("not a recording file"))?;
+        let (header, mut rest) = postcard::take_from_bytes::<Header>(rest).map_err(to_io_error)?;
+        if header.version != VERSION {
+            return Err(invalid_data("unsupported recording version"));
+        }
+
+        let mut records = Vec::new();
+        while !rest.is_empty() {
+            match postcard::take_from_bytes::<Record>(rest) {
+                Ok((record, next)) => {
+                    records.push(record);
+                    rest = next;
+                }
+                // the end of the file is missing if the recorder was not
+                // flushed, but the records before are still good
+                Err(postcard::Error::DeserializeUnexpectedEnd) => break,
+                Err(err) => return Err(to_io_error(err)),
+            }
+        }
+
+        Ok(Self {
+            start: SystemTime::UNIX_EPOCH + Duration::from_micros(header.start_micros),
+            records,
+        })
+    }
+}
+
+/// A fake transport playing back the frames received in a recording.
+///
+/// The frames are read at the pace they were recorded, scaled by `speed`,
+/// or as fast as possible when it is `None`. What is written is kept in
+/// [`ReplayTransport::sent`] so it can be compared with the recording.
+///
+/// Reading fails with [`io::ErrorKind::UnexpectedEof`] at the end of the
+/// recording.
+pub struct ReplayTransport {
+    received: VecDeque<Record>,
+    speed: Option<f64>,
+    start: Instant,
+    /// The bytes of the frame being read.
+    pending: VecDeque<u8>,
+    sent: Vec<u8>,
+}
+
+impl ReplayTransport {
+    /// Returns a transport playing back the recording.
+    pub fn new(recording: Recording, speed: Option<f64>) -> Self {
+        let received = recording
+            .records
+            .into_iter()
+            .filter(|record| record.direction == Direction::Received)
+            .collect();
+
+        Self {
+            received,
+            speed,
+            start: Instant::now(),
+            pending: VecDeque::new(),
+            sent: Vec::new(),
+        }
+    }
+
+    /// Returns the bytes written to the transport.
+    pub fn sent(&self) -> &[u8] {
+        &self.sent
+    }
+
+    /// Waits until the next frame is due and makes it pending.
+    fn next_frame(&mut self) -> io::Result<()> {
+        let record = self
+            .received
+            .pop_front()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "end of the recording"))?;
+
+        if let Some(speed) = self.speed {
+            let due = record.time().div_f64(speed);
+            if let Some(delay) = due.checked_sub(self.start.elapsed()) {
+                thread::sleep(delay);
+            }
+        }
+
+        self.pending.extend(record.frame);
+        Ok(())
+    }
+}
+
+impl io::Read for ReplayTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.is_empty() {
+            self.next_frame()?;
+        }
+
+        let n = buf.len().min(self.pending.len());
+        for (b, pending) in buf.iter_mut().zip(self.pending.drain(..n)) {
+            *b = pending;
+        }
+        Ok(n)
+    }
+}
+
+impl io::Write for ReplayTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.sent.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn clear(&mut self) -> io::Result<()> {
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+fn to_io_error(err: postcard::Error) -> io::Error {
+    invalid_data(&err.to_string())
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
@@ -0,0 +1,19 @@
+use std::io;
+
+use serialport::{ClearBuffer, SerialPort};
+
+/// A byte stream to the firmware.
+///
+/// It is usually a serial port, but it can be faked to test the host code
+/// without the Xmaxx.
+pub trait Transport: io::Read + io::Write + Send {
+    /// Discards the bytes not yet read or written.
+    fn clear(&mut self) -> io::Result<()>;
+}
+
+impl Transport for Box<dyn SerialPort> {
+    fn clear(&mut self) -> io::Result<()> {
+        SerialPort::clear(self.as_ref(), ClearBuffer::All)?;
+        Ok(())
+    }
+}
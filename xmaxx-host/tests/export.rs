@@ -0,0 +1,138 @@
+//! The export of recordings to CSV and MCAP.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use xmaxx_host::*;
+use xmaxx_messages::*;
+
+/// Returns a path for the export of this test, in the temporary directory.
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("xmaxx-{name}-{}", std::process::id()))
+}
+
+fn record<M: serde::Serialize>(direction: Direction, millis: u64, message: &M) -> Record {
+    let mut buf = [0u8; Info::MAX_SERIAL_SIZE];
+    Record {
+        direction,
+        micros: millis * 1000,
+        frame: serialize(message, &mut buf).unwrap().to_vec(),
+    }
+}
+
+fn sensors(rpm: i32) -> Info {
+    Info::Sensors(Sensors {
+        fl_whl_rpm: CentiRpm(rpm),
+        fr_whl_rpm: CentiRpm(rpm),
+        rl_whl_rpm: CentiRpm(-rpm),
+        rr_whl_rpm: CentiRpm(-rpm),
+    })
+}
+
+/// A small session: two sensors, a ping and its pong, and a corrupt frame.
+fn recording() -> Recording {
+    let mut corrupt = record(Direction::Received, 30, &sensors(0));
+    corrupt.frame = vec![0x55, 0x55, 0x00];
+
+    Recording {
+        start: SystemTime::UNIX_EPOCH + Duration::from_secs(1_000),
+        records: vec![
+            record(Direction::Received, 0, &sensors(100)),
+            record(Direction::Sent, 10, &Command::Ping(7)),
+            record(Direction::Received, 20, &Info::Pong(7)),
+            corrupt,
+            record(Direction::Received, 1500, &sensors(-250)),
+        ],
+    }
+}
+
+#[test]
+fn the_csv_files_have_a_row_per_message() {
+    let dir = temp_path("csv");
+
+    let mut paths = export_csv(&recording(), &dir).unwrap();
+    paths.sort();
+    let read = |table: &str| std::fs::read_to_string(dir.join(format!("{table}.csv"))).unwrap();
+    let (ping, pong, sensors) = (read("ping"), read("pong"), read("sensors"));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let names: Vec<_> = paths.iter().map(|path| path.file_name().unwrap()).collect();
+    assert_eq!(names, ["ping.csv", "pong.csv", "sensors.csv"]);
+    assert_eq!(ping, "time,id\n0.010000,7\n");
+    assert_eq!(pong, "time,id\n0.020000,7\n");
+    assert_eq!(
+        sensors,
+        "time,fl_whl_rpm,fr_whl_rpm,rl_whl_rpm,rr_whl_rpm\n\
+         0.000000,100,100,-100,-100\n\
+         1.500000,-250,-250,250,250\n"
+    );
+}
+
+/// The records of an MCAP file: op code and content.
+fn mcap_records(bytes: &[u8]) -> Vec<(u8, &[u8])> {
+    const MAGIC: &[u8] = b"\x89MCAP0\r\n";
+    assert!(bytes.starts_with(MAGIC) && bytes.ends_with(MAGIC));
+
+    let mut rest = &bytes[MAGIC.len()..bytes.len() - MAGIC.len()];
+    let mut records = Vec::new();
+    while !rest.is_empty() {
+        let len = u64::from_le_bytes(rest[1..9].try_into().unwrap()) as usize;
+        records.push((rest[0], &rest[9..9 + len]));
+        rest = &rest[9 + len..];
+    }
+    records
+}
+
+/// Reads a string prefixed with its length.
+fn mcap_str(bytes: &[u8]) -> &str {
+    let len = u32::from_le_bytes(bytes[..4].try_into().unwrap()) as usize;
+    std::str::from_utf8(&bytes[4..4 + len]).unwrap()
+}
+
+#[test]
+fn the_mcap_file_has_a_channel_per_message_type() {
+    let path = temp_path("export.mcap");
+
+    export_mcap(&recording(), &path).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let records = mcap_records(&bytes);
+    // header first, then data end and footer
+    let ops: Vec<u8> = records.iter().map(|(op, _)| *op).collect();
+    assert_eq!(ops.first(), Some(&0x01));
+    assert_eq!(ops[ops.len() - 2..], [0x0f, 0x02]);
+
+    let topics: HashMap<u16, &str> = records
+        .iter()
+        .filter(|(op, _)| *op == 0x04)
+        .map(|(_, content)| {
+            let id = u16::from_le_bytes(content[..2].try_into().unwrap());
+            (id, mcap_str(&content[4..]))
+        })
+        .collect();
+    let mut messages = HashMap::<&str, Vec<(u32, u64, serde_json::Value)>>::new();
+    for (_, content) in records.iter().filter(|(op, _)| *op == 0x05) {
+        let id = u16::from_le_bytes(content[..2].try_into().unwrap());
+        let sequence = u32::from_le_bytes(content[2..6].try_into().unwrap());
+        let log_time = u64::from_le_bytes(content[6..14].try_into().unwrap());
+        let data = serde_json::from_slice(&content[22..]).unwrap();
+        messages
+            .entry(topics[&id])
+            .or_default()
+            .push((sequence, log_time, data));
+    }
+
+    let mut names: Vec<_> = topics.values().copied().collect();
+    names.sort();
+    assert_eq!(names, ["/ping", "/pong", "/sensors"]);
+    let sensors = &messages["/sensors"];
+    assert_eq!(sensors.len(), 2);
+    assert_eq!(messages["/ping"].len(), 1);
+    assert_eq!(messages["/pong"].len(), 1);
+    let (sequence, log_time, data) = &sensors[1];
+    assert_eq!(*sequence, 1);
+    assert_eq!(*log_time, 1_001_500_000_000);
+    assert_eq!(data["rl_whl_rpm"], 250);
+}
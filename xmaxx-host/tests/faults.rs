@@ -0,0 +1,446 @@
+//! The host and the firmware's command handling on a faulty link.
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::io;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use embedded_hal::digital::{self, OutputPin};
+use embedded_hal::pwm::{ErrorType, SetDutyCycle};
+
+use xmaxx_core::readbuf::ReadBuf;
+use xmaxx_core::serial::{read_command, write_event};
+use xmaxx_core::{Controller, Drivetrain};
+use xmaxx_host::*;
+use xmaxx_messages::*;
+
+const SEED: u64 = 42;
+const SENSORS: [CentiRpm; 4] = [CentiRpm(1), CentiRpm(-2), CentiRpm(3), CentiRpm(-4)];
+
+/// A PWM output going nowhere.
+struct NoPwm;
+
+impl ErrorType for NoPwm {
+    type Error = Infallible;
+}
+
+impl SetDutyCycle for NoPwm {
+    fn max_duty_cycle(&self) -> u16 {
+        255
+    }
+
+    fn set_duty_cycle(&mut self, _duty: u16) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// A digital output going nowhere.
+struct NoPin;
+
+impl digital::ErrorType for NoPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for NoPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// The serial port of the fake firmware.
+#[derive(Default)]
+struct Serial {
+    /// What the host wrote.
+    input: VecDeque<u8>,
+    /// What the firmware wrote.
+    output: VecDeque<u8>,
+}
+
+impl embedded_hal_v0::serial::Read<u8> for Serial {
+    type Error = Infallible;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        self.input.pop_front().ok_or(nb::Error::WouldBlock)
+    }
+}
+
+impl embedded_hal_v0::serial::Write<u8> for Serial {
+    type Error = Infallible;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.output.push_back(word);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// The firmware's main loop over an in-memory serial port.
+///
+/// An iteration runs whenever the host reads and nothing is pending.
+struct FakeFirmware {
+    controller: Controller<NoPwm, NoPwm, NoPwm, NoPwm, NoPwm, NoPin, NoPin>,
+    read_buf: ReadBuf<{ Command::MAX_SERIAL_SIZE }>,
+    serial: Serial,
+    /// The errors of the firmware.
+    errors: Arc<Mutex<Vec<Log>>>,
+    /// The bytes read before the port dies (`None` -> never).
+    lifetime: Option<usize>,
+    /// The reads that time out before it talks.
+    silence: usize,
+}
+
+impl FakeFirmware {
+    fn new() -> Self {
+        Self {
+            controller: Controller::new(Drivetrain {
+                steering: NoPwm,
+                motor_fl: NoPwm,
+                motor_fr: NoPwm,
+                motor_rl: NoPwm,
+                motor_rr: NoPwm,
+                enable_front: NoPin,
+                enable_rear: NoPin,
+            }),
+            read_buf: ReadBuf::new(),
+            serial: Serial::default(),
+            errors: Arc::default(),
+            lifetime: None,
+            silence: 0,
+        }
+    }
+
+    /// Returns an error if the port died.
+    fn check_alive(&self) -> io::Result<()> {
+        match self.lifetime {
+            Some(0) => Err(io::ErrorKind::BrokenPipe.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn run_once(&mut self) {
+        let mut write_buf = [0u8; Info::MAX_SERIAL_SIZE];
+
+        let command = read_command(&mut self.read_buf, &mut self.serial);
+        if let Some(info) = self.controller.handle(command) {
+            write_event(&info, &mut write_buf, &mut self.serial).unwrap();
+            match info {
+                Info::Log(Log::NoCommandReceived) => {}
+                Info::Log(log) => self.errors.lock().unwrap().push(log),
+                _ => {}
+            }
+        }
+
+        let [fl_whl_rpm, fr_whl_rpm, rl_whl_rpm, rr_whl_rpm] = SENSORS;
+        let sensors = Sensors {
+            fl_whl_rpm,
+            fr_whl_rpm,
+            rl_whl_rpm,
+            rr_whl_rpm,
+        };
+        write_event(&Info::Sensors(sensors), &mut write_buf, &mut self.serial).unwrap();
+    }
+}
+
+impl io::Read for FakeFirmware {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.check_alive()?;
+        if self.silence > 0 {
+            self.silence -= 1;
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        if self.serial.output.is_empty() {
+            self.run_once();
+        }
+
+        let n = buf.len().min(self.serial.output.len());
+        let n = self.lifetime.map_or(n, |lifetime| n.min(lifetime));
+        if let Some(lifetime) = &mut self.lifetime {
+            *lifetime -= n;
+        }
+        for (b, byte) in buf.iter_mut().zip(self.serial.output.drain(..n)) {
+            *b = byte;
+        }
+        Ok(n)
+    }
+}
+
+impl io::Write for FakeFirmware {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check_alive()?;
+        self.serial.input.extend(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for FakeFirmware {
+    fn clear(&mut self) -> io::Result<()> {
+        self.serial.output.clear();
+        Ok(())
+    }
+}
+
+/// Returns a connection to a fake firmware through a faulty link, and the
+/// errors of the firmware.
+fn connect(read: Faults, write: Faults) -> (Firmware, Arc<Mutex<Vec<Log>>>) {
+    let fake = FakeFirmware::new();
+    let errors = fake.errors.clone();
+    let transport = FaultyTransport::new(fake, read, write, SEED);
+    let settings = Settings {
+        send_delay: Duration::ZERO,
+        handshake_timeout: None,
+        ..Default::default()
+    };
+
+    (
+        Firmware::with_transport(Box::new(transport), settings),
+        errors,
+    )
+}
+
+/// Reads `n` bytes through a faulty link from a fake firmware.
+fn faulty_bytes(faults: Faults, seed: u64, n: usize) -> Vec<u8> {
+    let mut transport = FaultyTransport::new(FakeFirmware::new(), faults, Faults::default(), seed);
+    let mut bytes = vec![0u8; n];
+    io::Read::read_exact(&mut transport, &mut bytes).unwrap();
+    bytes
+}
+
+fn noisy() -> Faults {
+    Faults {
+        bytes: FaultRates {
+            drop: 0.01,
+            duplicate: 0.01,
+            reorder: 0.01,
+            corrupt: 0.01,
+            delay: 0.0,
+        },
+        frames: FaultRates {
+            drop: 0.05,
+            duplicate: 0.05,
+            reorder: 0.05,
+            corrupt: 0.05,
+            delay: 0.0,
+        },
+        delay: Duration::ZERO,
+    }
+}
+
+#[test]
+fn faults_are_reproducible() {
+    let clean = faulty_bytes(Faults::default(), SEED, 4096);
+    let first = faulty_bytes(noisy(), SEED, 4096);
+    let second = faulty_bytes(noisy(), SEED, 4096);
+    let other = faulty_bytes(noisy(), SEED + 1, 4096);
+
+    assert_ne!(first, clean);
+    assert_eq!(first, second);
+    assert_ne!(first, other);
+}
+
+#[test]
+fn recv_skips_corrupted_frames() {
+    let (mut firmware, _) = connect(noisy(), Faults::default());
+
+    let (mut good, mut bad) = (0, 0);
+    for _ in 0..1000 {
+        if let Info::Sensors(s) = firmware.recv().unwrap() {
+            match [s.fl_whl_rpm, s.fr_whl_rpm, s.rl_whl_rpm, s.rr_whl_rpm] {
+                SENSORS => good += 1,
+                _ => bad += 1,
+            }
+        }
+    }
+
+    let stats = firmware.stats();
+    assert!(stats.discarded_frames > 0, "{stats:?}");
+    assert!(stats.discarded_bytes > 0, "{stats:?}");
+    assert!(good > 400, "{good} good sensors");
+    // there is no checksum, so a corrupted value often still decodes
+    assert!(bad < good / 5, "{bad} bad sensors");
+}
+
+#[test]
+fn read_command_recovers_from_faults() {
+    let (mut firmware, errors) = connect(Faults::default(), noisy());
+
+    let answered = (0..100)
+        .filter(|_| firmware.ping(Duration::from_millis(20)).is_ok())
+        .count();
+
+    let errors = errors.lock().unwrap();
+    assert!(errors.contains(&Log::DeserializationError), "{errors:?}");
+    // a bad frame only loses itself and the one it merges with
+    assert!(answered > 50, "{answered} pings answered");
+}
+
+#[test]
+fn duplicated_commands_are_answered_twice() {
+    let write = Faults {
+        frames: FaultRates {
+            duplicate: 1.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let (mut firmware, _) = connect(Faults::default(), write);
+
+    firmware.send(&Command::Ping(7)).unwrap();
+    let pongs = (0..10)
+        .filter(|_| matches!(firmware.recv().unwrap(), Info::Pong(7)))
+        .count();
+
+    assert_eq!(pongs, 2);
+}
+
+#[test]
+fn delayed_commands_are_answered_late() {
+    let delay = Duration::from_millis(50);
+    let write = Faults {
+        frames: FaultRates {
+            delay: 1.0,
+            ..Default::default()
+        },
+        delay,
+        ..Default::default()
+    };
+    let (mut firmware, _) = connect(Faults::default(), write);
+
+    let rtt = firmware.ping(Duration::from_secs(1)).unwrap();
+
+    assert!(rtt >= delay, "{rtt:?}");
+}
+
+/// Returns a connection to fake firmwares, the first one dying after
+/// `lifetime` bytes, and the number of times it was opened.
+///
+/// Opening the port fails `failures` times after the first one died, like
+/// while the Arduino resets.
+fn reconnecting(
+    lifetime: usize,
+    failures: u32,
+    policy: ReconnectPolicy,
+) -> (Result<Firmware, Error>, Arc<AtomicU32>) {
+    let opened = Arc::new(AtomicU32::new(0));
+    let open = {
+        let opened = opened.clone();
+        move || {
+            let mut fake = FakeFirmware::new();
+            match opened.fetch_add(1, Ordering::SeqCst) {
+                0 => fake.lifetime = Some(lifetime),
+                n if n <= failures => return Err(io::ErrorKind::NotFound.into()),
+                _ => {}
+            }
+            Ok(Box::new(fake) as Box<dyn Transport>)
+        }
+    };
+    let settings = Settings {
+        send_delay: Duration::ZERO,
+        handshake_timeout: Some(Duration::from_millis(100)),
+        reconnect: policy,
+        ..Default::default()
+    };
+
+    (Firmware::with_reopen(open, settings), opened)
+}
+
+#[test]
+fn reconnects_when_the_port_dies() {
+    let policy = ReconnectPolicy {
+        retry_delay: Duration::ZERO,
+        ..Default::default()
+    };
+    let (firmware, opened) = reconnecting(100, 2, policy);
+    let mut firmware = firmware.unwrap();
+
+    // the handshake read a frame of the first firmware, the port dies
+    // during these and the next ones come from the second one
+    for _ in 0..20 {
+        firmware.recv().unwrap();
+    }
+    firmware.ping(Duration::from_millis(100)).unwrap();
+
+    assert_eq!(opened.load(Ordering::SeqCst), 4);
+    assert_eq!(firmware.state(), ConnectionState::Connected);
+    let events: Vec<_> = std::iter::from_fn(|| firmware.poll_event()).collect();
+    let port = String::from("transport");
+    assert!(
+        matches!(
+            &events[..],
+            [
+                ConnectionEvent::Connected { port: first },
+                ConnectionEvent::Disconnected { .. },
+                ConnectionEvent::ReconnectFailed { attempt: 1, .. },
+                ConnectionEvent::ReconnectFailed { attempt: 2, .. },
+                ConnectionEvent::Reconnected { port: second },
+            ] if *first == port && *second == port
+        ),
+        "{events:?}"
+    );
+}
+
+#[test]
+fn gives_up_reconnecting_after_the_attempts() {
+    let policy = ReconnectPolicy {
+        max_attempts: Some(3),
+        retry_delay: Duration::ZERO,
+        ..Default::default()
+    };
+    let (firmware, opened) = reconnecting(100, u32::MAX, policy);
+    let mut firmware = firmware.unwrap();
+
+    let error = (0..20).find_map(|_| firmware.recv().err());
+
+    assert!(matches!(error, Some(Error::Io(_))), "{error:?}");
+    assert_eq!(opened.load(Ordering::SeqCst), 4);
+    assert_eq!(firmware.state(), ConnectionState::Disconnected);
+    // the next operation tries to reconnect again
+    assert!(firmware.ping(Duration::from_millis(100)).is_err());
+    assert_eq!(opened.load(Ordering::SeqCst), 7);
+}
+
+#[test]
+fn the_handshake_fails_on_a_dead_port() {
+    let (firmware, opened) = reconnecting(0, 0, ReconnectPolicy::default());
+
+    assert!(matches!(firmware, Err(Error::Io(_))));
+    assert_eq!(opened.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn a_silent_transport_is_kept() {
+    let mut fake = FakeFirmware::new();
+    fake.silence = 5;
+    let settings = Settings {
+        send_delay: Duration::ZERO,
+        handshake_timeout: None,
+        max_timeouts: Some(3),
+        ..Default::default()
+    };
+    let mut firmware = Firmware::with_transport(Box::new(fake), settings);
+
+    for _ in 0..2 {
+        assert!(matches!(firmware.recv(), Err(Error::Io(_))));
+    }
+    // it cannot be reopened, so it is not dropped when it goes stale
+    for _ in 0..3 {
+        assert!(matches!(firmware.recv(), Err(Error::Timeout)));
+    }
+    assert_eq!(firmware.state(), ConnectionState::Connected);
+
+    // and it talks again
+    firmware.recv().unwrap();
+    assert_eq!(firmware.state(), ConnectionState::Connected);
+}
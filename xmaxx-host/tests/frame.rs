@@ -0,0 +1,122 @@
+//! The splitting of the received bytes into messages.
+
+use xmaxx_host::{FrameDecoder, RecvStats};
+use xmaxx_messages::*;
+
+/// Returns the frame of a message.
+fn frame(info: &Info) -> Vec<u8> {
+    let mut buf = [0u8; Info::MAX_SERIAL_SIZE];
+    serialize(info, &mut buf).unwrap().to_vec()
+}
+
+/// Pushes the bytes and returns the messages they complete.
+fn decode(decoder: &mut FrameDecoder<Info>, bytes: &[u8]) -> Vec<Info> {
+    bytes
+        .iter()
+        .filter_map(|&byte| decoder.push(byte))
+        .collect()
+}
+
+fn new() -> FrameDecoder<Info> {
+    FrameDecoder::new(Info::MAX_SERIAL_SIZE)
+}
+
+#[test]
+fn back_to_back_frames_are_all_decoded() {
+    let mut decoder = new();
+    let bytes: Vec<u8> = (0..10).flat_map(|id| frame(&Info::Pong(id))).collect();
+
+    let infos = decode(&mut decoder, &bytes);
+
+    assert!(matches!(infos[..], [Info::Pong(0), .., Info::Pong(9)]));
+    assert_eq!(infos.len(), 10);
+    assert_eq!(
+        decoder.stats(),
+        RecvStats {
+            frames: 10,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn a_frame_split_across_pushes_is_decoded_once_complete() {
+    let mut decoder = new();
+    let bytes = frame(&Info::Pong(7));
+    let (start, end) = bytes.split_at(bytes.len() / 2);
+
+    assert!(decode(&mut decoder, start).is_empty());
+    assert!(matches!(decode(&mut decoder, end)[..], [Info::Pong(7)]));
+}
+
+#[test]
+fn garbage_before_a_frame_is_skipped() {
+    let mut decoder = new();
+    let mut bytes = b"panicked at src/main.rs".to_vec();
+    bytes.extend(frame(&Info::Pong(7)));
+
+    let infos = decode(&mut decoder, &bytes);
+
+    assert!(matches!(infos[..], [Info::Pong(7)]));
+    let stats = decoder.stats();
+    assert_eq!(stats.frames, 1);
+    assert_eq!(stats.discarded_frames, 0);
+    assert_eq!(stats.discarded_bytes, 23);
+}
+
+#[test]
+fn the_decoder_resyncs_after_a_truncated_frame() {
+    let mut decoder = new();
+    let truncated = frame(&Info::Pong(1));
+    let mut bytes = truncated[..truncated.len() - 2].to_vec();
+    for id in 2..5 {
+        bytes.extend(frame(&Info::Pong(id)));
+    }
+
+    let infos = decode(&mut decoder, &bytes);
+
+    // without its delimiter, it merges with the next frame, which is lost
+    // or garbled
+    assert!(infos.len() <= 3, "{infos:?}");
+    assert!(
+        matches!(infos[..], [.., Info::Pong(3), Info::Pong(4)]),
+        "{infos:?}"
+    );
+}
+
+#[test]
+fn a_corrupt_frame_is_discarded() {
+    let mut decoder = new();
+    // a cobs frame of an unknown variant
+    let mut bytes = vec![0x03, 0x7F, 0x7F, 0x00];
+    bytes.extend(frame(&Info::Pong(2)));
+
+    let infos = decode(&mut decoder, &bytes);
+
+    assert!(matches!(infos[..], [Info::Pong(2)]));
+    let stats = decoder.stats();
+    assert_eq!(stats.frames, 1);
+    assert_eq!(stats.discarded_frames, 1);
+    assert_eq!(stats.discarded_bytes, 4);
+}
+
+#[test]
+fn a_long_run_of_garbage_does_not_grow_the_buffer() {
+    let mut decoder = new();
+    let garbage = vec![0x55; 10 * Info::MAX_SERIAL_SIZE];
+    let mut bytes = garbage.clone();
+    bytes.extend(frame(&Info::Pong(2)));
+
+    let infos = decode(&mut decoder, &bytes);
+
+    assert!(matches!(infos[..], [Info::Pong(2)]));
+    assert_eq!(decoder.stats().discarded_bytes, garbage.len() as u64);
+
+    // and the reset drops the partial frame
+    decode(&mut decoder, &[0x55; 3]);
+    decoder.reset();
+    assert!(matches!(
+        decode(&mut decoder, &frame(&Info::Pong(3)))[..],
+        [Info::Pong(3)]
+    ));
+}
@@ -0,0 +1,140 @@
+//! The recording and the replay of sessions.
+
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use xmaxx_host::*;
+use xmaxx_messages::*;
+
+/// Returns a path for a recording of this test, in the temporary directory.
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("xmaxx-{name}-{}.rec", std::process::id()))
+}
+
+/// Returns the frame of a message.
+fn frame<M: serde::Serialize>(message: &M) -> Vec<u8> {
+    let mut buf = [0u8; Info::MAX_SERIAL_SIZE];
+    serialize(message, &mut buf).unwrap().to_vec()
+}
+
+fn received(millis: u64, info: &Info) -> Record {
+    Record {
+        direction: Direction::Received,
+        micros: millis * 1000,
+        frame: frame(info),
+    }
+}
+
+#[test]
+fn a_recording_reads_back_identically() {
+    let path = temp_path("roundtrip");
+    let before = SystemTime::now();
+
+    let mut recorder = Recorder::create(&path).unwrap();
+    recorder.sent(&frame(&Command::Ping(1))).unwrap();
+    thread::sleep(Duration::from_millis(20));
+    for byte in frame(&Info::Pong(1)) {
+        recorder.received(byte).unwrap();
+    }
+    // a corrupt frame is recorded as is
+    for byte in [0x55, 0x55, 0x00] {
+        recorder.received(byte).unwrap();
+    }
+    // the partial frame is not
+    recorder.received(0x55).unwrap();
+    recorder.flush().unwrap();
+
+    let recording = Recording::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(recording.start >= before - Duration::from_secs(1));
+    let frames: Vec<_> = recording
+        .records
+        .iter()
+        .map(|record| (record.direction, record.frame.clone()))
+        .collect();
+    assert_eq!(
+        frames,
+        [
+            (Direction::Sent, frame(&Command::Ping(1))),
+            (Direction::Received, frame(&Info::Pong(1))),
+            (Direction::Received, vec![0x55, 0x55, 0x00]),
+        ]
+    );
+    let [ping, pong, corrupt] = &recording.records[..] else {
+        unreachable!()
+    };
+    assert!(pong.time() - ping.time() >= Duration::from_millis(20));
+    assert!(pong.time() <= corrupt.time());
+    assert!(matches!(
+        ping.decode(),
+        Some(Message::Sent(Command::Ping(1)))
+    ));
+    assert!(matches!(
+        pong.decode(),
+        Some(Message::Received(Info::Pong(1)))
+    ));
+    assert!(corrupt.decode().is_none());
+}
+
+#[test]
+fn a_file_that_is_not_a_recording_is_refused() {
+    let path = temp_path("invalid");
+    std::fs::write(&path, b"not a recording").unwrap();
+
+    let error = Recording::open(&path).unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn the_replay_keeps_the_pace_of_the_recording() {
+    let recording = Recording {
+        start: SystemTime::now(),
+        records: vec![
+            received(0, &Info::Pong(1)),
+            received(100, &Info::Pong(2)),
+            received(200, &Info::Pong(3)),
+        ],
+    };
+    let replay = |speed| {
+        let transport = ReplayTransport::new(recording.clone(), speed);
+        let mut firmware = Firmware::with_transport(Box::new(transport), Settings::default());
+
+        let start = Instant::now();
+        let infos: Vec<_> = (0..3).map(|_| firmware.recv().unwrap()).collect();
+        assert!(matches!(
+            infos[..],
+            [Info::Pong(1), Info::Pong(2), Info::Pong(3)]
+        ));
+        // the end of the recording is the end of the connection
+        assert!(firmware.recv().is_err());
+        start.elapsed()
+    };
+
+    let real_time = replay(Some(1.0));
+    assert!(real_time >= Duration::from_millis(200), "{real_time:?}");
+    assert!(real_time < Duration::from_millis(300), "{real_time:?}");
+
+    let twice = replay(Some(2.0));
+    assert!(twice >= Duration::from_millis(100), "{twice:?}");
+    assert!(twice < Duration::from_millis(200), "{twice:?}");
+
+    let fast = replay(None);
+    assert!(fast < Duration::from_millis(50), "{fast:?}");
+}
+
+#[test]
+fn the_replay_keeps_what_is_sent() {
+    let recording = Recording {
+        start: SystemTime::now(),
+        records: vec![received(0, &Info::Pong(1))],
+    };
+    let mut transport = ReplayTransport::new(recording, None);
+
+    std::io::Write::write_all(&mut transport, &frame(&Command::Ping(1))).unwrap();
+
+    assert_eq!(transport.sent(), frame(&Command::Ping(1)));
+}
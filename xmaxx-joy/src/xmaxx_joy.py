@@ -27,6 +27,12 @@ class XmaxxJoy:
 
     DPAD_HAT = 0
 
+    # steering angle to go straight and its range on each side (deg)
+    STEERING_ZERO = 90.0
+    STEERING_RANGE = 50.0
+    # wheel speed with a trigger fully pressed (RPM)
+    MAX_RPM = 1000.0
+
     def __init__(self, firmware):
         """Initializes the joystick.
 
@@ -47,11 +53,13 @@ class XmaxxJoy:
 
     def parse_command(self):
         """Reads the joystick and returns a command."""
+        # the triggers go from -1 (released) to 1 (pressed)
         forward = self._joy.get_axis(self.RT_AX)
         backward = self._joy.get_axis(self.LT_AX)
-        net = forward - backward
-        steering = self._joy.get_axis(self.L_LR_AX)
-        return Command(steering, net, net, net, net)
+        rpm = (forward - backward) / 2 * self.MAX_RPM
+        # the stick goes from -1 (left) to 1 (right)
+        steering = self.STEERING_ZERO - self._joy.get_axis(self.L_LR_AX) * self.STEERING_RANGE
+        return Command.from_degrees_and_rpm(steering, rpm, rpm, rpm, rpm)
 
     def listen_and_command(self, freq=30):
         """Listens to the joystick and sends commands to the firmware.
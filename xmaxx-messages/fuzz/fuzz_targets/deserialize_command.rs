@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use xmaxx_messages::{deserialize, Command};
+
+// The firmware decodes whatever arrives on the serial port.
+fuzz_target!(|data: &[u8]| {
+    let mut buffer = data.to_vec();
+    let _ = deserialize::<Command>(&mut buffer);
+});
@@ -0,0 +1,272 @@
+//! Human-readable messages, for the host.
+
+use std::fmt;
+
+use crate::*;
+
+impl fmt::Display for CentiDegrees {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2} deg", self.degrees())
+    }
+}
+
+impl fmt::Display for CentiRpm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2} rpm", self.rpm())
+    }
+}
+
+impl fmt::Display for MilliAmps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.3} A", self.amps())
+    }
+}
+
+impl fmt::Display for MilliVolts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2} V", self.volts())
+    }
+}
+
+impl fmt::Display for MilliMeters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.3} m", self.meters())
+    }
+}
+
+impl fmt::Display for PerMille {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1} %", self.ratio() * 100.0)
+    }
+}
+
+impl fmt::Display for MilliG {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.3} g", self.g())
+    }
+}
+
+impl fmt::Display for CentiDegreesPerSecond {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2} deg/s", self.degrees_per_second())
+    }
+}
+
+impl fmt::Display for Info {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Info::Sensors(sensors) => write!(f, "sensors {sensors}"),
+            Info::Log(log) => write!(f, "log {log}"),
+            Info::Pong(id) => write!(f, "pong {id}"),
+            Info::Param(param, value) => write!(f, "param {param} = {value}"),
+            Info::Currents(currents) => write!(f, "currents {currents}"),
+            Info::Battery(battery) => write!(f, "battery {battery}"),
+            Info::Encoders(encoders) => write!(f, "encoders {encoders}"),
+            Info::Imu(imu) => write!(f, "imu {imu}"),
+            Info::Control(control) => write!(f, "control {control}"),
+            Info::Proximity(proximity) => write!(f, "proximity {proximity}"),
+            Info::Traction(traction) => write!(f, "traction {traction}"),
+            Info::Odometry(odometry) => write!(f, "odometry {odometry}"),
+            Info::Axles(axles) => write!(f, "axles {axles}"),
+        }
+    }
+}
+
+impl fmt::Display for Sensors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "fl {}, fr {}, rl {}, rr {}",
+            self.fl_whl_rpm, self.fr_whl_rpm, self.rl_whl_rpm, self.rr_whl_rpm
+        )
+    }
+}
+
+impl fmt::Display for Currents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "fl {}, fr {}, rl {}, rr {}",
+            self.fl, self.fr, self.rl, self.rr
+        )
+    }
+}
+
+impl fmt::Display for Battery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.voltage)
+    }
+}
+
+impl fmt::Display for Encoders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "fl {} ticks, fr {} ticks, rl {} ticks, rr {} ticks",
+            self.fl, self.fr, self.rl, self.rr
+        )
+    }
+}
+
+impl fmt::Display for Imu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "accel {} {} {}, gyro {} {} {}, yaw rate {}",
+            self.accel_x,
+            self.accel_y,
+            self.accel_z,
+            self.gyro_x,
+            self.gyro_y,
+            self.gyro_z,
+            self.yaw_rate
+        )
+    }
+}
+
+impl fmt::Display for Proximity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let distance = |distance: Option<MilliMeters>| match distance {
+            Some(distance) => distance.to_string(),
+            None => "clear".to_string(),
+        };
+        write!(
+            f,
+            "front {}, rear {}",
+            distance(self.front),
+            distance(self.rear)
+        )
+    }
+}
+
+impl fmt::Display for Traction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "slip fl {}, fr {}, rl {}, rr {}, control {}",
+            self.fl,
+            self.fr,
+            self.rl,
+            self.rr,
+            if self.active { "active" } else { "inactive" }
+        )
+    }
+}
+
+impl fmt::Display for Odometry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "x {}, y {}, heading {}, distance {} at {} ms",
+            self.x, self.y, self.heading, self.distance, self.time
+        )
+    }
+}
+
+impl fmt::Display for Axles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = |enabled| if enabled { "enabled" } else { "disabled" };
+        write!(
+            f,
+            "{}, front {}, rear {}",
+            self.mode,
+            state(self.front),
+            state(self.rear)
+        )
+    }
+}
+
+impl fmt::Display for DriveMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DriveMode::AllWheel => "all-wheel drive",
+            DriveMode::FrontWheel => "front-wheel drive",
+            DriveMode::RearWheel => "rear-wheel drive",
+            DriveMode::Coast => "coast",
+        })
+    }
+}
+
+impl fmt::Display for Control {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} mode, from {}", self.mode, self.source)
+    }
+}
+
+impl fmt::Display for ControlMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ControlMode::Host => "host",
+            ControlMode::Rc => "rc",
+            ControlMode::Auto => "auto",
+        })
+    }
+}
+
+impl fmt::Display for ControlSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ControlSource::Host => "host",
+            ControlSource::Rc => "rc",
+            ControlSource::Stopped => "nothing, stopped",
+        })
+    }
+}
+
+impl Log {
+    /// Returns what happened in the firmware.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Log::SerializationError => "could not serialize a message",
+            Log::DeserializationError => "could not deserialize a command",
+            Log::ReadBufferOverflow => "a command was too long for the read buffer",
+            Log::ReadTimeout => "timed out reading a command",
+            Log::FirmwarePanic => "the firmware panicked",
+            Log::InvalidCommand => "the command was out of range",
+            Log::CommandReceived => "a command was received",
+            Log::NoCommandReceived => "no command was received",
+            Log::InvalidParam => "the value of the parameter was refused",
+            Log::Overcurrent => "a motor drew too much current and was cut",
+            Log::LowBattery => "the battery is low and the speed is limited",
+            Log::BatteryCutoff => "the battery is empty and the motors are disarmed",
+            Log::ImuError => "the IMU did not answer",
+            Log::RcSignalLost => "the RC signal was lost and the wheels are stopped",
+            Log::CollisionStop => "an obstacle is too close and the wheels are stopped",
+        }
+    }
+}
+
+impl fmt::Display for Log {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::Drive(drive) => write!(f, "drive {drive}"),
+            Command::Ping(id) => write!(f, "ping {id}"),
+            Command::GetParam(param) => write!(f, "get_param {param}"),
+            Command::SetParam(param, value) => write!(f, "set_param {param} = {value}"),
+            Command::ResetOdometry => f.write_str("reset_odometry"),
+            Command::SetDriveMode(mode) => write!(f, "set_drive_mode {mode}"),
+        }
+    }
+}
+
+impl fmt::Display for Drive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "steering {}, fl {}, fr {}, rl {}, rr {}",
+            self.steering, self.fl_whl_rpm, self.fr_whl_rpm, self.rl_whl_rpm, self.rr_whl_rpm
+        )
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
@@ -1,35 +1,238 @@
-#![no_std]
+#![cfg_attr(not(feature = "std"), no_std)]
 
+use postcard::experimental::max_size::MaxSize;
 use postcard::{from_bytes_cobs, to_slice_cobs};
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "std")]
+mod display;
+mod units;
+
+pub use units::*;
+
+/// Returns the length of the longest frame of a message whose postcard
+/// encoding is at most `len` bytes long.
+///
+/// COBS adds an overhead byte, and another one every 254 bytes, and the
+/// frame ends with a null byte.
+const fn frame_size(len: usize) -> usize {
+    len + len.div_ceil(254) + 1
+}
+
 /// Information sent by the firmware.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, MaxSize, Debug, Clone, PartialEq, Eq)]
 pub enum Info {
     Sensors(Sensors),
     Log(Log),
+    /// Answer to [`Command::Ping`] with the same id.
+    Pong(u32),
+    /// Value of a parameter, answer to [`Command::GetParam`] and
+    /// [`Command::SetParam`].
+    Param(Param, i32),
+    Currents(Currents),
+    Battery(Battery),
+    Encoders(Encoders),
+    Imu(Imu),
+    Control(Control),
+    Proximity(Proximity),
+    Traction(Traction),
+    /// Pose dead-reckoned by the firmware, also the answer to
+    /// [`Command::ResetOdometry`].
+    Odometry(Odometry),
+    /// Axles driven, also the answer to [`Command::SetDriveMode`].
+    Axles(Axles),
 }
 
 impl Info {
-    pub const MAX_SERIAL_SIZE: usize = ((core::mem::size_of::<Info>() * 8) / 7 + 1) // postcard encoding max len
-    + ((core::mem::size_of::<Info>() * 8) / 7 + 1) / 8 + 2; // cobs overhead
+    /// The length of the longest frame of an info.
+    pub const MAX_SERIAL_SIZE: usize = frame_size(Info::POSTCARD_MAX_SIZE);
 }
 
 /// Sensor readings.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, MaxSize, Debug, Clone, PartialEq, Eq)]
 pub struct Sensors {
-    /// Front left wheel RPM.
-    pub fl_whl_rpm: i32,
-    /// Front right wheel RPM.
-    pub fr_whl_rpm: i32,
-    /// Rear left wheel RPM.
-    pub rl_whl_rpm: i32,
-    /// Rear right wheel RPM.
-    pub rr_whl_rpm: i32,
+    /// Front left wheel speed.
+    pub fl_whl_rpm: CentiRpm,
+    /// Front right wheel speed.
+    pub fr_whl_rpm: CentiRpm,
+    /// Rear left wheel speed.
+    pub rl_whl_rpm: CentiRpm,
+    /// Rear right wheel speed.
+    pub rr_whl_rpm: CentiRpm,
+}
+
+/// Currents drawn by the motors.
+#[derive(Serialize, Deserialize, MaxSize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct Currents {
+    /// Front left motor current.
+    pub fl: MilliAmps,
+    /// Front right motor current.
+    pub fr: MilliAmps,
+    /// Rear left motor current.
+    pub rl: MilliAmps,
+    /// Rear right motor current.
+    pub rr: MilliAmps,
+}
+
+/// State of the battery pack.
+#[derive(Serialize, Deserialize, MaxSize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct Battery {
+    /// Voltage of the pack.
+    pub voltage: MilliVolts,
+}
+
+/// Ticks counted by the wheel encoders since the firmware started.
+///
+/// The counts wrap around, so the distance between two messages is their
+/// wrapping difference.
+#[derive(Serialize, Deserialize, MaxSize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct Encoders {
+    /// Front left wheel ticks.
+    pub fl: i32,
+    /// Front right wheel ticks.
+    pub fr: i32,
+    /// Rear left wheel ticks.
+    pub rl: i32,
+    /// Rear right wheel ticks.
+    pub rr: i32,
+}
+
+/// Readings of the inertial measurement unit.
+///
+/// The axes are those of the IMU, mounted with x forwards, y to the left
+/// and z up.
+#[derive(Serialize, Deserialize, MaxSize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct Imu {
+    /// Acceleration along x, forwards.
+    pub accel_x: MilliG,
+    /// Acceleration along y, to the left.
+    pub accel_y: MilliG,
+    /// Acceleration along z, up; about 1 g at rest.
+    pub accel_z: MilliG,
+    /// Rotation around x, rolling to the right.
+    pub gyro_x: CentiDegreesPerSecond,
+    /// Rotation around y, pitching down.
+    pub gyro_y: CentiDegreesPerSecond,
+    /// Rotation around z, turning left.
+    pub gyro_z: CentiDegreesPerSecond,
+    /// Rotation around z without the bias of the gyroscope, which is
+    /// measured while the Xmaxx is at rest.
+    pub yaw_rate: CentiDegreesPerSecond,
+}
+
+/// Distances to the obstacles measured by the range sensors.
+#[derive(Serialize, Deserialize, MaxSize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct Proximity {
+    /// Distance in front, if anything is in range.
+    pub front: Option<MilliMeters>,
+    /// Distance behind, if anything is in range.
+    pub rear: Option<MilliMeters>,
+}
+
+/// Slip of the wheels and state of the traction control.
+///
+/// The slip of a wheel is how much faster it turns than the ground goes
+/// under it, relative to its speed: 0 when it rolls, 1 when it spins in
+/// place.
+#[derive(Serialize, Deserialize, MaxSize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct Traction {
+    /// Front left wheel slip.
+    pub fl: PerMille,
+    /// Front right wheel slip.
+    pub fr: PerMille,
+    /// Rear left wheel slip.
+    pub rl: PerMille,
+    /// Rear right wheel slip.
+    pub rr: PerMille,
+    /// Whether the traction control reduces the speed of a slipping wheel.
+    pub active: bool,
+}
+
+/// Pose of the Xmaxx dead-reckoned from the speeds of its rear wheels and
+/// the steering commanded, since the firmware started or the last
+/// [`Command::ResetOdometry`].
+///
+/// The pose is of the center of the rear axle, in the frame of the pose at
+/// the reset: x forwards and y to the left.
+#[derive(Serialize, Deserialize, MaxSize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct Odometry {
+    /// Time of the pose on the clock of the firmware (ms), in steps of 10
+    /// ms, which wraps around after 49 days.
+    pub time: u32,
+    /// Position along x, forwards.
+    pub x: MilliMeters,
+    /// Position along y, to the left.
+    pub y: MilliMeters,
+    /// Heading counterclockwise from x, from -180 to 180 deg.
+    pub heading: CentiDegrees,
+    /// Distance traveled, forwards and backwards.
+    pub distance: MilliMeters,
+}
+
+/// State of the motor drivers of the axles.
+#[derive(Serialize, Deserialize, MaxSize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Axles {
+    /// Axles that the host wants driven.
+    pub mode: DriveMode,
+    /// Whether the drivers of the front motors are enabled.
+    pub front: bool,
+    /// Whether the drivers of the rear motors are enabled.
+    pub rear: bool,
+}
+
+/// Axles driven by their motors, the others roll freely.
+///
+/// The protections that cut the motors disable their drivers whatever the
+/// mode.
+#[derive(Serialize, Deserialize, MaxSize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriveMode {
+    /// Both axles, the default.
+    AllWheel,
+    /// The front axle.
+    FrontWheel,
+    /// The rear axle.
+    RearWheel,
+    /// Neither axle, the Xmaxx coasts.
+    Coast,
+}
+
+/// Who drives the Xmaxx.
+#[derive(Serialize, Deserialize, MaxSize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Control {
+    /// Mode selected on the RC transmitter.
+    pub mode: ControlMode,
+    /// Source of the command followed.
+    pub source: ControlSource,
+}
+
+/// Mode selected by the mode switch of the RC transmitter.
+///
+/// Without an RC receiver, the mode is [`ControlMode::Host`].
+#[derive(Serialize, Deserialize, MaxSize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlMode {
+    /// The host drives, and the wheels stop when it stops sending commands.
+    Host,
+    /// The RC transmitter drives.
+    Rc,
+    /// The host drives while it sends commands, and the RC transmitter
+    /// drives when it stops, like when the host is down.
+    Auto,
+}
+
+/// Source of the command followed by the firmware.
+#[derive(Serialize, Deserialize, MaxSize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlSource {
+    /// The last command of the host.
+    Host,
+    /// The sticks of the RC transmitter.
+    Rc,
+    /// Nothing, the wheels are stopped because the source of the mode is
+    /// lost.
+    Stopped,
 }
 
 /// Information about what it happening in the firmware.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, MaxSize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Log {
     SerializationError,
     DeserializationError,
@@ -39,29 +242,175 @@ pub enum Log {
     InvalidCommand,
     CommandReceived,
     NoCommandReceived,
+    InvalidParam,
+    /// A motor drew more than its current limit for too long and was cut
+    /// until its speed is commanded to zero.
+    Overcurrent,
+    /// The battery went below its warning voltage and the speed is limited.
+    LowBattery,
+    /// The battery went below its cutoff voltage and the motors are
+    /// disarmed until it recovers and the speeds are commanded to zero.
+    BatteryCutoff,
+    /// The IMU did not answer on I2C, or is not the expected device.
+    ImuError,
+    /// The signal of the RC receiver was lost while it drove, and the
+    /// wheels are stopped until it comes back.
+    RcSignalLost,
+    /// An obstacle is closer than the stop distance in the direction
+    /// commanded, and the wheels are stopped.
+    CollisionStop,
 }
 
 /// Command sent to the firmware.
-#[derive(Serialize, Deserialize, Default, Debug)]
-pub struct Command {
-    /// Angle of the steering (90 deg -> straight).
-    pub steering: i32,
-    /// Front left wheel RPM.
-    pub fl_whl_rpm: i32,
-    /// Front right wheel RPM.
-    pub fr_whl_rpm: i32,
-    /// Rear left wheel RPM.
-    pub rl_whl_rpm: i32,
-    /// Rear right wheel RPM.
-    pub rr_whl_rpm: i32,
+#[derive(Serialize, Deserialize, MaxSize, Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Drives the Xmaxx.
+    Drive(Drive),
+    /// Asks the firmware to answer with [`Info::Pong`] and the same id.
+    Ping(u32),
+    /// Asks the firmware for the value of a parameter.
+    GetParam(Param),
+    /// Sets a parameter of the firmware.
+    SetParam(Param, i32),
+    /// Moves the pose of [`Info::Odometry`] back to the origin and its
+    /// distance to zero, answered with the new odometry.
+    ResetOdometry,
+    /// Selects the axles driven, answered with [`Info::Axles`].
+    SetDriveMode(DriveMode),
 }
 
 impl Command {
-    pub const MAX_SERIAL_SIZE: usize = ((core::mem::size_of::<Info>() * 8) / 7 + 1) // postcard encoding max len
-    + ((core::mem::size_of::<Info>() * 8) / 7 + 1) / 8 + 2; // cobs overhead
+    /// The length of the longest frame of a command.
+    pub const MAX_SERIAL_SIZE: usize = frame_size(Command::POSTCARD_MAX_SIZE);
+}
+
+// The firmware allocates its buffers from these on a chip with 8 KB of RAM.
+const _: () = assert!(Info::MAX_SERIAL_SIZE <= 32);
+const _: () = assert!(Command::MAX_SERIAL_SIZE <= 32);
+
+/// Steering and wheel speeds to apply.
+#[derive(Serialize, Deserialize, MaxSize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct Drive {
+    /// Angle of the steering (90 deg -> straight).
+    pub steering: CentiDegrees,
+    /// Front left wheel speed.
+    pub fl_whl_rpm: CentiRpm,
+    /// Front right wheel speed.
+    pub fr_whl_rpm: CentiRpm,
+    /// Rear left wheel speed.
+    pub rl_whl_rpm: CentiRpm,
+    /// Rear right wheel speed.
+    pub rr_whl_rpm: CentiRpm,
+}
+
+/// Parameter of the firmware that can be read and set at runtime.
+///
+/// The parameters are reset to their default when the firmware restarts.
+#[derive(Serialize, Deserialize, MaxSize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Param {
+    /// Maximum wheel speed, faster commands are clamped ([`CentiRpm`]).
+    RpmLimit,
+    /// Current above which the front left motor is derated ([`MilliAmps`]).
+    FlCurrentLimit,
+    /// Current above which the front right motor is derated ([`MilliAmps`]).
+    FrCurrentLimit,
+    /// Current above which the rear left motor is derated ([`MilliAmps`]).
+    RlCurrentLimit,
+    /// Current above which the rear right motor is derated ([`MilliAmps`]).
+    RrCurrentLimit,
+    /// Battery voltage below which the speed is limited ([`MilliVolts`]),
+    /// above the cutoff.
+    BatteryWarning,
+    /// Battery voltage below which the motors are disarmed ([`MilliVolts`]),
+    /// below the warning.
+    BatteryCutoff,
+    /// Number of readings averaged into each wheel speed, from 1 to 16.
+    SpeedOversampling,
+    /// Smoothing of the wheel speeds, from 0 (none) to 8; each step halves
+    /// the cutoff frequency.
+    SpeedFilter,
+    /// Ticks of the wheel encoders in a revolution of a wheel.
+    EncoderResolution,
+    /// Where the wheel speeds come from: 0 for the analog outputs of the
+    /// motor drivers, 1 for the wheel encoders.
+    SpeedSource,
+    /// Rate at which the IMU samples, from 4 to 1000 Hz.
+    ImuRate,
+    /// Wheel speed at full throttle on the RC transmitter ([`CentiRpm`]).
+    RcSpeed,
+    /// Distance to an obstacle at which the wheels are stopped towards it
+    /// ([`MilliMeters`]).
+    StopDistance,
+    /// Distance to an obstacle from which the speed towards it is limited,
+    /// down to zero at the stop distance ([`MilliMeters`]), not below the
+    /// stop distance.
+    SlowDistance,
+    /// Whether the speed of the slipping wheels is reduced: 0 for no, 1 for
+    /// yes.
+    TractionControl,
+    /// Slip above which a wheel is slipping ([`PerMille`]), from 0 to 1000.
+    SlipThreshold,
+    /// Whether the speed of the wheels is reduced when the yaw rate of the
+    /// IMU is far from the one of the steering: 0 for no, 1 for yes, only
+    /// with an IMU.
+    StabilityControl,
+}
+
+impl Param {
+    /// Every parameter.
+    pub const ALL: &'static [Param] = &[
+        Param::RpmLimit,
+        Param::FlCurrentLimit,
+        Param::FrCurrentLimit,
+        Param::RlCurrentLimit,
+        Param::RrCurrentLimit,
+        Param::BatteryWarning,
+        Param::BatteryCutoff,
+        Param::SpeedOversampling,
+        Param::SpeedFilter,
+        Param::EncoderResolution,
+        Param::SpeedSource,
+        Param::ImuRate,
+        Param::RcSpeed,
+        Param::StopDistance,
+        Param::SlowDistance,
+        Param::TractionControl,
+        Param::SlipThreshold,
+        Param::StabilityControl,
+    ];
+
+    /// Returns the name of the parameter.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Param::RpmLimit => "rpm_limit",
+            Param::FlCurrentLimit => "fl_current_limit",
+            Param::FrCurrentLimit => "fr_current_limit",
+            Param::RlCurrentLimit => "rl_current_limit",
+            Param::RrCurrentLimit => "rr_current_limit",
+            Param::BatteryWarning => "battery_warning",
+            Param::BatteryCutoff => "battery_cutoff",
+            Param::SpeedOversampling => "speed_oversampling",
+            Param::SpeedFilter => "speed_filter",
+            Param::EncoderResolution => "encoder_resolution",
+            Param::SpeedSource => "speed_source",
+            Param::ImuRate => "imu_rate",
+            Param::RcSpeed => "rc_speed",
+            Param::StopDistance => "stop_distance",
+            Param::SlowDistance => "slow_distance",
+            Param::TractionControl => "traction_control",
+            Param::SlipThreshold => "slip_threshold",
+            Param::StabilityControl => "stability_control",
+        }
+    }
+
+    /// Returns the parameter with this name.
+    pub fn from_name(name: &str) -> Option<Param> {
+        Self::ALL.iter().copied().find(|param| param.name() == name)
+    }
 }
 
 /// Serializes the message.
+#[allow(clippy::needless_lifetimes)]
 pub fn serialize<'a, 'b, M>(
     message: &'b M,
     buffer: &'a mut [u8],
@@ -83,3 +432,21 @@ where
     // the caller site.
     from_bytes_cobs(buffer)
 }
+
+/// Serializes the message to JSON.
+#[cfg(feature = "std")]
+pub fn to_json<M>(message: &M) -> Result<String, serde_json::Error>
+where
+    M: Serialize,
+{
+    serde_json::to_string(message)
+}
+
+/// Deserializes the message from JSON.
+#[cfg(feature = "std")]
+pub fn from_json<'a, M>(json: &'a str) -> Result<M, serde_json::Error>
+where
+    M: Deserialize<'a>,
+{
+    serde_json::from_str(json)
+}
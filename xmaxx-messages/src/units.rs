@@ -0,0 +1,223 @@
+//! Fixed-point physical units of the messages.
+//!
+//! The firmware has no floating-point unit, so the values are sent as
+//! integers in fractions of their unit.
+
+use core::f32::consts::PI;
+
+use postcard::experimental::max_size::MaxSize;
+use serde::{Deserialize, Serialize};
+
+/// The number of steps in a degree and in an RPM.
+const SCALE: f32 = 100.0;
+
+/// Rounds to the nearest integer, saturating at the bounds of `i32`.
+fn round(value: f32) -> i32 {
+    // `f32::round` is not available without std
+    if value < 0.0 {
+        (value - 0.5) as i32
+    } else {
+        (value + 0.5) as i32
+    }
+}
+
+/// Rounds to the nearest integer, saturating at the bounds of `i16`.
+fn round_i16(value: f32) -> i16 {
+    round(value).clamp(i16::MIN.into(), i16::MAX.into()) as i16
+}
+
+/// An angle in hundredths of a degree.
+#[derive(
+    Serialize, Deserialize, MaxSize, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub struct CentiDegrees(pub i32);
+
+impl CentiDegrees {
+    /// Returns the angle closest to `degrees`.
+    pub fn from_degrees(degrees: f32) -> Self {
+        Self(round(degrees * SCALE))
+    }
+
+    /// Returns the angle closest to `radians`.
+    pub fn from_radians(radians: f32) -> Self {
+        Self::from_degrees(radians.to_degrees())
+    }
+
+    /// Returns the angle in degrees.
+    pub fn degrees(self) -> f32 {
+        self.0 as f32 / SCALE
+    }
+
+    /// Returns the angle in radians.
+    pub fn radians(self) -> f32 {
+        self.degrees().to_radians()
+    }
+}
+
+/// A rotational speed in hundredths of a revolution per minute.
+#[derive(
+    Serialize, Deserialize, MaxSize, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub struct CentiRpm(pub i32);
+
+impl CentiRpm {
+    /// Returns the speed closest to `rpm`.
+    pub fn from_rpm(rpm: f32) -> Self {
+        Self(round(rpm * SCALE))
+    }
+
+    /// Returns the speed closest to `radians_per_second`.
+    pub fn from_radians_per_second(radians_per_second: f32) -> Self {
+        Self::from_rpm(radians_per_second * 60.0 / (2.0 * PI))
+    }
+
+    /// Returns the speed in revolutions per minute.
+    pub fn rpm(self) -> f32 {
+        self.0 as f32 / SCALE
+    }
+
+    /// Returns the speed in radians per second.
+    pub fn radians_per_second(self) -> f32 {
+        self.rpm() * 2.0 * PI / 60.0
+    }
+}
+
+/// An electric current in thousandths of an ampere.
+#[derive(
+    Serialize, Deserialize, MaxSize, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub struct MilliAmps(pub i32);
+
+impl MilliAmps {
+    /// Returns the current closest to `amps`.
+    pub fn from_amps(amps: f32) -> Self {
+        Self(round(amps * 1000.0))
+    }
+
+    /// Returns the current in amperes.
+    pub fn amps(self) -> f32 {
+        self.0 as f32 / 1000.0
+    }
+}
+
+/// An electric potential in thousandths of a volt.
+#[derive(
+    Serialize, Deserialize, MaxSize, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub struct MilliVolts(pub i32);
+
+impl MilliVolts {
+    /// Returns the voltage closest to `volts`.
+    pub fn from_volts(volts: f32) -> Self {
+        Self(round(volts * 1000.0))
+    }
+
+    /// Returns the voltage in volts.
+    pub fn volts(self) -> f32 {
+        self.0 as f32 / 1000.0
+    }
+}
+
+/// A distance in thousandths of a meter.
+#[derive(
+    Serialize, Deserialize, MaxSize, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub struct MilliMeters(pub i32);
+
+impl MilliMeters {
+    /// Returns the distance closest to `meters`.
+    pub fn from_meters(meters: f32) -> Self {
+        Self(round(meters * 1000.0))
+    }
+
+    /// Returns the distance in meters.
+    pub fn meters(self) -> f32 {
+        self.0 as f32 / 1000.0
+    }
+}
+
+/// A ratio in thousandths.
+///
+/// It only has 16 bits, up to ±32.7, so a ratio for each wheel fits in a
+/// frame.
+#[derive(
+    Serialize, Deserialize, MaxSize, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub struct PerMille(pub i16);
+
+impl PerMille {
+    /// Returns the ratio closest to `ratio`.
+    pub fn from_ratio(ratio: f32) -> Self {
+        Self(round_i16(ratio * 1000.0))
+    }
+
+    /// Returns the ratio.
+    pub fn ratio(self) -> f32 {
+        self.0 as f32 / 1000.0
+    }
+}
+
+/// An acceleration in thousandths of the standard gravity.
+///
+/// It only has 16 bits, up to ±32 g, so the readings of an IMU fit in a
+/// frame.
+#[derive(
+    Serialize, Deserialize, MaxSize, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub struct MilliG(pub i16);
+
+impl MilliG {
+    /// The standard gravity (m/s²).
+    const STANDARD_GRAVITY: f32 = 9.806_65;
+
+    /// Returns the acceleration closest to `g`.
+    pub fn from_g(g: f32) -> Self {
+        Self(round_i16(g * 1000.0))
+    }
+
+    /// Returns the acceleration closest to `meters_per_second_squared`.
+    pub fn from_meters_per_second_squared(meters_per_second_squared: f32) -> Self {
+        Self::from_g(meters_per_second_squared / Self::STANDARD_GRAVITY)
+    }
+
+    /// Returns the acceleration in standard gravities.
+    pub fn g(self) -> f32 {
+        self.0 as f32 / 1000.0
+    }
+
+    /// Returns the acceleration in meters per second squared.
+    pub fn meters_per_second_squared(self) -> f32 {
+        self.g() * Self::STANDARD_GRAVITY
+    }
+}
+
+/// An angular rate in hundredths of a degree per second.
+///
+/// It only has 16 bits, up to ±327 deg/s, so the readings of an IMU fit in
+/// a frame.
+#[derive(
+    Serialize, Deserialize, MaxSize, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub struct CentiDegreesPerSecond(pub i16);
+
+impl CentiDegreesPerSecond {
+    /// Returns the rate closest to `degrees_per_second`.
+    pub fn from_degrees_per_second(degrees_per_second: f32) -> Self {
+        Self(round_i16(degrees_per_second * SCALE))
+    }
+
+    /// Returns the rate closest to `radians_per_second`.
+    pub fn from_radians_per_second(radians_per_second: f32) -> Self {
+        Self::from_degrees_per_second(radians_per_second.to_degrees())
+    }
+
+    /// Returns the rate in degrees per second.
+    pub fn degrees_per_second(self) -> f32 {
+        self.0 as f32 / SCALE
+    }
+
+    /// Returns the rate in radians per second.
+    pub fn radians_per_second(self) -> f32 {
+        self.degrees_per_second().to_radians()
+    }
+}
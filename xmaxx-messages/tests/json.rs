@@ -0,0 +1,54 @@
+//! The messages in JSON, with the `std` feature.
+
+use proptest::prelude::*;
+
+use xmaxx_messages::*;
+
+proptest! {
+    #[test]
+    fn drive_roundtrips(steering in any::<i32>(), rpm in any::<i32>()) {
+        let command = Command::Drive(Drive {
+            steering: CentiDegrees(steering),
+            fl_whl_rpm: CentiRpm(rpm),
+            fr_whl_rpm: CentiRpm(-rpm / 2),
+            rl_whl_rpm: CentiRpm(rpm / 3),
+            rr_whl_rpm: CentiRpm(rpm / 4),
+        });
+
+        let json = to_json(&command).unwrap();
+        prop_assert_eq!(from_json::<Command>(&json).unwrap(), command);
+    }
+}
+
+#[test]
+fn info_is_readable() {
+    let info = Info::Param(Param::RpmLimit, 1000);
+
+    assert_eq!(to_json(&info).unwrap(), r#"{"Param":["RpmLimit",1000]}"#);
+    assert_eq!(
+        from_json::<Info>(r#"{"Log":"InvalidParam"}"#).unwrap(),
+        Info::Log(Log::InvalidParam)
+    );
+}
+
+#[test]
+fn messages_display() {
+    let drive = Command::Drive(Drive {
+        steering: CentiDegrees(9000),
+        fl_whl_rpm: CentiRpm(-123_456),
+        ..Default::default()
+    });
+
+    assert_eq!(
+        drive.to_string(),
+        "drive steering 90.00 deg, fl -1234.56 rpm, fr 0.00 rpm, rl 0.00 rpm, rr 0.00 rpm"
+    );
+    assert_eq!(
+        Info::Log(Log::ReadTimeout).to_string(),
+        "log timed out reading a command"
+    );
+    assert_eq!(
+        Command::SetParam(Param::RpmLimit, 1000).to_string(),
+        "set_param rpm_limit = 1000"
+    );
+}
@@ -0,0 +1,373 @@
+//! The messages are decoded as they were encoded, and their longest frame
+//! is exactly the size of the buffers of the firmware.
+
+use proptest::prelude::*;
+use proptest::sample::select;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use xmaxx_messages::*;
+
+/// Every log.
+const LOGS: &[fn() -> Log] = &[
+    || Log::SerializationError,
+    || Log::DeserializationError,
+    || Log::ReadBufferOverflow,
+    || Log::ReadTimeout,
+    || Log::FirmwarePanic,
+    || Log::InvalidCommand,
+    || Log::CommandReceived,
+    || Log::NoCommandReceived,
+    || Log::InvalidParam,
+    || Log::Overcurrent,
+    || Log::LowBattery,
+    || Log::BatteryCutoff,
+    || Log::ImuError,
+    || Log::RcSignalLost,
+    || Log::CollisionStop,
+];
+
+fn param() -> impl Strategy<Value = Param> {
+    select(Param::ALL)
+}
+
+fn log() -> impl Strategy<Value = Log> {
+    select(LOGS).prop_map(|log| log())
+}
+
+fn centi_degrees() -> impl Strategy<Value = CentiDegrees> {
+    any::<i32>().prop_map(CentiDegrees)
+}
+
+fn centi_rpm() -> impl Strategy<Value = CentiRpm> {
+    any::<i32>().prop_map(CentiRpm)
+}
+
+prop_compose! {
+    fn sensors()(
+        fl_whl_rpm in centi_rpm(),
+        fr_whl_rpm in centi_rpm(),
+        rl_whl_rpm in centi_rpm(),
+        rr_whl_rpm in centi_rpm(),
+    ) -> Sensors {
+        Sensors { fl_whl_rpm, fr_whl_rpm, rl_whl_rpm, rr_whl_rpm }
+    }
+}
+
+prop_compose! {
+    fn drive()(
+        steering in centi_degrees(),
+        fl_whl_rpm in centi_rpm(),
+        fr_whl_rpm in centi_rpm(),
+        rl_whl_rpm in centi_rpm(),
+        rr_whl_rpm in centi_rpm(),
+    ) -> Drive {
+        Drive { steering, fl_whl_rpm, fr_whl_rpm, rl_whl_rpm, rr_whl_rpm }
+    }
+}
+
+prop_compose! {
+    fn currents()(
+        fl in any::<i32>(),
+        fr in any::<i32>(),
+        rl in any::<i32>(),
+        rr in any::<i32>(),
+    ) -> Currents {
+        Currents { fl: MilliAmps(fl), fr: MilliAmps(fr), rl: MilliAmps(rl), rr: MilliAmps(rr) }
+    }
+}
+
+fn battery() -> impl Strategy<Value = Battery> {
+    any::<i32>().prop_map(|voltage| Battery {
+        voltage: MilliVolts(voltage),
+    })
+}
+
+prop_compose! {
+    fn encoders()(
+        fl in any::<i32>(),
+        fr in any::<i32>(),
+        rl in any::<i32>(),
+        rr in any::<i32>(),
+    ) -> Encoders {
+        Encoders { fl, fr, rl, rr }
+    }
+}
+
+prop_compose! {
+    fn imu()(
+        accel in any::<[i16; 3]>(),
+        gyro in any::<[i16; 3]>(),
+        yaw_rate in any::<i16>(),
+    ) -> Imu {
+        let [accel_x, accel_y, accel_z] = accel.map(MilliG);
+        let [gyro_x, gyro_y, gyro_z] = gyro.map(CentiDegreesPerSecond);
+        Imu {
+            accel_x,
+            accel_y,
+            accel_z,
+            gyro_x,
+            gyro_y,
+            gyro_z,
+            yaw_rate: CentiDegreesPerSecond(yaw_rate),
+        }
+    }
+}
+
+fn control() -> impl Strategy<Value = Control> {
+    let mode = select(&[ControlMode::Host, ControlMode::Rc, ControlMode::Auto][..]);
+    let source = select(
+        &[
+            ControlSource::Host,
+            ControlSource::Rc,
+            ControlSource::Stopped,
+        ][..],
+    );
+    (mode, source).prop_map(|(mode, source)| Control { mode, source })
+}
+
+fn proximity() -> impl Strategy<Value = Proximity> {
+    let distance = || prop::option::of(any::<i32>().prop_map(MilliMeters));
+    (distance(), distance()).prop_map(|(front, rear)| Proximity { front, rear })
+}
+
+prop_compose! {
+    fn traction()(
+        slips in any::<[i16; 4]>(),
+        active in any::<bool>(),
+    ) -> Traction {
+        let [fl, fr, rl, rr] = slips.map(PerMille);
+        Traction { fl, fr, rl, rr, active }
+    }
+}
+
+prop_compose! {
+    fn odometry()(
+        time in any::<u32>(),
+        position in any::<[i32; 2]>(),
+        heading in any::<i32>(),
+        distance in any::<i32>(),
+    ) -> Odometry {
+        let [x, y] = position.map(MilliMeters);
+        Odometry {
+            time,
+            x,
+            y,
+            heading: CentiDegrees(heading),
+            distance: MilliMeters(distance),
+        }
+    }
+}
+
+fn drive_mode() -> impl Strategy<Value = DriveMode> {
+    select(
+        &[
+            DriveMode::AllWheel,
+            DriveMode::FrontWheel,
+            DriveMode::RearWheel,
+            DriveMode::Coast,
+        ][..],
+    )
+}
+
+prop_compose! {
+    fn axles()(
+        mode in drive_mode(),
+        front in any::<bool>(),
+        rear in any::<bool>(),
+    ) -> Axles {
+        Axles { mode, front, rear }
+    }
+}
+
+fn info() -> impl Strategy<Value = Info> {
+    prop_oneof![
+        sensors().prop_map(Info::Sensors),
+        log().prop_map(Info::Log),
+        any::<u32>().prop_map(Info::Pong),
+        (param(), any::<i32>()).prop_map(|(param, value)| Info::Param(param, value)),
+        currents().prop_map(Info::Currents),
+        battery().prop_map(Info::Battery),
+        encoders().prop_map(Info::Encoders),
+        imu().prop_map(Info::Imu),
+        control().prop_map(Info::Control),
+        proximity().prop_map(Info::Proximity),
+        traction().prop_map(Info::Traction),
+        odometry().prop_map(Info::Odometry),
+        axles().prop_map(Info::Axles),
+    ]
+}
+
+fn command() -> impl Strategy<Value = Command> {
+    prop_oneof![
+        drive().prop_map(Command::Drive),
+        any::<u32>().prop_map(Command::Ping),
+        param().prop_map(Command::GetParam),
+        (param(), any::<i32>()).prop_map(|(param, value)| Command::SetParam(param, value)),
+        Just(Command::ResetOdometry),
+        drive_mode().prop_map(Command::SetDriveMode),
+    ]
+}
+
+/// Encodes the message in a buffer of `N` bytes and decodes it back.
+fn roundtrip<M, const N: usize>(message: &M) -> M
+where
+    M: Serialize + DeserializeOwned,
+{
+    let mut buffer = [0u8; N];
+    let encoded = serialize(message, &mut buffer).expect("the message fits the buffer");
+    deserialize(encoded).expect("the message decodes")
+}
+
+proptest! {
+    #[test]
+    fn info_roundtrips(info in info()) {
+        prop_assert_eq!(roundtrip::<_, { Info::MAX_SERIAL_SIZE }>(&info), info);
+    }
+
+    #[test]
+    fn command_roundtrips(command in command()) {
+        prop_assert_eq!(roundtrip::<_, { Command::MAX_SERIAL_SIZE }>(&command), command);
+    }
+
+    #[test]
+    fn sensors_roundtrip(sensors in sensors()) {
+        prop_assert_eq!(roundtrip::<_, 64>(&sensors), sensors);
+    }
+
+    #[test]
+    fn drive_roundtrips(drive in drive()) {
+        prop_assert_eq!(roundtrip::<_, 64>(&drive), drive);
+    }
+
+    #[test]
+    fn log_roundtrips(log in log()) {
+        prop_assert_eq!(roundtrip::<_, 64>(&log), log);
+    }
+
+    #[test]
+    fn param_roundtrips(param in param()) {
+        prop_assert_eq!(roundtrip::<_, 64>(&param), param);
+    }
+
+    #[test]
+    fn garbage_does_not_panic(mut bytes in prop::collection::vec(any::<u8>(), 0..64)) {
+        let _ = deserialize::<Info>(&mut bytes.clone());
+        let _ = deserialize::<Command>(&mut bytes);
+    }
+}
+
+/// Returns the length of the longest frame of the messages, with its null
+/// byte.
+fn max_encoded_len<M: Serialize>(messages: &[M]) -> usize {
+    messages
+        .iter()
+        .map(|message| {
+            let mut buffer = [0u8; 256];
+            serialize(message, &mut buffer).unwrap().len()
+        })
+        .max()
+        .unwrap()
+}
+
+/// The declared size is the longest frame, so the buffers are tight.
+#[test]
+fn worst_case_info_fits() {
+    // the values are encoded as varints, so the extremes are the longest
+    let worst = i32::MIN;
+    let mut infos = vec![
+        Info::Sensors(Sensors {
+            fl_whl_rpm: CentiRpm(worst),
+            fr_whl_rpm: CentiRpm(worst),
+            rl_whl_rpm: CentiRpm(worst),
+            rr_whl_rpm: CentiRpm(worst),
+        }),
+        Info::Pong(u32::MAX),
+        Info::Currents(Currents {
+            fl: MilliAmps(worst),
+            fr: MilliAmps(worst),
+            rl: MilliAmps(worst),
+            rr: MilliAmps(worst),
+        }),
+        Info::Battery(Battery {
+            voltage: MilliVolts(worst),
+        }),
+        Info::Encoders(Encoders {
+            fl: worst,
+            fr: worst,
+            rl: worst,
+            rr: worst,
+        }),
+        Info::Imu(Imu {
+            accel_x: MilliG(i16::MIN),
+            accel_y: MilliG(i16::MIN),
+            accel_z: MilliG(i16::MIN),
+            gyro_x: CentiDegreesPerSecond(i16::MIN),
+            gyro_y: CentiDegreesPerSecond(i16::MIN),
+            gyro_z: CentiDegreesPerSecond(i16::MIN),
+            yaw_rate: CentiDegreesPerSecond(i16::MIN),
+        }),
+        Info::Control(Control {
+            mode: ControlMode::Auto,
+            source: ControlSource::Stopped,
+        }),
+        Info::Proximity(Proximity {
+            front: Some(MilliMeters(worst)),
+            rear: Some(MilliMeters(worst)),
+        }),
+        Info::Traction(Traction {
+            fl: PerMille(i16::MIN),
+            fr: PerMille(i16::MIN),
+            rl: PerMille(i16::MIN),
+            rr: PerMille(i16::MIN),
+            active: true,
+        }),
+        Info::Odometry(Odometry {
+            time: u32::MAX,
+            x: MilliMeters(worst),
+            y: MilliMeters(worst),
+            heading: CentiDegrees(worst),
+            distance: MilliMeters(worst),
+        }),
+        Info::Axles(Axles {
+            mode: DriveMode::Coast,
+            front: true,
+            rear: true,
+        }),
+    ];
+    infos.extend(LOGS.iter().map(|log| Info::Log(log())));
+    infos.extend(Param::ALL.iter().map(|param| Info::Param(*param, worst)));
+
+    let len = max_encoded_len(&infos);
+    assert!(
+        len <= Info::MAX_SERIAL_SIZE,
+        "{len} > {}",
+        Info::MAX_SERIAL_SIZE
+    );
+}
+
+#[test]
+fn worst_case_command_fits() {
+    let worst = i32::MIN;
+    let mut commands = vec![
+        Command::Drive(Drive {
+            steering: CentiDegrees(worst),
+            fl_whl_rpm: CentiRpm(worst),
+            fr_whl_rpm: CentiRpm(worst),
+            rl_whl_rpm: CentiRpm(worst),
+            rr_whl_rpm: CentiRpm(worst),
+        }),
+        Command::Ping(u32::MAX),
+        Command::ResetOdometry,
+        Command::SetDriveMode(DriveMode::Coast),
+    ];
+    commands.extend(Param::ALL.iter().map(|param| Command::GetParam(*param)));
+    commands.extend(
+        Param::ALL
+            .iter()
+            .map(|param| Command::SetParam(*param, worst)),
+    );
+
+    let len = max_encoded_len(&commands);
+    assert_eq!(len, Command::MAX_SERIAL_SIZE);
+}
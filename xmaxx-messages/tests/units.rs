@@ -0,0 +1,71 @@
+//! The conversions of the units to and from floats.
+
+use std::f32::consts::PI;
+
+use xmaxx_messages::*;
+
+#[test]
+fn degrees_are_rounded_to_the_nearest_hundredth() {
+    assert_eq!(CentiDegrees::from_degrees(90.0), CentiDegrees(9000));
+    assert_eq!(CentiDegrees::from_degrees(12.345_6), CentiDegrees(1235));
+    assert_eq!(CentiDegrees::from_degrees(-12.345_6), CentiDegrees(-1235));
+    assert_eq!(CentiDegrees(-1235).degrees(), -12.35);
+}
+
+#[test]
+fn radians_convert_to_degrees() {
+    assert_eq!(CentiDegrees::from_radians(PI / 2.0), CentiDegrees(9000));
+    assert!((CentiDegrees(18000).radians() - PI).abs() < 1e-6);
+}
+
+#[test]
+fn rpm_convert_to_radians_per_second() {
+    assert_eq!(CentiRpm::from_rpm(4500.0), CentiRpm(450_000));
+    assert_eq!(
+        CentiRpm::from_radians_per_second(-2.0 * PI),
+        CentiRpm(-6000)
+    );
+    assert!((CentiRpm(6000).radians_per_second() - 2.0 * PI).abs() < 1e-6);
+}
+
+#[test]
+fn out_of_range_values_saturate() {
+    assert_eq!(CentiRpm::from_rpm(f32::MAX), CentiRpm(i32::MAX));
+    assert_eq!(CentiRpm::from_rpm(f32::MIN), CentiRpm(i32::MIN));
+}
+
+#[test]
+fn amps_volts_and_meters_are_rounded_to_the_nearest_thousandth() {
+    assert_eq!(MilliAmps::from_amps(-1.234_56), MilliAmps(-1235));
+    assert_eq!(MilliAmps(1500).amps(), 1.5);
+    assert_eq!(MilliVolts::from_volts(25.2), MilliVolts(25_200));
+    assert_eq!(MilliVolts(3300).volts(), 3.3);
+    assert_eq!(MilliMeters::from_meters(1.234_56), MilliMeters(1235));
+    assert_eq!(MilliMeters(300).meters(), 0.3);
+}
+
+#[test]
+fn ratios_saturate_at_16_bits() {
+    assert_eq!(PerMille::from_ratio(0.123_45), PerMille(123));
+    assert_eq!(PerMille(-250).ratio(), -0.25);
+    assert_eq!(PerMille::from_ratio(40.0), PerMille(i16::MAX));
+}
+
+#[test]
+fn imu_units_saturate_at_16_bits() {
+    assert_eq!(MilliG::from_g(-1.234_56), MilliG(-1235));
+    assert_eq!(
+        MilliG::from_meters_per_second_squared(9.806_65),
+        MilliG(1000)
+    );
+    assert_eq!(MilliG::from_g(100.0), MilliG(i16::MAX));
+    assert_eq!(
+        CentiDegreesPerSecond::from_degrees_per_second(-12.345_6),
+        CentiDegreesPerSecond(-1235)
+    );
+    assert_eq!(
+        CentiDegreesPerSecond::from_degrees_per_second(-1000.0),
+        CentiDegreesPerSecond(i16::MIN)
+    );
+    assert!((CentiDegreesPerSecond(9000).radians_per_second() - PI / 2.0).abs() < 1e-6);
+}
@@ -1,167 +1,1300 @@
 #![doc = include_str!("../README.md")]
+#![allow(non_local_definitions)] // raised by the pyo3 macros
 
-use std::thread;
 use std::time::Duration;
 
-use pyo3::exceptions::PyException;
+use pyo3::exceptions::{PyException, PyValueError};
 use pyo3::prelude::*;
 
-use serialport;
-use serialport::SerialPort;
-
+use xmaxx_host::*;
 use xmaxx_messages::*;
 
-/// A command to be sent to the firmware.
-#[pyclass(name = "Command")]
-struct PyCommand {
-    steering: i32,
-    fl_whl_rpm: i32,
-    fr_whl_rpm: i32,
-    rl_whl_rpm: i32,
-    rr_whl_rpm: i32,
+/// An angle in hundredths of a degree, as sent to the firmware.
+///
+/// >>> CentiDegrees.from_degrees(90.0)
+/// CentiDegrees(9000)
+#[pyclass(name = "CentiDegrees")]
+#[derive(Clone, Copy)]
+struct PyCentiDegrees(CentiDegrees);
+
+#[pymethods]
+impl PyCentiDegrees {
+    #[new]
+    fn new(value: i32) -> Self {
+        Self(CentiDegrees(value))
+    }
+
+    /// Returns the angle closest to `degrees`.
+    #[staticmethod]
+    fn from_degrees(degrees: f32) -> Self {
+        Self(CentiDegrees::from_degrees(degrees))
+    }
+
+    /// Returns the angle closest to `radians`.
+    #[staticmethod]
+    fn from_radians(radians: f32) -> Self {
+        Self(CentiDegrees::from_radians(radians))
+    }
+
+    /// The angle in hundredths of a degree.
+    #[getter]
+    fn value(&self) -> i32 {
+        self.0 .0
+    }
+
+    /// The angle in degrees.
+    #[getter]
+    fn degrees(&self) -> f32 {
+        self.0.degrees()
+    }
+
+    /// The angle in radians.
+    #[getter]
+    fn radians(&self) -> f32 {
+        self.0.radians()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// A rotational speed in hundredths of a revolution per minute, as sent to
+/// and by the firmware.
+///
+/// >>> CentiRpm.from_rpm(500.0)
+/// CentiRpm(50000)
+#[pyclass(name = "CentiRpm")]
+#[derive(Clone, Copy)]
+struct PyCentiRpm(CentiRpm);
+
+#[pymethods]
+impl PyCentiRpm {
+    #[new]
+    fn new(value: i32) -> Self {
+        Self(CentiRpm(value))
+    }
+
+    /// Returns the speed closest to `rpm`.
+    #[staticmethod]
+    fn from_rpm(rpm: f32) -> Self {
+        Self(CentiRpm::from_rpm(rpm))
+    }
+
+    /// Returns the speed closest to `radians_per_second`.
+    #[staticmethod]
+    fn from_radians_per_second(radians_per_second: f32) -> Self {
+        Self(CentiRpm::from_radians_per_second(radians_per_second))
+    }
+
+    /// The speed in hundredths of RPM.
+    #[getter]
+    fn value(&self) -> i32 {
+        self.0 .0
+    }
+
+    /// The speed in revolutions per minute.
+    #[getter]
+    fn rpm(&self) -> f32 {
+        self.0.rpm()
+    }
+
+    /// The speed in radians per second.
+    #[getter]
+    fn radians_per_second(&self) -> f32 {
+        self.0.radians_per_second()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// An electric current in thousandths of an ampere, as sent by the
+/// firmware.
+///
+/// >>> MilliAmps.from_amps(1.5)
+/// MilliAmps(1500)
+#[pyclass(name = "MilliAmps")]
+#[derive(Clone, Copy)]
+struct PyMilliAmps(MilliAmps);
+
+#[pymethods]
+impl PyMilliAmps {
+    #[new]
+    fn new(value: i32) -> Self {
+        Self(MilliAmps(value))
+    }
+
+    /// Returns the current closest to `amps`.
+    #[staticmethod]
+    fn from_amps(amps: f32) -> Self {
+        Self(MilliAmps::from_amps(amps))
+    }
+
+    /// The current in thousandths of an ampere.
+    #[getter]
+    fn value(&self) -> i32 {
+        self.0 .0
+    }
+
+    /// The current in amperes.
+    #[getter]
+    fn amps(&self) -> f32 {
+        self.0.amps()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// An electric potential in thousandths of a volt, as sent by the
+/// firmware.
+///
+/// >>> MilliVolts.from_volts(25.2)
+/// MilliVolts(25200)
+#[pyclass(name = "MilliVolts")]
+#[derive(Clone, Copy)]
+struct PyMilliVolts(MilliVolts);
+
+#[pymethods]
+impl PyMilliVolts {
+    #[new]
+    fn new(value: i32) -> Self {
+        Self(MilliVolts(value))
+    }
+
+    /// Returns the voltage closest to `volts`.
+    #[staticmethod]
+    fn from_volts(volts: f32) -> Self {
+        Self(MilliVolts::from_volts(volts))
+    }
+
+    /// The voltage in thousandths of a volt.
+    #[getter]
+    fn value(&self) -> i32 {
+        self.0 .0
+    }
+
+    /// The voltage in volts.
+    #[getter]
+    fn volts(&self) -> f32 {
+        self.0.volts()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// An acceleration in thousandths of the standard gravity, as sent by the
+/// firmware.
+///
+/// >>> MilliG.from_g(1.0)
+/// MilliG(1000)
+#[pyclass(name = "MilliG")]
+#[derive(Clone, Copy)]
+struct PyMilliG(MilliG);
+
+#[pymethods]
+impl PyMilliG {
+    #[new]
+    fn new(value: i16) -> Self {
+        Self(MilliG(value))
+    }
+
+    /// Returns the acceleration closest to `g`.
+    #[staticmethod]
+    fn from_g(g: f32) -> Self {
+        Self(MilliG::from_g(g))
+    }
+
+    /// Returns the acceleration closest to `meters_per_second_squared`.
+    #[staticmethod]
+    fn from_meters_per_second_squared(meters_per_second_squared: f32) -> Self {
+        Self(MilliG::from_meters_per_second_squared(
+            meters_per_second_squared,
+        ))
+    }
+
+    /// The acceleration in thousandths of the standard gravity.
+    #[getter]
+    fn value(&self) -> i16 {
+        self.0 .0
+    }
+
+    /// The acceleration in standard gravities.
+    #[getter]
+    fn g(&self) -> f32 {
+        self.0.g()
+    }
+
+    /// The acceleration in meters per second squared.
+    #[getter]
+    fn meters_per_second_squared(&self) -> f32 {
+        self.0.meters_per_second_squared()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// A distance in millimeters, as sent by the firmware.
+///
+/// >>> MilliMeters.from_meters(1.5)
+/// MilliMeters(1500)
+#[pyclass(name = "MilliMeters")]
+#[derive(Clone, Copy)]
+struct PyMilliMeters(MilliMeters);
+
+#[pymethods]
+impl PyMilliMeters {
+    #[new]
+    fn new(value: i32) -> Self {
+        Self(MilliMeters(value))
+    }
+
+    /// Returns the distance closest to `meters`.
+    #[staticmethod]
+    fn from_meters(meters: f32) -> Self {
+        Self(MilliMeters::from_meters(meters))
+    }
+
+    /// The distance in millimeters.
+    #[getter]
+    fn value(&self) -> i32 {
+        self.0 .0
+    }
+
+    /// The distance in meters.
+    #[getter]
+    fn meters(&self) -> f32 {
+        self.0.meters()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// A ratio in thousandths, as sent by the firmware.
+///
+/// >>> PerMille.from_ratio(0.25)
+/// PerMille(250)
+#[pyclass(name = "PerMille")]
+#[derive(Clone, Copy)]
+struct PyPerMille(PerMille);
+
+#[pymethods]
+impl PyPerMille {
+    #[new]
+    fn new(value: i16) -> Self {
+        Self(PerMille(value))
+    }
+
+    /// Returns the ratio closest to `ratio`.
+    #[staticmethod]
+    fn from_ratio(ratio: f32) -> Self {
+        Self(PerMille::from_ratio(ratio))
+    }
+
+    /// The ratio in thousandths.
+    #[getter]
+    fn value(&self) -> i16 {
+        self.0 .0
+    }
+
+    /// The ratio.
+    #[getter]
+    fn ratio(&self) -> f32 {
+        self.0.ratio()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// An angular rate in hundredths of a degree per second, as sent by the
+/// firmware.
+///
+/// >>> CentiDegreesPerSecond.from_degrees_per_second(90.0)
+/// CentiDegreesPerSecond(9000)
+#[pyclass(name = "CentiDegreesPerSecond")]
+#[derive(Clone, Copy)]
+struct PyCentiDegreesPerSecond(CentiDegreesPerSecond);
+
+#[pymethods]
+impl PyCentiDegreesPerSecond {
+    #[new]
+    fn new(value: i16) -> Self {
+        Self(CentiDegreesPerSecond(value))
+    }
+
+    /// Returns the rate closest to `degrees_per_second`.
+    #[staticmethod]
+    fn from_degrees_per_second(degrees_per_second: f32) -> Self {
+        Self(CentiDegreesPerSecond::from_degrees_per_second(
+            degrees_per_second,
+        ))
+    }
+
+    /// Returns the rate closest to `radians_per_second`.
+    #[staticmethod]
+    fn from_radians_per_second(radians_per_second: f32) -> Self {
+        Self(CentiDegreesPerSecond::from_radians_per_second(
+            radians_per_second,
+        ))
+    }
+
+    /// The rate in hundredths of a degree per second.
+    #[getter]
+    fn value(&self) -> i16 {
+        self.0 .0
+    }
+
+    /// The rate in degrees per second.
+    #[getter]
+    fn degrees_per_second(&self) -> f32 {
+        self.0.degrees_per_second()
+    }
+
+    /// The rate in radians per second.
+    #[getter]
+    fn radians_per_second(&self) -> f32 {
+        self.0.radians_per_second()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// A command to be sent to the firmware.
+#[pyclass(name = "Command")]
+#[derive(Clone)]
+struct PyCommand(Drive);
+
+#[pymethods]
+impl PyCommand {
+    #[new]
+    fn new(
+        steering: PyCentiDegrees,
+        fl_whl_rpm: PyCentiRpm,
+        fr_whl_rpm: PyCentiRpm,
+        rl_whl_rpm: PyCentiRpm,
+        rr_whl_rpm: PyCentiRpm,
+    ) -> Self {
+        Self(Drive {
+            steering: steering.0,
+            fl_whl_rpm: fl_whl_rpm.0,
+            fr_whl_rpm: fr_whl_rpm.0,
+            rl_whl_rpm: rl_whl_rpm.0,
+            rr_whl_rpm: rr_whl_rpm.0,
+        })
+    }
+
+    /// Returns the command with the steering in degrees and the speeds in
+    /// RPM.
+    #[staticmethod]
+    fn from_degrees_and_rpm(
+        steering: f32,
+        fl_whl_rpm: f32,
+        fr_whl_rpm: f32,
+        rl_whl_rpm: f32,
+        rr_whl_rpm: f32,
+    ) -> Self {
+        Self::new(
+            PyCentiDegrees::from_degrees(steering),
+            PyCentiRpm::from_rpm(fl_whl_rpm),
+            PyCentiRpm::from_rpm(fr_whl_rpm),
+            PyCentiRpm::from_rpm(rl_whl_rpm),
+            PyCentiRpm::from_rpm(rr_whl_rpm),
+        )
+    }
+
+    /// The angle of the steering, 90 degrees is straight.
+    #[getter]
+    fn steering(&self) -> PyCentiDegrees {
+        PyCentiDegrees(self.0.steering)
+    }
+
+    /// Front left wheel speed.
+    #[getter]
+    fn fl_whl_rpm(&self) -> PyCentiRpm {
+        PyCentiRpm(self.0.fl_whl_rpm)
+    }
+
+    /// Front right wheel speed.
+    #[getter]
+    fn fr_whl_rpm(&self) -> PyCentiRpm {
+        PyCentiRpm(self.0.fr_whl_rpm)
+    }
+
+    /// Rear left wheel speed.
+    #[getter]
+    fn rl_whl_rpm(&self) -> PyCentiRpm {
+        PyCentiRpm(self.0.rl_whl_rpm)
+    }
+
+    /// Rear right wheel speed.
+    #[getter]
+    fn rr_whl_rpm(&self) -> PyCentiRpm {
+        PyCentiRpm(self.0.rr_whl_rpm)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// Wrapper type around [`Info`].
+///
+/// It is not a Python object but it converts to thirteen: [`PySensors`],
+/// [`PyLog`], [`PyPong`], [`PyParam`], [`PyCurrents`], [`PyBattery`],
+/// [`PyEncoders`], [`PyImu`], [`PyControl`], [`PyProximity`],
+/// [`PyTraction`], [`PyOdometry`] and [`PyAxles`]. A Python function
+/// returning this types can be annotated with
+/// `Union[Sensors, Log, Pong, Param, Currents, Battery, Encoders, Imu, Control, Proximity, Traction, Odometry, Axles]`.
+struct PyInfo(Info);
+
+impl IntoPy<PyObject> for PyInfo {
+    fn into_py(self, py: Python) -> PyObject {
+        match self.0 {
+            Info::Sensors(sensors) => PySensors(sensors).into_py(py),
+            Info::Log(log) => PyLog(log).into_py(py),
+            Info::Pong(id) => PyPong(id).into_py(py),
+            Info::Param(param, value) => PyParam(param, value).into_py(py),
+            Info::Currents(currents) => PyCurrents(currents).into_py(py),
+            Info::Battery(battery) => PyBattery(battery).into_py(py),
+            Info::Encoders(encoders) => PyEncoders(encoders).into_py(py),
+            Info::Imu(imu) => PyImu(imu).into_py(py),
+            Info::Control(control) => PyControl(control).into_py(py),
+            Info::Proximity(proximity) => PyProximity(proximity).into_py(py),
+            Info::Traction(traction) => PyTraction(traction).into_py(py),
+            Info::Odometry(odometry) => PyOdometry(odometry).into_py(py),
+            Info::Axles(axles) => PyAxles(axles).into_py(py),
+        }
+    }
+}
+
+/// The answer of the firmware to a ping.
+#[pyclass(name = "Pong")]
+struct PyPong(u32);
+
+#[pymethods]
+impl PyPong {
+    /// The id of the ping.
+    #[getter]
+    fn id(&self) -> u32 {
+        self.0
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", Info::Pong(self.0))
+    }
+
+    fn __str__(&self) -> String {
+        Info::Pong(self.0).to_string()
+    }
+}
+
+/// The value of a parameter of the firmware.
+#[pyclass(name = "Param")]
+struct PyParam(Param, i32);
+
+#[pymethods]
+impl PyParam {
+    /// The name of the parameter.
+    #[getter]
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    /// The value of the parameter.
+    #[getter]
+    fn value(&self) -> i32 {
+        self.1
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", Info::Param(self.0, self.1))
+    }
+
+    fn __str__(&self) -> String {
+        Info::Param(self.0, self.1).to_string()
+    }
+}
+
+/// Returns the parameter with this name or raises a `ValueError`.
+fn param(name: &str) -> PyResult<Param> {
+    Param::from_name(name)
+        .ok_or_else(|| PyValueError::new_err(format!("unknown parameter: {name}")))
+}
+
+/// Sensor information from the firmware.
+#[pyclass(name = "Sensors")]
+struct PySensors(Sensors);
+
+#[pymethods]
+impl PySensors {
+    /// Front left wheel speed.
+    #[getter]
+    fn fl_whl_rpm(&self) -> PyCentiRpm {
+        PyCentiRpm(self.0.fl_whl_rpm)
+    }
+
+    /// Front right wheel speed.
+    #[getter]
+    fn fr_whl_rpm(&self) -> PyCentiRpm {
+        PyCentiRpm(self.0.fr_whl_rpm)
+    }
+
+    /// Rear left wheel speed.
+    #[getter]
+    fn rl_whl_rpm(&self) -> PyCentiRpm {
+        PyCentiRpm(self.0.rl_whl_rpm)
+    }
+
+    /// Rear right wheel speed.
+    #[getter]
+    fn rr_whl_rpm(&self) -> PyCentiRpm {
+        PyCentiRpm(self.0.rr_whl_rpm)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// Currents drawn by the motors.
+#[pyclass(name = "Currents")]
+struct PyCurrents(Currents);
+
+#[pymethods]
+impl PyCurrents {
+    /// Front left motor current.
+    #[getter]
+    fn fl(&self) -> PyMilliAmps {
+        PyMilliAmps(self.0.fl)
+    }
+
+    /// Front right motor current.
+    #[getter]
+    fn fr(&self) -> PyMilliAmps {
+        PyMilliAmps(self.0.fr)
+    }
+
+    /// Rear left motor current.
+    #[getter]
+    fn rl(&self) -> PyMilliAmps {
+        PyMilliAmps(self.0.rl)
+    }
+
+    /// Rear right motor current.
+    #[getter]
+    fn rr(&self) -> PyMilliAmps {
+        PyMilliAmps(self.0.rr)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// State of the battery pack.
+#[pyclass(name = "Battery")]
+struct PyBattery(Battery);
+
+#[pymethods]
+impl PyBattery {
+    /// Voltage of the pack.
+    #[getter]
+    fn voltage(&self) -> PyMilliVolts {
+        PyMilliVolts(self.0.voltage)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// Ticks counted by the wheel encoders since the firmware started.
+///
+/// The counts wrap around at the bounds of a 32 bits integer.
+#[pyclass(name = "Encoders")]
+struct PyEncoders(Encoders);
+
+#[pymethods]
+impl PyEncoders {
+    /// Front left wheel ticks.
+    #[getter]
+    fn fl(&self) -> i32 {
+        self.0.fl
+    }
+
+    /// Front right wheel ticks.
+    #[getter]
+    fn fr(&self) -> i32 {
+        self.0.fr
+    }
+
+    /// Rear left wheel ticks.
+    #[getter]
+    fn rl(&self) -> i32 {
+        self.0.rl
+    }
+
+    /// Rear right wheel ticks.
+    #[getter]
+    fn rr(&self) -> i32 {
+        self.0.rr
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// Readings of the inertial measurement unit.
+///
+/// The axes are those of the IMU, mounted with x forwards, y to the left
+/// and z up.
+#[pyclass(name = "Imu")]
+struct PyImu(Imu);
+
+#[pymethods]
+impl PyImu {
+    /// Acceleration along x, forwards.
+    #[getter]
+    fn accel_x(&self) -> PyMilliG {
+        PyMilliG(self.0.accel_x)
+    }
+
+    /// Acceleration along y, to the left.
+    #[getter]
+    fn accel_y(&self) -> PyMilliG {
+        PyMilliG(self.0.accel_y)
+    }
+
+    /// Acceleration along z, up; about 1 g at rest.
+    #[getter]
+    fn accel_z(&self) -> PyMilliG {
+        PyMilliG(self.0.accel_z)
+    }
+
+    /// Rotation around x, rolling to the right.
+    #[getter]
+    fn gyro_x(&self) -> PyCentiDegreesPerSecond {
+        PyCentiDegreesPerSecond(self.0.gyro_x)
+    }
+
+    /// Rotation around y, pitching down.
+    #[getter]
+    fn gyro_y(&self) -> PyCentiDegreesPerSecond {
+        PyCentiDegreesPerSecond(self.0.gyro_y)
+    }
+
+    /// Rotation around z, turning left.
+    #[getter]
+    fn gyro_z(&self) -> PyCentiDegreesPerSecond {
+        PyCentiDegreesPerSecond(self.0.gyro_z)
+    }
+
+    /// Rotation around z without the bias of the gyroscope, which is
+    /// measured while the Xmaxx is at rest.
+    #[getter]
+    fn yaw_rate(&self) -> PyCentiDegreesPerSecond {
+        PyCentiDegreesPerSecond(self.0.yaw_rate)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// Who drives the Xmaxx.
+#[pyclass(name = "Control")]
+struct PyControl(Control);
+
+#[pymethods]
+impl PyControl {
+    /// Mode selected on the RC transmitter.
+    #[getter]
+    fn mode(&self) -> PyControlMode {
+        PyControlMode(self.0.mode)
+    }
+
+    /// Source of the command followed.
+    #[getter]
+    fn source(&self) -> PyControlSource {
+        PyControlSource(self.0.source)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// Distances to the nearest obstacles measured by the range sensors.
+#[pyclass(name = "Proximity")]
+struct PyProximity(Proximity);
+
+#[pymethods]
+impl PyProximity {
+    /// Distance in front, or None if nothing is in range.
+    #[getter]
+    fn front(&self) -> Option<PyMilliMeters> {
+        self.0.front.map(PyMilliMeters)
+    }
+
+    /// Distance behind, or None if nothing is in range.
+    #[getter]
+    fn rear(&self) -> Option<PyMilliMeters> {
+        self.0.rear.map(PyMilliMeters)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// Slip of the wheels and state of the traction control.
+///
+/// The slip of a wheel is how much faster it turns than the ground goes
+/// under it, relative to its speed: 0 when it rolls, 1 when it spins in
+/// place.
+#[pyclass(name = "Traction")]
+struct PyTraction(Traction);
+
+#[pymethods]
+impl PyTraction {
+    /// Front left wheel slip.
+    #[getter]
+    fn fl(&self) -> PyPerMille {
+        PyPerMille(self.0.fl)
+    }
+
+    /// Front right wheel slip.
+    #[getter]
+    fn fr(&self) -> PyPerMille {
+        PyPerMille(self.0.fr)
+    }
+
+    /// Rear left wheel slip.
+    #[getter]
+    fn rl(&self) -> PyPerMille {
+        PyPerMille(self.0.rl)
+    }
+
+    /// Rear right wheel slip.
+    #[getter]
+    fn rr(&self) -> PyPerMille {
+        PyPerMille(self.0.rr)
+    }
+
+    /// Whether the traction control reduces the speed of a slipping wheel.
+    #[getter]
+    fn active(&self) -> bool {
+        self.0.active
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
 }
 
+/// Pose of the Xmaxx dead-reckoned by the firmware from the speeds of its
+/// rear wheels and the steering, since it started or the last
+/// `Firmware.reset_odometry()`.
+///
+/// The pose is of the center of the rear axle, in the frame of the pose at
+/// the reset: x forwards and y to the left.
+#[pyclass(name = "Odometry")]
+struct PyOdometry(Odometry);
+
 #[pymethods]
-impl PyCommand {
-    #[new]
-    fn new(
-        steering: i32,
-        fl_whl_rpm: i32,
-        fr_whl_rpm: i32,
-        rl_whl_rpm: i32,
-        rr_whl_rpm: i32,
-    ) -> Self {
-        Self {
-            steering,
-            fl_whl_rpm,
-            fr_whl_rpm,
-            rl_whl_rpm,
-            rr_whl_rpm,
-        }
+impl PyOdometry {
+    /// Time of the pose on the clock of the firmware (ms).
+    #[getter]
+    fn time(&self) -> u32 {
+        self.0.time
+    }
+
+    /// Position along x, forwards.
+    #[getter]
+    fn x(&self) -> PyMilliMeters {
+        PyMilliMeters(self.0.x)
+    }
+
+    /// Position along y, to the left.
+    #[getter]
+    fn y(&self) -> PyMilliMeters {
+        PyMilliMeters(self.0.y)
+    }
+
+    /// Heading counterclockwise from x, from -180 to 180 deg.
+    #[getter]
+    fn heading(&self) -> PyCentiDegrees {
+        PyCentiDegrees(self.0.heading)
+    }
+
+    /// Distance traveled, forwards and backwards.
+    #[getter]
+    fn distance(&self) -> PyMilliMeters {
+        PyMilliMeters(self.0.distance)
     }
 
     fn __repr__(&self) -> String {
-        format!(
-            "Command(steering={}, fl_whl_rpm={}, fr_whl_rpm={}, rl_whl_rpm={}, rr_whl_rpm={})",
-            self.steering, self.fl_whl_rpm, self.fr_whl_rpm, self.rl_whl_rpm, self.rr_whl_rpm
-        )
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
     }
 }
 
-impl Into<Command> for &PyCommand {
-    fn into(self) -> Command {
-        Command {
-            steering: self.steering,
-            fl_whl_rpm: self.fl_whl_rpm,
-            fr_whl_rpm: self.fr_whl_rpm,
-            rl_whl_rpm: self.rl_whl_rpm,
-            rr_whl_rpm: self.rr_whl_rpm,
-        }
+/// State of the motor drivers of the axles.
+#[pyclass(name = "Axles")]
+struct PyAxles(Axles);
+
+#[pymethods]
+impl PyAxles {
+    /// Axles that the host wants driven.
+    #[getter]
+    fn mode(&self) -> PyDriveMode {
+        PyDriveMode(self.0.mode)
+    }
+
+    /// Whether the drivers of the front motors are enabled.
+    #[getter]
+    fn front(&self) -> bool {
+        self.0.front
+    }
+
+    /// Whether the drivers of the rear motors are enabled.
+    #[getter]
+    fn rear(&self) -> bool {
+        self.0.rear
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
     }
 }
 
-/// Wrapper type around [`Info`].
+/// Axles driven by their motors, the others roll freely.
 ///
-/// It is not a Python object but it converts to two: [`PySensors`] and
-/// [`PyLog`]. A Python function returning this types can be annotated
-/// with `Union[Sensors, Log]`.
-enum PyInfo {
-    Sensors(PySensors),
-    Log(PyLog),
-}
+/// The protections that cut the motors disable their drivers whatever the
+/// mode.
+///
+/// >>> DriveMode.RearWheel
+/// DriveMode.RearWheel
+#[pyclass(name = "DriveMode")]
+#[derive(Clone, Copy)]
+struct PyDriveMode(DriveMode);
 
-impl IntoPy<PyObject> for PyInfo {
-    fn into_py(self, py: Python) -> PyObject {
-        match self {
-            Self::Sensors(sensors) => sensors.into_py(py),
-            Self::Log(log) => log.into_py(py),
-        }
+#[pymethods]
+#[allow(non_upper_case_globals)]
+impl PyDriveMode {
+    /// Both axles, the default.
+    #[classattr]
+    const AllWheel: Self = Self(DriveMode::AllWheel);
+    /// The front axle.
+    #[classattr]
+    const FrontWheel: Self = Self(DriveMode::FrontWheel);
+    /// The rear axle.
+    #[classattr]
+    const RearWheel: Self = Self(DriveMode::RearWheel);
+    /// Neither axle, the Xmaxx coasts.
+    #[classattr]
+    const Coast: Self = Self(DriveMode::Coast);
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.0 == other.0
     }
-}
 
-/// Sensor information from the firmware.
-#[pyclass(name = "Sensors")]
-struct PySensors {
-    /// Front left wheel RPM.
-    #[pyo3(get)]
-    fl_whl_rpm: i32,
-    /// Front right wheel RPM.
-    #[pyo3(get)]
-    fr_whl_rpm: i32,
-    /// Rear left wheel RPM.
-    #[pyo3(get)]
-    rl_whl_rpm: i32,
-    /// Rear right wheel RPM.
-    #[pyo3(get)]
-    rr_whl_rpm: i32,
+    fn __hash__(&self) -> u64 {
+        self.0 as u64
+    }
+
+    fn __repr__(&self) -> String {
+        format!("DriveMode.{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
 }
 
+/// Mode selected by the mode switch of the RC transmitter.
+#[pyclass(name = "ControlMode")]
+#[derive(Clone, Copy)]
+struct PyControlMode(ControlMode);
+
 #[pymethods]
-impl PySensors {
+#[allow(non_upper_case_globals)]
+impl PyControlMode {
+    /// The host drives.
+    #[classattr]
+    const Host: Self = Self(ControlMode::Host);
+    /// The RC transmitter drives.
+    #[classattr]
+    const Rc: Self = Self(ControlMode::Rc);
+    /// The host drives while it sends commands, and the RC transmitter
+    /// drives when it stops.
+    #[classattr]
+    const Auto: Self = Self(ControlMode::Auto);
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+
+    fn __hash__(&self) -> u64 {
+        self.0 as u64
+    }
+
     fn __repr__(&self) -> String {
-        format!(
-            "Sensors(fl_whl_rpm={}, fr_whl_rpm={}, rl_whl_rpm={}, rr_whl_rpm={})",
-            self.fl_whl_rpm, self.fr_whl_rpm, self.rl_whl_rpm, self.rr_whl_rpm
-        )
+        format!("ControlMode.{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
     }
 }
 
-impl From<Sensors> for PySensors {
-    fn from(sensors: Sensors) -> Self {
-        Self {
-            fl_whl_rpm: sensors.fl_whl_rpm,
-            fr_whl_rpm: sensors.fr_whl_rpm,
-            rl_whl_rpm: sensors.rl_whl_rpm,
-            rr_whl_rpm: sensors.rr_whl_rpm,
-        }
+/// Source of the command followed by the firmware.
+#[pyclass(name = "ControlSource")]
+#[derive(Clone, Copy)]
+struct PyControlSource(ControlSource);
+
+#[pymethods]
+#[allow(non_upper_case_globals)]
+impl PyControlSource {
+    /// The last command of the host.
+    #[classattr]
+    const Host: Self = Self(ControlSource::Host);
+    /// The sticks of the RC transmitter.
+    #[classattr]
+    const Rc: Self = Self(ControlSource::Rc);
+    /// Nothing, the wheels are stopped.
+    #[classattr]
+    const Stopped: Self = Self(ControlSource::Stopped);
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+
+    fn __hash__(&self) -> u64 {
+        self.0 as u64
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ControlSource.{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
     }
 }
 
 /// Information about what is happening in the firmware.
+///
+/// >>> Log.Overcurrent.description
+/// 'a motor drew too much current and was cut'
 #[pyclass(name = "Log")]
-enum PyLog {
+#[derive(Clone, Copy)]
+struct PyLog(Log);
+
+#[pymethods]
+#[allow(non_upper_case_globals)]
+impl PyLog {
     /// The firmware could not serialize a message.
-    SerializationError,
+    #[classattr]
+    const SerializationError: Self = Self(Log::SerializationError);
     /// The firmware could not deserialize a message.
-    DeserializationError,
+    #[classattr]
+    const DeserializationError: Self = Self(Log::DeserializationError);
     /// The software read buffer overflowed.
-    ReadBufferOverflow,
+    #[classattr]
+    const ReadBufferOverflow: Self = Self(Log::ReadBufferOverflow);
     /// It was too long since the last message received.
-    ReadTimeout,
+    #[classattr]
+    const ReadTimeout: Self = Self(Log::ReadTimeout);
     /// The firmware panicked and must must reseted.
-    FirmwarePanic,
+    #[classattr]
+    const FirmwarePanic: Self = Self(Log::FirmwarePanic);
     /// The command sent was invalid.
-    InvalidCommand,
+    #[classattr]
+    const InvalidCommand: Self = Self(Log::InvalidCommand);
     /// A command was received.
-    CommandReceived,
+    #[classattr]
+    const CommandReceived: Self = Self(Log::CommandReceived);
     /// No command was received.
-    NoCommandReceived,
-}
-
-impl From<Log> for PyLog {
-    fn from(log: Log) -> Self {
-        match log {
-            Log::SerializationError => Self::SerializationError,
-            Log::DeserializationError => Self::DeserializationError,
-            Log::ReadBufferOverflow => Self::ReadBufferOverflow,
-            Log::ReadTimeout => Self::ReadTimeout,
-            Log::FirmwarePanic => Self::FirmwarePanic,
-            Log::InvalidCommand => Self::InvalidCommand,
-            Log::CommandReceived => Self::CommandReceived,
-            Log::NoCommandReceived => Self::NoCommandReceived,
+    #[classattr]
+    const NoCommandReceived: Self = Self(Log::NoCommandReceived);
+    /// The value of a parameter was out of range.
+    #[classattr]
+    const InvalidParam: Self = Self(Log::InvalidParam);
+    /// A motor drew too much current and was cut until its speed is
+    /// commanded to zero.
+    #[classattr]
+    const Overcurrent: Self = Self(Log::Overcurrent);
+    /// The battery went below its warning voltage and the speed is limited.
+    #[classattr]
+    const LowBattery: Self = Self(Log::LowBattery);
+    /// The battery went below its cutoff voltage and the motors are
+    /// disarmed until it recovers and the speeds are commanded to zero.
+    #[classattr]
+    const BatteryCutoff: Self = Self(Log::BatteryCutoff);
+    /// The IMU did not answer on I2C, or is not the expected device.
+    #[classattr]
+    const ImuError: Self = Self(Log::ImuError);
+    /// The signal of the RC receiver was lost while it drove, and the
+    /// wheels are stopped until it comes back.
+    #[classattr]
+    const RcSignalLost: Self = Self(Log::RcSignalLost);
+    /// An obstacle is closer than the stop distance in the direction
+    /// commanded, and the wheels are stopped.
+    #[classattr]
+    const CollisionStop: Self = Self(Log::CollisionStop);
+
+    /// What happened in the firmware.
+    #[getter]
+    fn description(&self) -> &'static str {
+        self.0.description()
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+
+    fn __hash__(&self) -> u64 {
+        self.0 as u64
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Log.{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// Converts an error from the host library to a Python exception.
+fn to_py_err(err: Error) -> PyErr {
+    match err {
+        Error::Io(err) => err.into(),
+        err => PyException::new_err(err.to_string()),
+    }
+}
+
+/// Something that happened to the connection with the firmware.
+#[pyclass(name = "ConnectionEvent")]
+struct PyConnectionEvent(ConnectionEvent);
+
+#[pymethods]
+impl PyConnectionEvent {
+    /// What happened: "connected", "disconnected", "reconnect_failed",
+    /// "reconnected" or "closed".
+    #[getter]
+    fn kind(&self) -> &'static str {
+        match self.0 {
+            ConnectionEvent::Connected { .. } => "connected",
+            ConnectionEvent::Disconnected { .. } => "disconnected",
+            ConnectionEvent::ReconnectFailed { .. } => "reconnect_failed",
+            ConnectionEvent::Reconnected { .. } => "reconnected",
+            ConnectionEvent::Closed => "closed",
+        }
+    }
+
+    /// The path of the port, if the port was opened.
+    #[getter]
+    fn port(&self) -> Option<&str> {
+        match &self.0 {
+            ConnectionEvent::Connected { port } | ConnectionEvent::Reconnected { port } => {
+                Some(port)
+            }
+            _ => None,
         }
     }
+
+    /// The error, if something failed.
+    #[getter]
+    fn error(&self) -> Option<&str> {
+        match &self.0 {
+            ConnectionEvent::Disconnected { error }
+            | ConnectionEvent::ReconnectFailed { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+
+    /// The reconnection attempt, if it failed.
+    #[getter]
+    fn attempt(&self) -> Option<u32> {
+        match self.0 {
+            ConnectionEvent::ReconnectFailed { attempt, .. } => Some(attempt),
+            _ => None,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// Statistics about the frames received from the firmware.
+#[pyclass(name = "RecvStats")]
+struct PyRecvStats(RecvStats);
+
+#[pymethods]
+impl PyRecvStats {
+    /// The number of valid frames received.
+    #[getter]
+    fn frames(&self) -> u64 {
+        self.0.frames
+    }
+
+    /// The number of frames that could not be deserialized.
+    #[getter]
+    fn discarded_frames(&self) -> u64 {
+        self.0.discarded_frames
+    }
+
+    /// The number of bytes discarded, in or before frames.
+    #[getter]
+    fn discarded_bytes(&self) -> u64 {
+        self.0.discarded_bytes
+    }
+
+    /// The number of io operations that timed out.
+    #[getter]
+    fn timeouts(&self) -> u64 {
+        self.0.timeouts
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
 }
 
 /// A socket to communicate with the Xmaxx firmware.
 ///
+/// When the Arduino resets or the USB cable is reconnected, the port is
+/// reopened automatically, even if it came back at another path. Use
+/// `state` and `events()` to follow what happened.
+///
 /// **Note:** if there are problems with deserialization in the firmware,
 /// it might be because the computer is sending the next bytes too soon.
 /// Try increasing the send delay.
 #[pyclass(name = "Firmware")]
 struct PyFirmware {
-    port: Option<Box<dyn SerialPort>>,
-    send_delay: Duration,
+    firmware: Firmware,
+}
+
+/// Builds the settings from the Python arguments.
+#[allow(clippy::too_many_arguments)]
+fn settings(
+    baudrate: u32,
+    timeout: u64,
+    send_delay: u64,
+    handshake_timeout: Option<u64>,
+    max_timeouts: Option<u32>,
+    reconnect: bool,
+    max_attempts: Option<u32>,
+    retry_delay: u64,
+) -> Settings {
+    Settings {
+        baudrate,
+        timeout: Duration::from_millis(timeout),
+        send_delay: Duration::from_millis(send_delay),
+        handshake_timeout: handshake_timeout.map(Duration::from_millis),
+        max_timeouts,
+        reconnect: ReconnectPolicy {
+            enabled: reconnect,
+            max_attempts,
+            retry_delay: Duration::from_millis(retry_delay),
+        },
+    }
 }
 
 #[pymethods]
@@ -178,21 +1311,169 @@ impl PyFirmware {
     ///     the timeout on io operations (ms)
     /// send_delay: int = 3
     ///     the delay between each byte sent (ms)
+    /// handshake_timeout: Optional[int] = 3000
+    ///     how long to wait for the firmware to talk after opening the port,
+    ///     None to not wait (ms)
+    /// max_timeouts: Optional[int] = 3
+    ///     the number of consecutive timeouts after which the port is
+    ///     considered dead and reopened, None to never
+    /// reconnect: bool = True
+    ///     whether to reopen the port when it dies
+    /// max_attempts: Optional[int] = 10
+    ///     the number of reconnection attempts, None to never give up
+    /// retry_delay: int = 500
+    ///     the delay between two reconnection attempts (ms)
     #[new]
-    #[pyo3(signature = (port, baudrate=57600, timeout=500, send_delay=3))]
-    fn new(port: &str, baudrate: u32, timeout: u64, send_delay: u64) -> PyResult<Self> {
-        match serialport::new(port, baudrate).open() {
-            Ok(mut port) => {
-                // must set timeout otherwise it is 0 and every operation hits it
-                port.set_timeout(Duration::from_millis(timeout))
-                    .expect("setting timeout should just work?");
-                Ok(Self {
-                    port: Some(port),
-                    send_delay: Duration::from_millis(send_delay),
-                })
-            }
-            Err(err) => Err(PyException::new_err(err.description)),
-        }
+    #[pyo3(signature = (
+        port,
+        baudrate=57600,
+        timeout=500,
+        send_delay=3,
+        handshake_timeout=Some(3000),
+        max_timeouts=Some(3),
+        reconnect=true,
+        max_attempts=Some(10),
+        retry_delay=500,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        port: &str,
+        baudrate: u32,
+        timeout: u64,
+        send_delay: u64,
+        handshake_timeout: Option<u64>,
+        max_timeouts: Option<u32>,
+        reconnect: bool,
+        max_attempts: Option<u32>,
+        retry_delay: u64,
+    ) -> PyResult<Self> {
+        let settings = settings(
+            baudrate,
+            timeout,
+            send_delay,
+            handshake_timeout,
+            max_timeouts,
+            reconnect,
+            max_attempts,
+            retry_delay,
+        );
+        let firmware = Firmware::open(PortSpec::Path(port.into()), settings).map_err(to_py_err)?;
+
+        Ok(Self { firmware })
+    }
+
+    /// Instantiates a connection to the firmware on a USB device.
+    ///
+    /// The device is found by its ids, whatever the path the OS gave it.
+    ///
+    /// Parameters:
+    /// -----------
+    /// vid: int
+    ///     the USB vendor ID
+    /// pid: int
+    ///     the USB product ID
+    /// serial_number: Optional[str] = None
+    ///     the serial number of the device, to tell apart identical boards
+    ///
+    /// The other parameters are the same as the constructor's.
+    #[staticmethod]
+    #[pyo3(signature = (
+        vid,
+        pid,
+        serial_number=None,
+        baudrate=57600,
+        timeout=500,
+        send_delay=3,
+        handshake_timeout=Some(3000),
+        max_timeouts=Some(3),
+        reconnect=true,
+        max_attempts=Some(10),
+        retry_delay=500,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn from_usb(
+        vid: u16,
+        pid: u16,
+        serial_number: Option<String>,
+        baudrate: u32,
+        timeout: u64,
+        send_delay: u64,
+        handshake_timeout: Option<u64>,
+        max_timeouts: Option<u32>,
+        reconnect: bool,
+        max_attempts: Option<u32>,
+        retry_delay: u64,
+    ) -> PyResult<Self> {
+        let settings = settings(
+            baudrate,
+            timeout,
+            send_delay,
+            handshake_timeout,
+            max_timeouts,
+            reconnect,
+            max_attempts,
+            retry_delay,
+        );
+        let spec = PortSpec::Usb(UsbId {
+            vid,
+            pid,
+            serial_number,
+        });
+        let firmware = Firmware::open(spec, settings).map_err(to_py_err)?;
+
+        Ok(Self { firmware })
+    }
+
+    /// Replays a recorded session as if it came from the firmware.
+    ///
+    /// The information received is read from the recording at the pace it
+    /// was recorded. The commands sent are discarded.
+    ///
+    /// Parameters:
+    /// -----------
+    /// path: str
+    ///     the path to the recording file
+    /// speed: Optional[float] = 1.0
+    ///     the playback speed, None to play as fast as possible
+    #[staticmethod]
+    #[pyo3(signature = (path, speed=Some(1.0)))]
+    fn replay(path: &str, speed: Option<f64>) -> PyResult<Self> {
+        let recording = Recording::open(path)?;
+        let transport = ReplayTransport::new(recording, speed);
+        let settings = Settings {
+            send_delay: Duration::ZERO,
+            handshake_timeout: None,
+            ..Default::default()
+        };
+
+        Ok(Self {
+            firmware: Firmware::with_transport(Box::new(transport), settings),
+        })
+    }
+
+    /// The state of the connection: "connected", "reconnecting",
+    /// "disconnected" or "closed".
+    #[getter]
+    fn state(&self) -> String {
+        self.firmware.state().to_string()
+    }
+
+    /// The path of the last opened port.
+    #[getter]
+    fn port(&self) -> Option<String> {
+        self.firmware.path().map(String::from)
+    }
+
+    /// Returns the connection events since the last call.
+    ///
+    /// Returns:
+    /// --------
+    /// List[ConnectionEvent]
+    ///     the events, oldest first
+    fn events(&mut self) -> Vec<PyConnectionEvent> {
+        std::iter::from_fn(|| self.firmware.poll_event())
+            .map(PyConnectionEvent)
+            .collect()
     }
 
     /// Sends a command to the firmware.
@@ -201,10 +1482,10 @@ impl PyFirmware {
     /// blocking function.
     ///
     /// Raises an exception if the socket was closed or if an io error occurs
-    /// during the write operation.
+    /// during the write operation and the port could not be reopened.
     ///
-    /// BUG After a while without sending, operation times out systematically
-    /// until a new firmware instantiated.
+    /// If operations time out `max_timeouts` times in a row, the port is
+    /// considered stale and reopened.
     ///
     /// Parameters:
     /// -----------
@@ -212,68 +1493,185 @@ impl PyFirmware {
     ///     the command to send to the firmware
     ///
     fn send(&mut self, command: &PyCommand) -> PyResult<()> {
-        if let Some(port) = &mut self.port {
-            let mut buf = [0u8; Command::MAX_SERIAL_SIZE];
-            let msg = serialize::<Command>(&command.into(), &mut buf)
-                .expect("serializing should just work");
-
-            for i in 0..msg.len() {
-                port.write(&msg[i..i + 1])?;
-                thread::sleep(self.send_delay);
-            }
-
-            port.flush()?;
-
-            Ok(())
-        } else {
-            Err(PyException::new_err("the socket was closed"))
-        }
+        self.firmware
+            .send(&Command::Drive(command.0.clone()))
+            .map_err(to_py_err)
     }
 
     /// Receives information from the firmware.
     ///
-    /// Raises errors on failed io operations and if it fails to deserialize
-    /// a message.
+    /// Raises errors on failed io operations. The frames that cannot be
+    /// deserialized, like the text printed when the firmware panics, are
+    /// skipped and counted in `stats()`.
     ///
-    /// This method returns either a `Sensors` or a `Log`. Therefore,
-    /// it is recommended to match its output a little like this:
+    /// This method returns either a `Sensors`, a `Log`, a `Pong`, a `Param`,
+    /// a `Currents`, a `Battery`, an `Encoders`, an `Imu`, a `Control`, a
+    /// `Proximity`, a `Traction`, an `Odometry` or an `Axles`.
+    /// Therefore, it is recommended to match its output a little like this:
     /// ```python
     /// >>> match firmware.recv():
     /// ...    case Sensors() as sensors:
     /// ...        ...
     /// ...    case Log() as log:
     /// ...        ...
+    /// ...    case Pong() | Param() | Currents() | Battery() | Encoders() | Imu() | Control() | Proximity() | Traction() | Odometry() | Axles():
+    /// ...        ...
     /// ```
     ///
     /// Returns:
     /// --------
-    /// Union[Sensors, Log]
+    /// Union[Sensors, Log, Pong, Param, Currents, Battery, Encoders, Imu, Control, Proximity, Traction, Odometry, Axles]
     ///     an event in the firmware
     ///
     fn recv(&mut self) -> PyResult<PyInfo> {
-        if let Some(port) = &mut self.port {
-            let mut b = [0u8; 1];
-            let mut buf = Vec::<u8>::new();
+        self.firmware.recv().map(PyInfo).map_err(to_py_err)
+    }
+
+    /// Returns the statistics about the received frames.
+    ///
+    /// Returns:
+    /// --------
+    /// RecvStats
+    ///     the statistics since the connection was opened
+    fn stats(&self) -> PyRecvStats {
+        PyRecvStats(self.firmware.stats())
+    }
+
+    /// Pings the firmware.
+    ///
+    /// The information received while waiting for the answer is discarded.
+    ///
+    /// Raises an exception if the firmware does not answer in time.
+    ///
+    /// Parameters:
+    /// -----------
+    /// timeout: int = 1000
+    ///     how long to wait for the answer (ms)
+    ///
+    /// Returns:
+    /// --------
+    /// float
+    ///     the round trip time (ms)
+    #[pyo3(signature = (timeout=1000))]
+    fn ping(&mut self, timeout: u64) -> PyResult<f64> {
+        let rtt = self
+            .firmware
+            .ping(Duration::from_millis(timeout))
+            .map_err(to_py_err)?;
 
-            loop {
-                port.read(&mut b)?;
-                buf.push(b[0]);
+        Ok(rtt.as_secs_f64() * 1000.0)
+    }
 
-                if buf.last() == Some(&0u8) {
-                    break;
-                }
-            }
+    /// Returns the value of a parameter of the firmware.
+    ///
+    /// Raises an exception if the firmware does not answer in time.
+    ///
+    /// Parameters:
+    /// -----------
+    /// name: str
+    ///     the name of the parameter, like "rpm_limit"
+    /// timeout: int = 1000
+    ///     how long to wait for the answer (ms)
+    ///
+    /// Returns:
+    /// --------
+    /// int
+    ///     the value of the parameter
+    #[pyo3(signature = (name, timeout=1000))]
+    fn get_param(&mut self, name: &str, timeout: u64) -> PyResult<i32> {
+        self.firmware
+            .get_param(param(name)?, Duration::from_millis(timeout))
+            .map_err(to_py_err)
+    }
 
-            let info = deserialize(buf.as_mut_slice())
-                .or_else(|_| Err(PyException::new_err("could not deserialize")))?;
+    /// Sets a parameter of the firmware until it resets.
+    ///
+    /// Raises an exception if the firmware refuses the value or does not
+    /// answer in time.
+    ///
+    /// Parameters:
+    /// -----------
+    /// name: str
+    ///     the name of the parameter, like "rpm_limit"
+    /// value: int
+    ///     the new value of the parameter
+    /// timeout: int = 1000
+    ///     how long to wait for the answer (ms)
+    ///
+    /// Returns:
+    /// --------
+    /// int
+    ///     the new value of the parameter
+    #[pyo3(signature = (name, value, timeout=1000))]
+    fn set_param(&mut self, name: &str, value: i32, timeout: u64) -> PyResult<i32> {
+        self.firmware
+            .set_param(param(name)?, value, Duration::from_millis(timeout))
+            .map_err(to_py_err)
+    }
 
-            Ok(match info {
-                Info::Sensors(sensors) => PyInfo::Sensors(sensors.into()),
-                Info::Log(log) => PyInfo::Log(log.into()),
-            })
-        } else {
-            Err(PyException::new_err("the socket was closed"))
-        }
+    /// Moves the odometry of the firmware back to the origin.
+    ///
+    /// Raises an exception if the firmware does not answer in time.
+    ///
+    /// Parameters:
+    /// -----------
+    /// timeout: int = 1000
+    ///     how long to wait for the answer (ms)
+    ///
+    /// Returns:
+    /// --------
+    /// Odometry
+    ///     the odometry at the origin, at the time of the reset
+    #[pyo3(signature = (timeout=1000))]
+    fn reset_odometry(&mut self, timeout: u64) -> PyResult<PyOdometry> {
+        self.firmware
+            .reset_odometry(Duration::from_millis(timeout))
+            .map(PyOdometry)
+            .map_err(to_py_err)
+    }
+
+    /// Selects the axles driven by their motors.
+    ///
+    /// The protections that cut the motors disable their drivers whatever
+    /// the mode. Raises an exception if the firmware does not answer in
+    /// time.
+    ///
+    /// Parameters:
+    /// -----------
+    /// mode: DriveMode
+    ///     the axles to drive
+    /// timeout: int = 1000
+    ///     how long to wait for the answer (ms)
+    ///
+    /// Returns:
+    /// --------
+    /// Axles
+    ///     the mode set, and the drivers enabled before it applies
+    #[pyo3(signature = (mode, timeout=1000))]
+    fn set_drive_mode(&mut self, mode: PyDriveMode, timeout: u64) -> PyResult<PyAxles> {
+        self.firmware
+            .set_drive_mode(mode.0, Duration::from_millis(timeout))
+            .map(PyAxles)
+            .map_err(to_py_err)
+    }
+
+    /// Records the raw frames sent and received to a file.
+    ///
+    /// The recording can be played back with `Firmware.replay()`. A previous
+    /// recording is stopped.
+    ///
+    /// Parameters:
+    /// -----------
+    /// path: str
+    ///     the path to the recording file, overwritten if it exists
+    fn record(&mut self, path: &str) -> PyResult<()> {
+        let recorder = Recorder::create(path)?;
+        self.firmware.record(Some(recorder)).map_err(to_py_err)
+    }
+
+    /// Stops recording, if it was.
+    fn stop_recording(&mut self) -> PyResult<()> {
+        self.firmware.record(None).map_err(to_py_err)
     }
 
     /// Closes the connection to the firmware.
@@ -281,12 +1679,141 @@ impl PyFirmware {
     /// The calling instance can no longer be used after. To reopen the
     /// communication, instantiate a new object.
     fn close(&mut self) {
-        // cannot move self to drop
-        // setting `self.port` to none drops the serial port therefore closing it
-        self.port = None;
+        self.firmware.close();
+    }
+}
+
+/// A serial port that might be connected to the firmware.
+#[pyclass(name = "Candidate")]
+struct PyCandidate(Candidate);
+
+#[pymethods]
+impl PyCandidate {
+    /// The path of the port.
+    #[getter]
+    fn path(&self) -> &str {
+        &self.0.path
+    }
+
+    /// The USB vendor ID.
+    #[getter]
+    fn vid(&self) -> u16 {
+        self.0.usb.vid
+    }
+
+    /// The USB product ID.
+    #[getter]
+    fn pid(&self) -> u16 {
+        self.0.usb.pid
+    }
+
+    /// The serial number of the USB device.
+    #[getter]
+    fn serial_number(&self) -> Option<&str> {
+        self.0.usb.serial_number.as_deref()
+    }
+
+    /// The manufacturer string of the USB device.
+    #[getter]
+    fn manufacturer(&self) -> Option<&str> {
+        self.0.manufacturer.as_deref()
+    }
+
+    /// The product string of the USB device.
+    #[getter]
+    fn product(&self) -> Option<&str> {
+        self.0.product.as_deref()
+    }
+
+    /// The name of the board, if it is known.
+    #[getter]
+    fn board(&self) -> Option<&'static str> {
+        self.0.board
+    }
+
+    /// Whether the firmware answered a ping (None -> not probed).
+    #[getter]
+    fn firmware(&self) -> Option<bool> {
+        self.0.firmware
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
     }
 }
 
+/// Lists the serial ports that might be connected to the firmware.
+///
+/// Probing opens each port, which resets the Arduino, and pings it.
+///
+/// Parameters:
+/// -----------
+/// all: bool = False
+///     list every USB serial port, not only the known Arduino Mega boards
+/// probe: bool = False
+///     ping each port to confirm it runs the firmware
+/// timeout: int = 3000
+///     how long to wait for the firmware to answer when probing (ms)
+///
+/// Returns:
+/// --------
+/// List[Candidate]
+///     the serial ports
+#[pyfunction]
+#[pyo3(name = "list_firmwares", signature = (all=false, probe=false, timeout=3000))]
+fn py_list_firmwares(all: bool, probe: bool, timeout: u64) -> PyResult<Vec<PyCandidate>> {
+    let probe = probe.then(|| Duration::from_millis(timeout));
+    let candidates = list_firmwares(all, probe).map_err(to_py_err)?;
+
+    Ok(candidates.into_iter().map(PyCandidate).collect())
+}
+
+/// Exports a recorded session to one CSV file per message type.
+///
+/// The first column is the time since the start of the recording (s).
+///
+/// Parameters:
+/// -----------
+/// recording: str
+///     the path to the recording file
+/// directory: str
+///     the directory where to write the files, created if needed
+///
+/// Returns:
+/// --------
+/// List[str]
+///     the paths of the files written
+#[pyfunction]
+#[pyo3(name = "export_csv")]
+fn py_export_csv(recording: &str, directory: &str) -> PyResult<Vec<String>> {
+    let recording = Recording::open(recording)?;
+    let paths = export_csv(&recording, directory)?;
+
+    Ok(paths
+        .into_iter()
+        .map(|path| path.display().to_string())
+        .collect())
+}
+
+/// Exports a recorded session to an MCAP file.
+///
+/// There is one topic per message type, encoded in JSON.
+///
+/// Parameters:
+/// -----------
+/// recording: str
+///     the path to the recording file
+/// path: str
+///     the path to the MCAP file, overwritten if it exists
+#[pyfunction]
+#[pyo3(name = "export_mcap")]
+fn py_export_mcap(recording: &str, path: &str) -> PyResult<()> {
+    let recording = Recording::open(recording)?;
+    export_mcap(&recording, path)?;
+
+    Ok(())
+}
+
 /// A Python module to interface with the Xmaxx firmware - in Rust.
 ///
 /// It provides the means to send commands to and receive information from the
@@ -296,9 +1823,10 @@ impl PyFirmware {
 /// ```python
 /// >>> from xmaxx_python import *
 /// >>>
-/// >>> firmware = Firmware("/path/to/port")
+/// >>> [candidate] = list_firmwares(probe=True)
+/// >>> firmware = Firmware(candidate.path)
 /// >>>
-/// >>> command = Command(42, 37, 37, 37, 37)
+/// >>> command = Command.from_degrees_and_rpm(90.0, 500.0, 500.0, 500.0, 500.0)
 /// >>> firmware.send(command)
 /// >>>
 /// >>> match firmware.recv():
@@ -310,8 +1838,36 @@ impl PyFirmware {
 #[pymodule]
 fn xmaxx_python(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<PyFirmware>()?;
+    m.add_class::<PyCentiDegrees>()?;
+    m.add_class::<PyCentiRpm>()?;
     m.add_class::<PyCommand>()?;
+    m.add_class::<PyMilliAmps>()?;
     m.add_class::<PySensors>()?;
+    m.add_class::<PyCurrents>()?;
+    m.add_class::<PyMilliVolts>()?;
+    m.add_class::<PyBattery>()?;
+    m.add_class::<PyEncoders>()?;
+    m.add_class::<PyMilliG>()?;
+    m.add_class::<PyCentiDegreesPerSecond>()?;
+    m.add_class::<PyMilliMeters>()?;
+    m.add_class::<PyPerMille>()?;
+    m.add_class::<PyImu>()?;
+    m.add_class::<PyControl>()?;
+    m.add_class::<PyControlMode>()?;
+    m.add_class::<PyControlSource>()?;
+    m.add_class::<PyProximity>()?;
+    m.add_class::<PyTraction>()?;
+    m.add_class::<PyOdometry>()?;
+    m.add_class::<PyAxles>()?;
+    m.add_class::<PyDriveMode>()?;
     m.add_class::<PyLog>()?;
+    m.add_class::<PyConnectionEvent>()?;
+    m.add_class::<PyPong>()?;
+    m.add_class::<PyParam>()?;
+    m.add_class::<PyCandidate>()?;
+    m.add_class::<PyRecvStats>()?;
+    m.add_function(wrap_pyfunction!(py_list_firmwares, m)?)?;
+    m.add_function(wrap_pyfunction!(py_export_csv, m)?)?;
+    m.add_function(wrap_pyfunction!(py_export_mcap, m)?)?;
     Ok(())
 }
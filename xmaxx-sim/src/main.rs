@@ -0,0 +1,258 @@
+use std::error::Error;
+use std::fs;
+use std::os::unix;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+
+use xmaxx_core::collision::RANGE_PERIOD;
+use xmaxx_core::conversion::{analog_sum_to_rpm, analog_to_current, analog_to_voltage};
+use xmaxx_core::imu::{ImuReader, Mpu6050, IMU_ADDRESS};
+use xmaxx_core::readbuf::ReadBuf;
+use xmaxx_core::serial::{read_command, write_event};
+use xmaxx_core::{Controller, Drivetrain, Readings, Telemetry};
+use xmaxx_messages::*;
+
+mod pty;
+mod vehicle;
+
+use pty::Pty;
+use vehicle::{Model, Vehicle};
+
+/// The telemetry sent one an iteration in turn, like the firmware with all
+/// its features.
+const SLOW_TELEMETRY: [Telemetry; 8] = [
+    Telemetry::Currents,
+    Telemetry::Battery,
+    Telemetry::Control,
+    Telemetry::Traction,
+    Telemetry::Axles,
+    Telemetry::Encoders,
+    Telemetry::Imu,
+    Telemetry::Proximity,
+];
+
+/// Set when Ctrl-C is pressed.
+static STOP: AtomicBool = AtomicBool::new(false);
+
+/// A simulator of the Xmaxx's firmware, on a pseudo-terminal.
+///
+/// It runs the command handling of the firmware with a model of the
+/// Xmaxx, and talks like the Arduino on its serial port.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// Creates a symbolic link to the pseudo-terminal, like /tmp/xmaxx.
+    #[arg(short, long)]
+    link: Option<PathBuf>,
+    /// The period of the main loop (ms).
+    #[arg(short, long, default_value_t = 10)]
+    period: u64,
+    /// Prints the commands received and the state of the Xmaxx.
+    #[arg(short, long)]
+    verbose: bool,
+    /// The seed of the noise on the sensors.
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// The time constant of the motors (s).
+    #[arg(long, default_value_t = Model::default().motor_tau)]
+    motor_tau: f64,
+    /// The maximum rate of the steering servo (deg/s).
+    #[arg(long, default_value_t = Model::default().servo_rate)]
+    servo_rate: f64,
+    /// The standard deviation of the noise on the speed sensors (analog
+    /// units).
+    #[arg(long, default_value_t = Model::default().adc_noise)]
+    adc_noise: f64,
+    /// The current drawn by each motor to hold a load, to try the
+    /// overcurrent protection (A).
+    #[arg(long, default_value_t = Model::default().motor_load)]
+    motor_load: f64,
+    /// The voltage of the battery at rest when the simulation starts, to
+    /// try the low-voltage protection (V).
+    #[arg(long, default_value_t = Model::default().battery_voltage)]
+    battery_voltage: f64,
+    /// The charge of the battery from full to empty (Ah).
+    #[arg(long, default_value_t = Model::default().battery_capacity)]
+    battery_capacity: f64,
+    /// Connects an RC receiver with the widths of its pulses: throttle,
+    /// steering and mode switch (µs, 1000 to 2000, 1500 is the center).
+    #[arg(long, value_name = "THROTTLE,STEERING,MODE", value_parser = parse_pulses)]
+    rc: Option<[u16; 3]>,
+    /// Puts these wheels on slippery ground, like `rl,rr`, where they spin
+    /// up faster than the others and do not drive the Xmaxx, to try the
+    /// traction control.
+    #[arg(long, value_name = "WHEELS", value_parser = parse_wheels)]
+    slippery: Option<[bool; 4]>,
+    /// Puts a wall across the x axis at this x, for the range sensors (m).
+    /// The Xmaxx starts at 0 heading towards +x.
+    #[arg(long, allow_hyphen_values = true)]
+    wall: Option<f64>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    ctrlc::set_handler(|| STOP.store(true, Ordering::Relaxed))?;
+
+    let mut serial = Pty::open()?;
+    if let Some(link) = &cli.link {
+        // replace the link of a previous run
+        let _ = fs::remove_file(link);
+        unix::fs::symlink(serial.path(), link)?;
+    }
+    println!("simulating the firmware on {}", serial.path().display());
+
+    let mut read_buf = ReadBuf::<{ Command::MAX_SERIAL_SIZE }>::new();
+    let mut write_buf = [0u8; Info::MAX_SERIAL_SIZE];
+
+    let model = Model {
+        motor_tau: cli.motor_tau,
+        servo_rate: cli.servo_rate,
+        adc_noise: cli.adc_noise,
+        motor_load: cli.motor_load,
+        battery_voltage: cli.battery_voltage,
+        battery_capacity: cli.battery_capacity,
+        ..Default::default()
+    };
+    let mut vehicle = Vehicle::new(model, cli.seed);
+    vehicle.wall = cli.wall;
+    vehicle.slippery = cli.slippery.unwrap_or_default();
+    let [motor_fl, motor_fr, motor_rl, motor_rr] = vehicle.motors.clone();
+    let [enable_front, enable_rear] = vehicle.enables.clone();
+    let mut controller = Controller::new(Drivetrain {
+        steering: vehicle.steering.clone(),
+        motor_fl,
+        motor_fr,
+        motor_rl,
+        motor_rr,
+        enable_front,
+        enable_rear,
+    });
+
+    // the IMU through the same driver as the firmware
+    controller.params.has_imu = true;
+    let mut imu = ImuReader::new(
+        Mpu6050::new(vehicle.imu.clone(), IMU_ADDRESS),
+        controller.params.imu_rate,
+    );
+
+    let period = Duration::from_millis(cli.period);
+    let mut next = Instant::now();
+    // like `millis` on the Arduino, in the time of the model so it is not
+    // jittered by the scheduling of the simulator
+    let mut now: u32 = 0;
+    let mut last_status = Instant::now();
+    while !STOP.load(Ordering::Relaxed) {
+        // the same as the firmware's main loop
+        let command = read_command(&mut read_buf, &mut serial);
+        if cli.verbose {
+            if let Ok(Some(command)) = &command {
+                println!("{command:?}");
+            }
+        }
+        if let Some(info) = controller.handle(command) {
+            write_event(&info, &mut write_buf, &mut serial)
+                .expect("should work because valid message and big enough buffer");
+        }
+
+        // oversample the speeds like the firmware
+        let oversampling = controller.params.speed_oversampling;
+        let mut sums = [0; 4];
+        for _ in 0..oversampling {
+            for (sum, analog) in sums.iter_mut().zip(vehicle.analog_speeds()) {
+                *sum += analog;
+            }
+        }
+        let (imu_reading, log) = imu.update(now, controller.params.imu_rate);
+        if let Some(log) = log {
+            write_event(&Info::Log(log), &mut write_buf, &mut serial)
+                .expect("should work because valid message and big enough buffer");
+        }
+        let readings = Readings {
+            speeds: sums.map(|sum| analog_sum_to_rpm(sum, oversampling.into())),
+            currents: vehicle.analog_currents().map(analog_to_current),
+            voltage: analog_to_voltage(vehicle.analog_voltage()),
+            ticks: vehicle.encoder_ticks(),
+            imu: imu_reading,
+            rc: match cli.rc {
+                Some(pulses) => pulses.map(Some),
+                None => [None; 3],
+            },
+            // ranging like the firmware, once per period of the sensors
+            echoes: if now % RANGE_PERIOD < cli.period as u32 {
+                vehicle.echoes().map(Some)
+            } else {
+                [None; 2]
+            },
+        };
+        for log in controller.update(now, readings) {
+            write_event(&Info::Log(log), &mut write_buf, &mut serial)
+                .expect("should work because valid message and big enough buffer");
+        }
+
+        for info in controller.telemetry(&SLOW_TELEMETRY) {
+            write_event(&info, &mut write_buf, &mut serial)
+                .expect("should work because valid message and big enough buffer");
+        }
+
+        vehicle.step(period.as_secs_f64());
+        now = now.wrapping_add(cli.period as u32);
+        if cli.verbose && last_status.elapsed() >= Duration::from_secs(1) {
+            last_status = Instant::now();
+            print_status(&vehicle);
+        }
+
+        next += period;
+        if let Some(delay) = next.checked_duration_since(Instant::now()) {
+            thread::sleep(delay);
+        }
+    }
+
+    if let Some(link) = &cli.link {
+        fs::remove_file(link)?;
+    }
+
+    Ok(())
+}
+
+/// Parses the widths of the pulses of the RC receiver, like `1500,1500,1000`.
+fn parse_pulses(pulses: &str) -> Result<[u16; 3], String> {
+    let widths = pulses
+        .split(',')
+        .map(|width| width.trim().parse::<u16>().map_err(|err| err.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    widths
+        .try_into()
+        .map_err(|_| "expected 3 widths separated by commas".to_string())
+}
+
+/// Parses a list of wheels, like `fl,rr`, into whether each wheel is in it:
+/// fl, fr, rl, rr.
+fn parse_wheels(wheels: &str) -> Result<[bool; 4], String> {
+    let mut selected = [false; 4];
+    for wheel in wheels.split(',') {
+        let i = ["fl", "fr", "rl", "rr"]
+            .iter()
+            .position(|name| *name == wheel.trim())
+            .ok_or_else(|| format!("unknown wheel {wheel:?}, expected fl, fr, rl or rr"))?;
+        selected[i] = true;
+    }
+    Ok(selected)
+}
+
+fn print_status(vehicle: &Vehicle) {
+    let pose = vehicle.pose();
+    let (left, right) = vehicle.ackermann_angles();
+    println!(
+        "x {:.2} m  y {:.2} m  heading {:.1}°  speed {:.2} m/s  wheels {:.1}° {:.1}°",
+        pose.x,
+        pose.y,
+        pose.heading.to_degrees(),
+        vehicle.speed(),
+        left.to_degrees(),
+        right.to_degrees(),
+    );
+}
@@ -0,0 +1,77 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::{AsFd, AsRawFd};
+use std::path::{Path, PathBuf};
+
+use nix::fcntl::{fcntl, FcntlArg, OFlag};
+use nix::pty::openpty;
+use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg};
+use nix::unistd::ttyname;
+
+/// A pseudo-terminal standing for the serial port of the Arduino.
+///
+/// The host opens the other end at [`Pty::path`] like a real serial port.
+pub struct Pty {
+    master: File,
+    /// Kept open so the terminal stays raw when the host closes it.
+    _slave: File,
+    path: PathBuf,
+}
+
+impl Pty {
+    /// Opens a new pseudo-terminal in raw mode.
+    pub fn open() -> io::Result<Self> {
+        let pty = openpty(None, None)?;
+
+        // the line discipline must not echo nor translate the bytes
+        let mut termios = tcgetattr(pty.slave.as_fd())?;
+        cfmakeraw(&mut termios);
+        tcsetattr(pty.slave.as_fd(), SetArg::TCSANOW, &termios)?;
+
+        // the firmware polls the serial port
+        fcntl(pty.master.as_raw_fd(), FcntlArg::F_SETFL(OFlag::O_NONBLOCK))?;
+
+        let path = ttyname(pty.slave.as_fd())?;
+        Ok(Self {
+            master: pty.master.into(),
+            _slave: pty.slave.into(),
+            path,
+        })
+    }
+
+    /// Returns the path of the end to open from the host.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl embedded_hal_v0::serial::Read<u8> for Pty {
+    type Error = io::Error;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        let mut b = [0u8; 1];
+        match self.master.read(&mut b) {
+            Ok(1) => Ok(b[0]),
+            Ok(_) => Err(nb::Error::WouldBlock),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Err(nb::Error::WouldBlock),
+            Err(err) => Err(nb::Error::Other(err)),
+        }
+    }
+}
+
+impl embedded_hal_v0::serial::Write<u8> for Pty {
+    type Error = io::Error;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        match self.master.write(&[word]) {
+            Ok(_) => Ok(()),
+            // nobody reads, so the byte is lost like on a real serial port
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            Err(err) => Err(nb::Error::Other(err)),
+        }
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,586 @@
+use std::cell::{Cell, RefCell};
+use std::convert::Infallible;
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+use embedded_hal::digital::{self, OutputPin};
+use embedded_hal::i2c::{ErrorKind, I2c, NoAcknowledgeSource, Operation};
+use embedded_hal::pwm::{ErrorType, SetDutyCycle};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rand_distr::{Distribution, Normal};
+
+use xmaxx_core::collision::RANGE_MAX;
+use xmaxx_core::conversion::*;
+use xmaxx_core::encoder::ENCODER_RESOLUTION;
+use xmaxx_core::imu::IMU_ADDRESS;
+use xmaxx_messages::{CentiDegrees, CentiRpm};
+
+/// The maximum duty cycle of the Arduino's PWM timers (8 bits).
+const MAX_DUTY: u16 = 255;
+/// The deadband of the motor drivers around neutral (‰ of duty cycle).
+const MOTOR_DEADBAND: i32 = 5;
+/// The angle of the steering servo to go straight (deg).
+const STEERING_ZERO: f64 = 90.0;
+/// The speed of sound in air at 20 °C (m/s).
+const SPEED_OF_SOUND: f64 = 343.0;
+/// The width of the echo of a range sensor without an obstacle in range,
+/// like the HC-SR04 (µs).
+const NO_ECHO: u32 = 38_000;
+/// The standard gravity (m/s²).
+const GRAVITY: f64 = 9.806_65;
+/// The register of the IMU with its identity, and its value.
+const IMU_WHO_AM_I: (usize, u8) = (0x75, 0x68);
+/// The first register of the IMU's readings, big-endian: the accelerations,
+/// the temperature and the rotation rates.
+const IMU_ACCEL_XOUT_H: usize = 0x3B;
+/// The scales of the ranges that the firmware sets: ±4 g and ±250 deg/s.
+const IMU_LSB_PER_G: f64 = 8192.0;
+const IMU_LSB_PER_DEGREE_PER_SECOND: f64 = 131.0;
+
+/// A PWM output of the simulated Arduino.
+///
+/// It is cheap to clone, the clones share the same duty cycle so the model
+/// reads what the firmware sets.
+#[derive(Clone, Default)]
+pub struct Pwm(Rc<Cell<u16>>);
+
+impl Pwm {
+    /// Returns the duty cycle as a fraction of [`DUTY_CYCLE_DENOM`].
+    fn duty(&self) -> i32 {
+        self.0.get() as i32 * DUTY_CYCLE_DENOM as i32 / MAX_DUTY as i32
+    }
+}
+
+impl ErrorType for Pwm {
+    type Error = Infallible;
+}
+
+impl SetDutyCycle for Pwm {
+    fn max_duty_cycle(&self) -> u16 {
+        MAX_DUTY
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+        self.0.set(duty);
+        Ok(())
+    }
+}
+
+/// A digital output of the simulated Arduino.
+///
+/// It is cheap to clone, the clones share the same level so the model reads
+/// what the firmware sets.
+#[derive(Clone, Default)]
+pub struct Output(Rc<Cell<bool>>);
+
+impl Output {
+    fn is_high(&self) -> bool {
+        self.0.get()
+    }
+}
+
+impl digital::ErrorType for Output {
+    type Error = Infallible;
+}
+
+impl OutputPin for Output {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.set(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.0.set(true);
+        Ok(())
+    }
+}
+
+/// The I2C bus of the simulated Arduino, with an MPU-6050.
+///
+/// The registers are read and written like on the real IMU, from the
+/// register written first in a transaction. It is cheap to clone, the
+/// clones share the same registers so the firmware reads what the model
+/// sets.
+#[derive(Clone)]
+pub struct ImuBus(Rc<RefCell<[u8; 128]>>);
+
+impl Default for ImuBus {
+    fn default() -> Self {
+        let mut registers = [0; 128];
+        registers[IMU_WHO_AM_I.0] = IMU_WHO_AM_I.1;
+        Self(Rc::new(RefCell::new(registers)))
+    }
+}
+
+impl ImuBus {
+    /// Sets the readings of the IMU, as raw values.
+    fn set_readings(&self, readings: [i16; 7]) {
+        let mut registers = self.0.borrow_mut();
+        for (i, value) in readings.iter().enumerate() {
+            let start = IMU_ACCEL_XOUT_H + 2 * i;
+            registers[start..start + 2].copy_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+impl embedded_hal::i2c::ErrorType for ImuBus {
+    type Error = ErrorKind;
+}
+
+impl I2c for ImuBus {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        if address != IMU_ADDRESS {
+            return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        }
+
+        let mut registers = self.0.borrow_mut();
+        let mut register = None;
+        for operation in operations {
+            match operation {
+                Operation::Write(bytes) => {
+                    for byte in bytes.iter() {
+                        // the first byte selects the register
+                        match &mut register {
+                            None => register = Some(usize::from(*byte) % registers.len()),
+                            Some(register) => {
+                                registers[*register] = *byte;
+                                *register = (*register + 1) % registers.len();
+                            }
+                        }
+                    }
+                }
+                Operation::Read(bytes) => {
+                    let register = register.get_or_insert(0);
+                    for byte in bytes.iter_mut() {
+                        *byte = registers[*register];
+                        *register = (*register + 1) % registers.len();
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The physical characteristics of the Xmaxx.
+#[derive(Debug, Clone)]
+pub struct Model {
+    /// The time constant of the motors' response to their driver (s).
+    pub motor_tau: f64,
+    /// The time constant of the response of a motor whose wheel slips,
+    /// turning only its wheel (s).
+    pub slip_tau: f64,
+    /// The time constant of the Xmaxx slowing down when no wheel drives it
+    /// (s).
+    pub coast_tau: f64,
+    /// The maximum rate of the steering servo (deg/s).
+    pub servo_rate: f64,
+    /// The angle of the front wheels per degree of the servo.
+    pub steering_ratio: f64,
+    /// The distance between the axles (m).
+    pub wheelbase: f64,
+    /// The distance between the left and right wheels (m).
+    pub track: f64,
+    /// The standard deviation of the noise on the speed sensors (analog
+    /// units).
+    pub adc_noise: f64,
+    /// The current drawn by a motor turning without load (A).
+    pub motor_friction: f64,
+    /// The current drawn by a motor to accelerate (A per RPM/s).
+    pub motor_inertia: f64,
+    /// The current drawn by a motor to hold a load, like a stalled wheel
+    /// (A).
+    pub motor_load: f64,
+    /// The voltage of the battery at rest when the simulation starts (V).
+    pub battery_voltage: f64,
+    /// The voltage of the battery at rest when it is full (V).
+    pub battery_full: f64,
+    /// The voltage of the battery at rest when it is empty (V).
+    pub battery_empty: f64,
+    /// The charge of the battery from full to empty (Ah).
+    pub battery_capacity: f64,
+    /// The internal resistance of the battery (Ω).
+    pub battery_resistance: f64,
+    /// The ticks of the wheel encoders in a revolution of a wheel.
+    pub encoder_resolution: i32,
+    /// The rate read by the gyroscopes at rest (deg/s).
+    pub gyro_bias: f64,
+    /// The standard deviation of the noise on the gyroscopes (deg/s).
+    pub gyro_noise: f64,
+    /// The standard deviation of the noise on the accelerometers (g).
+    pub accel_noise: f64,
+    /// The distance from the center of the rear axle forwards to the front
+    /// range sensor (m).
+    pub front_sensor: f64,
+    /// The distance from the center of the rear axle backwards to the rear
+    /// range sensor (m).
+    pub rear_sensor: f64,
+}
+
+impl Default for Model {
+    fn default() -> Self {
+        Self {
+            motor_tau: 0.15,
+            slip_tau: 0.03,
+            coast_tau: 2.0,
+            // 60° in 0.15 s, a usual servo
+            servo_rate: 400.0,
+            steering_ratio: STEERING_RATIO_10 as f64 / 10.0,
+            wheelbase: WHEELBASE as f64 / 1000.0,
+            track: 0.5,
+            adc_noise: 1.0,
+            motor_friction: 0.3,
+            motor_inertia: 0.0001,
+            motor_load: 0.0,
+            // a charged 8S LiPo, 4.2 V to 3.0 V a cell
+            battery_voltage: 33.6,
+            battery_full: 33.6,
+            battery_empty: 24.0,
+            battery_capacity: 5.0,
+            battery_resistance: 0.05,
+            encoder_resolution: ENCODER_RESOLUTION,
+            // typical of an MPU-6050
+            gyro_bias: 0.5,
+            gyro_noise: 0.05,
+            accel_noise: 0.004,
+            front_sensor: 0.65,
+            rear_sensor: 0.15,
+        }
+    }
+}
+
+/// The position of the Xmaxx in the plane.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pose {
+    /// (m)
+    pub x: f64,
+    /// (m)
+    pub y: f64,
+    /// Counterclockwise from the x axis (rad).
+    pub heading: f64,
+}
+
+/// A model of the Xmaxx driven by the PWM outputs of the firmware.
+///
+/// The motors follow their driver with a first-order response, the steering
+/// servo turns at a limited rate and the Xmaxx moves like a bicycle with
+/// Ackermann steering. The wheels on slippery ground spin up faster than
+/// the others and do not drive it, the others do not slip. The wheels of an
+/// axle whose drivers are disabled roll with the ground, and the Xmaxx
+/// coasts when no wheel drives it. The motors draw a current to overcome
+/// friction and their load and to accelerate. The speed and
+/// current sensors measure the motors, quantized by the ADC with some
+/// noise.
+///
+/// The wheel encoders count the turns of the wheels without noise.
+///
+/// The IMU, mounted flat at the center of the rear axle, measures the
+/// acceleration of the Xmaxx, its centripetal acceleration and gravity, and
+/// its yaw rate, with a bias and some noise.
+///
+/// The range sensors measure the distance to a wall across the x axis, if
+/// any, straight ahead and behind.
+///
+/// The voltage of the battery at rest falls linearly with the charge drawn
+/// by the motors, and sags with their current through its resistance.
+pub struct Vehicle {
+    pub steering: Pwm,
+    /// The motor drivers: fl, fr, rl, rr.
+    pub motors: [Pwm; 4],
+    /// The enable pins of the motor drivers: front and rear.
+    pub enables: [Output; 2],
+    pub imu: ImuBus,
+    /// The x of a wall across the x axis, as an obstacle (m).
+    pub wall: Option<f64>,
+    /// Whether the wheels are on slippery ground: fl, fr, rl, rr.
+    pub slippery: [bool; 4],
+    model: Model,
+    rng: StdRng,
+    /// The speed of the motors (RPM).
+    motor_rpm: [f64; 4],
+    /// The current drawn by the motors (A).
+    motor_current: [f64; 4],
+    /// The voltage of the battery at rest (V).
+    battery_voltage: f64,
+    /// The revolutions of the wheels: fl, fr, rl, rr.
+    wheel_turns: [f64; 4],
+    /// The angle of the steering servo (deg).
+    servo_angle: f64,
+    pose: Pose,
+    /// The speed of the center of the rear axle (m/s).
+    speed: f64,
+}
+
+impl Vehicle {
+    /// Returns a vehicle at rest at the origin, `seed` makes the noise
+    /// reproducible.
+    pub fn new(model: Model, seed: u64) -> Self {
+        Self {
+            battery_voltage: model.battery_voltage,
+            steering: Pwm::default(),
+            motors: Default::default(),
+            enables: Default::default(),
+            imu: ImuBus::default(),
+            wall: None,
+            slippery: [false; 4],
+            model,
+            rng: StdRng::seed_from_u64(seed),
+            motor_rpm: [0.0; 4],
+            motor_current: [0.0; 4],
+            wheel_turns: [0.0; 4],
+            servo_angle: STEERING_ZERO,
+            pose: Pose::default(),
+            speed: 0.0,
+        }
+    }
+
+    /// Moves the model forward by `dt` (s).
+    pub fn step(&mut self, dt: f64) {
+        let enabled = [0, 0, 1, 1].map(|axle| self.enables[axle].is_high());
+
+        // first-order response of the driven motors, faster without the load
+        // of the Xmaxx
+        let motors = self.motor_rpm.iter_mut().zip(&mut self.motor_current);
+        let wheels = self.slippery.into_iter().zip(enabled);
+        for (((rpm, current), motor), (slippery, enabled)) in motors.zip(&self.motors).zip(wheels) {
+            // a disabled driver neither drives nor brakes its motor
+            if !enabled {
+                *current = 0.0;
+                continue;
+            }
+            let tau = if slippery {
+                self.model.slip_tau
+            } else {
+                self.model.motor_tau
+            };
+            let alpha = 1.0 - (-dt / tau).exp();
+            let target = duty_to_rpm(motor.duty()).rpm() as f64;
+            let delta = (target - *rpm) * alpha;
+            *rpm += delta;
+
+            // the current has the sign of the torque, the load only pushes
+            // back while the motor is driven
+            let sign = |value: f64| {
+                if value.abs() < 1.0 {
+                    0.0
+                } else {
+                    value.signum()
+                }
+            };
+            *current = self.model.motor_friction * sign(*rpm)
+                + self.model.motor_load * sign(target)
+                + self.model.motor_inertia * delta / dt;
+        }
+
+        // the motors discharge the battery whichever way they turn
+        let drawn = self.total_current() * dt / 3600.0;
+        let range = self.model.battery_full - self.model.battery_empty;
+        self.battery_voltage -= drawn / self.model.battery_capacity * range;
+        self.battery_voltage = self.battery_voltage.max(self.model.battery_empty);
+
+        // the servo holds its angle without a signal
+        if self.steering.duty() != 0 {
+            let target = duty_to_angle(self.steering.duty()).degrees() as f64;
+            let max_delta = self.model.servo_rate * dt;
+            self.servo_angle += (target - self.servo_angle).clamp(-max_delta, max_delta);
+        }
+
+        // the driven wheels that grip do not slip, so the Xmaxx goes at their
+        // mean speed, and coasts without them
+        let wheel_speed =
+            |rpm: f64| rpm * 10.0 / GEARING_10 as f64 * 2.0 * PI / 60.0 * WHEEL_RADIUS as f64;
+        let gripping: Vec<f64> = self
+            .motor_rpm
+            .iter()
+            .zip(self.slippery.into_iter().zip(enabled))
+            .filter(|(_, (slippery, enabled))| !slippery && *enabled)
+            .map(|(rpm, _)| wheel_speed(*rpm))
+            .collect();
+        let speed = if gripping.is_empty() {
+            self.speed * (-dt / self.model.coast_tau).exp()
+        } else {
+            gripping.iter().sum::<f64>() / gripping.len() as f64
+        };
+
+        // the ground turns the undriven wheels that grip, the others spin down
+        let wheels = self.slippery.into_iter().zip(enabled);
+        for (rpm, (slippery, enabled)) in self.motor_rpm.iter_mut().zip(wheels) {
+            if enabled {
+                continue;
+            }
+            if slippery {
+                *rpm *= (-dt / self.model.motor_tau).exp();
+            } else {
+                *rpm = speed / wheel_speed(1.0);
+            }
+        }
+        for (turns, rpm) in self.wheel_turns.iter_mut().zip(self.motor_rpm) {
+            *turns += rpm * 10.0 / GEARING_10 as f64 / 60.0 * dt;
+        }
+
+        let acceleration = (speed - self.speed) / dt;
+        self.speed = speed;
+
+        // bicycle model around the center of the rear axle
+        let yaw_rate = self.speed * self.wheel_angle().tan() / self.model.wheelbase;
+        self.pose.x += self.speed * self.pose.heading.cos() * dt;
+        self.pose.y += self.speed * self.pose.heading.sin() * dt;
+        self.pose.heading = (self.pose.heading + yaw_rate * dt + PI).rem_euclid(2.0 * PI) - PI;
+
+        self.update_imu(acceleration, yaw_rate);
+    }
+
+    /// Sets the readings of the IMU from the acceleration (m/s²) and the
+    /// yaw rate (rad/s) of the Xmaxx.
+    fn update_imu(&mut self, acceleration: f64, yaw_rate: f64) {
+        let accel_noise =
+            Normal::new(0.0, self.model.accel_noise).expect("the noise should be positive");
+        let gyro_noise =
+            Normal::new(0.0, self.model.gyro_noise).expect("the noise should be positive");
+        let mut accel = |g: f64| {
+            let raw = (g + accel_noise.sample(&mut self.rng)) * IMU_LSB_PER_G;
+            raw.round().clamp(i16::MIN.into(), i16::MAX.into()) as i16
+        };
+        let accel = [
+            accel(acceleration / GRAVITY),
+            accel(self.speed * yaw_rate / GRAVITY),
+            accel(1.0),
+        ];
+        let mut gyro = |degrees_per_second: f64| {
+            let raw =
+                (degrees_per_second + self.model.gyro_bias + gyro_noise.sample(&mut self.rng))
+                    * IMU_LSB_PER_DEGREE_PER_SECOND;
+            raw.round().clamp(i16::MIN.into(), i16::MAX.into()) as i16
+        };
+        let gyro = [gyro(0.0), gyro(0.0), gyro(yaw_rate.to_degrees())];
+
+        // the temperature is not simulated
+        let [ax, ay, az] = accel;
+        let [gx, gy, gz] = gyro;
+        self.imu.set_readings([ax, ay, az, 0, gx, gy, gz]);
+    }
+
+    /// Returns the readings of the speed sensors: fl, fr, rl, rr.
+    pub fn analog_speeds(&mut self) -> [i32; 4] {
+        let noise = Normal::new(0.0, self.model.adc_noise).expect("the noise should be positive");
+        self.motor_rpm.map(|rpm| {
+            let analog = rpm * ANALOG as f64 / RPM_MAX.rpm() as f64
+                + ANALOG_ZERO_RPM as f64
+                + noise.sample(&mut self.rng);
+            (analog.round() as i32).clamp(0, ANALOG_MAX)
+        })
+    }
+
+    /// Returns the readings of the current sensors: fl, fr, rl, rr.
+    pub fn analog_currents(&mut self) -> [i32; 4] {
+        let noise = Normal::new(0.0, self.model.adc_noise).expect("the noise should be positive");
+        self.motor_current.map(|current| {
+            let analog = current * ANALOG as f64 / CURRENT_MAX.amps() as f64
+                + ANALOG_ZERO_CURRENT as f64
+                + noise.sample(&mut self.rng);
+            (analog.round() as i32).clamp(0, ANALOG_MAX)
+        })
+    }
+
+    /// Returns the reading of the battery voltage sensor.
+    pub fn analog_voltage(&mut self) -> i32 {
+        let noise = Normal::new(0.0, self.model.adc_noise).expect("the noise should be positive");
+        let voltage = self.battery_voltage - self.model.battery_resistance * self.total_current();
+        let analog = voltage * (ANALOG_MAX + 1) as f64 / BATTERY_MAX.volts() as f64
+            + noise.sample(&mut self.rng);
+        (analog.round() as i32).clamp(0, ANALOG_MAX)
+    }
+
+    /// Returns the counts of the wheel encoders: fl, fr, rl, rr.
+    pub fn encoder_ticks(&self) -> [i32; 4] {
+        let resolution = self.model.encoder_resolution as f64;
+        self.wheel_turns
+            .map(|turns| (turns * resolution).floor() as i64 as i32)
+    }
+
+    /// Returns the widths of the echoes of the range sensors: front and rear
+    /// (µs).
+    pub fn echoes(&self) -> [u32; 2] {
+        let cos = self.pose.heading.cos();
+        [self.model.front_sensor, -self.model.rear_sensor].map(|offset| {
+            let Some(wall) = self.wall else {
+                return NO_ECHO;
+            };
+            // along the heading from the sensor, backwards for the rear one
+            let x = self.pose.x + offset * cos;
+            let distance = (wall - x) / (offset.signum() * cos);
+            if distance >= 0.0 && distance <= RANGE_MAX.meters() as f64 {
+                (distance * 2.0 / SPEED_OF_SOUND * 1e6).round() as u32
+            } else {
+                NO_ECHO
+            }
+        })
+    }
+
+    /// Returns the current drawn from the battery by the motors (A).
+    fn total_current(&self) -> f64 {
+        self.motor_current.iter().map(|current| current.abs()).sum()
+    }
+
+    /// Returns the angle of the front wheels, positive to the left (rad).
+    ///
+    /// With Ackermann steering, the inner wheel turns more than the outer
+    /// one. This is the angle of the virtual wheel in the middle.
+    pub fn wheel_angle(&self) -> f64 {
+        ((STEERING_ZERO - self.servo_angle) * self.model.steering_ratio).to_radians()
+    }
+
+    /// Returns the angles of the left and right front wheels (rad).
+    pub fn ackermann_angles(&self) -> (f64, f64) {
+        let angle = self.wheel_angle();
+        if angle == 0.0 {
+            return (0.0, 0.0);
+        }
+
+        let radius = self.model.wheelbase / angle.tan();
+        let half_track = self.model.track / 2.0;
+        let left = (self.model.wheelbase / (radius - half_track)).atan();
+        let right = (self.model.wheelbase / (radius + half_track)).atan();
+        (left, right)
+    }
+
+    pub fn pose(&self) -> Pose {
+        self.pose
+    }
+
+    /// Returns the speed of the Xmaxx (m/s).
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+}
+
+/// Computes the speed that the motor driver holds for the duty cycle, the
+/// inverse of [`rpm_to_duty`].
+///
+/// The driver does not move without a signal, nor in its deadband around
+/// neutral, which also hides the rounding of the duty cycle.
+fn duty_to_rpm(duty: i32) -> CentiRpm {
+    if duty == 0 || (duty - MOTOR_DUTY_NUM_ZERO).abs() <= MOTOR_DEADBAND {
+        return CentiRpm(0);
+    }
+
+    let delta_duty = MOTOR_DUTY_NUM_MAX - MOTOR_DUTY_NUM_MIN;
+    let delta_rpm = RPM_MAX.0 - RPM_MIN.0;
+
+    CentiRpm((duty - MOTOR_DUTY_NUM_ZERO) * delta_rpm / delta_duty)
+}
+
+/// Computes the angle that the servo holds for the duty cycle, the inverse
+/// of [`angle_to_duty`].
+fn duty_to_angle(duty: i32) -> CentiDegrees {
+    let delta_duty = STEERING_DUTY_MAX - STEERING_DUTY_MIN;
+    let delta_angle = STEERING_ANGLE_MAX.0 - STEERING_ANGLE_MIN.0;
+
+    CentiDegrees((duty - STEERING_DUTY_MIN) * delta_angle / delta_duty + STEERING_ANGLE_MIN.0)
+}